@@ -0,0 +1,52 @@
+//! Capture events, save them as JSON, and render the saved trace later.
+//!
+//! ```sh
+//! cargo run --example serialize --features trace
+//! ```
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use unlock::{Events, Mutex};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    record("trace.json")?;
+
+    // This part would typically happen in a separate process, possibly on
+    // another machine.
+    let events: Events = serde_json::from_reader(BufReader::new(File::open("trace.json")?))?;
+    dbg!(events.len());
+    unlock::html::write("trace.html", &events)?;
+    Ok(())
+}
+
+/// Capture some contended lock activity and save it to `path`.
+fn record(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let lock = Arc::new(Mutex::new(Vec::new()));
+
+    unlock::capture();
+
+    let threads = (0..8)
+        .map(|n| {
+            let lock = lock.clone();
+
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    lock.lock().push(n);
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let events = unlock::drain();
+    serde_json::to_writer(BufWriter::new(File::create(path)?), &events)?;
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+use crate::event::Leave;
+use crate::{Event, Events, SortKey, ValidateError};
+
+impl Events {
+    /// Construct an empty collection with room for the given number of enter
+    /// and leave events.
+    ///
+    /// This is intended for assembling a collection incrementally, such as
+    /// when loading a large trace in chunks. Events are added with
+    /// [`push_enter`], [`push_leave`] or [`append`], after which
+    /// [`sort_and_validate`] must be called before the collection is
+    /// analyzed.
+    ///
+    /// [`push_enter`]: Events::push_enter
+    /// [`push_leave`]: Events::push_leave
+    /// [`append`]: Events::append
+    /// [`sort_and_validate`]: Events::sort_and_validate
+    pub fn with_capacity(enters: usize, leaves: usize) -> Self {
+        let mut events = Self::new();
+        events.enters.reserve(enters);
+        events.leaves.reserve(leaves);
+        events
+    }
+
+    /// Add an enter event to the collection.
+    ///
+    /// See [`Events::with_capacity`] for details.
+    pub fn push_enter(&mut self, event: Event) {
+        self.enters.push(event);
+    }
+
+    /// Add a leave event to the collection.
+    ///
+    /// See [`Events::with_capacity`] for details.
+    pub fn push_leave(&mut self, leave: Leave) {
+        self.leaves.push(leave);
+    }
+
+    /// Move all events of `other`, which was drained from the same capture,
+    /// into this collection.
+    ///
+    /// Unlike [`Events::merge`], identifiers and timestamps are kept as they
+    /// are, so this is used to put a capture which was split up back together
+    /// rather than to combine separate captures. See
+    /// [`Events::with_capacity`] for details.
+    pub fn append(&mut self, other: Events) {
+        self.enters.extend(other.enters);
+        self.leaves.extend(other.leaves);
+        self.dropped += other.dropped;
+        self.thread_index_conflicts += other.thread_index_conflicts;
+
+        for process in other.processes {
+            if !self.processes.iter().any(|p| p.id == process.id) {
+                self.processes.push(process);
+            }
+        }
+
+        self.creation_sites.extend(other.creation_sites);
+        self.keys.extend(other.keys);
+        for (process, names) in other.thread_names {
+            self.thread_names.entry(process).or_default().extend(names);
+        }
+    }
+
+    /// Sort an assembled collection the same way as [`drain`] does, and check
+    /// that its events are consistent.
+    ///
+    /// # Errors
+    ///
+    /// Errors if an identifier is used by more than one enter event, or if an
+    /// event refers to an enter event which is missing. The collection is
+    /// sorted but otherwise left as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let chunks = vec![unlock::drain(), unlock::drain()];
+    ///
+    /// let mut events = unlock::Events::with_capacity(0, 0);
+    ///
+    /// for chunk in chunks {
+    ///     events.append(chunk);
+    /// }
+    ///
+    /// events.sort_and_validate()?;
+    /// # Ok::<_, unlock::ValidateError>(())
+    /// ```
+    ///
+    /// [`drain`]: crate::drain
+    pub fn sort_and_validate(&mut self) -> Result<(), ValidateError> {
+        self.sort(SortKey::Id);
+
+        for pair in self.enters.windows(2) {
+            if pair[0].id == pair[1].id {
+                return Err(ValidateError::DuplicateEvent(pair[0].id));
+            }
+        }
+
+        let ids = self
+            .enters
+            .iter()
+            .map(|event| event.id)
+            .collect::<HashSet<_>>();
+
+        for event in &self.enters {
+            if let Some(parent) = event.parent {
+                if !ids.contains(&parent) {
+                    return Err(ValidateError::MissingParent(event.id, parent));
+                }
+            }
+        }
+
+        let mut closed = HashSet::with_capacity(self.leaves.len());
+
+        for leave in &self.leaves {
+            if !ids.contains(&leave.sibling) {
+                return Err(ValidateError::UnmatchedLeave(leave.sibling));
+            }
+
+            if !closed.insert(leave.sibling) {
+                return Err(ValidateError::DuplicateLeave(leave.sibling));
+            }
+        }
+
+        self.count_held();
+        Ok(())
+    }
+}
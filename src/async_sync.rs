@@ -0,0 +1,264 @@
+use std::any::type_name;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::AtomicU32;
+
+use super::event::{EventId, LockId, LockKind};
+use super::intern::{name, Name};
+use super::tracing_context::get;
+
+/// Wrapper for [`tokio::sync::Mutex<T>`].
+///
+/// Waiting for the lock is recorded across the `.await`, so the wait includes
+/// any time the task spent yielded to the scheduler.
+pub struct AsyncMutex<T> {
+    lock: LockId,
+    type_name: Name,
+    waiters: AtomicU32,
+    inner: tokio::sync::Mutex<T>,
+}
+
+impl<T> AsyncMutex<T> {
+    /// Create a new `AsyncMutex<T>`.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        let lock = LockId::next(LockKind::AsyncMutex);
+        get().created(lock);
+
+        Self {
+            lock,
+            type_name: Name::new(type_name::<T>()),
+            waiters: AtomicU32::new(0),
+            inner: tokio::sync::Mutex::new(value),
+        }
+    }
+
+    /// Create a new `AsyncMutex<T>` with a key which identifies it in captured
+    /// events.
+    ///
+    /// The index of a lock depends on the order in which locks are
+    /// constructed, which might vary between runs. The key on the other hand
+    /// is chosen by the user, so it can be used to identify the same lock
+    /// across separate runs and processes. See [`Events::lock_key`].
+    ///
+    /// This is only available if the `trace` feature is enabled, since the
+    /// lock types are otherwise re-exported as they are.
+    ///
+    /// [`Events::lock_key`]: crate::Events::lock_key
+    #[inline]
+    pub fn with_key(value: T, key: &'static str) -> Self {
+        let this = Self::new(value);
+        get().keyed(this.lock, key);
+        this
+    }
+
+    /// Lock the `AsyncMutex<T>`, waiting asynchronously until it's available.
+    #[inline]
+    pub async fn lock(&self) -> AsyncMutexGuard<'_, T> {
+        let (inner, event) = get()
+            .acquire_async(
+                self.lock,
+                name!("lock"),
+                self.type_name,
+                &self.waiters,
+                || self.inner.try_lock().ok(),
+                self.inner.lock(),
+            )
+            .await;
+
+        AsyncMutexGuard { inner, event }
+    }
+}
+
+impl<T> fmt::Debug for AsyncMutex<T>
+where
+    T: fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// Wrapper for [`tokio::sync::MutexGuard<T>`].
+///
+/// The guard can be held across `.await` points, so it might be dropped on a
+/// different thread than the one which acquired the lock. The leave event is
+/// then recorded on the releasing thread, while the critical section is still
+/// shown on the timeline of the thread which acquired it.
+pub struct AsyncMutexGuard<'a, T> {
+    inner: tokio::sync::MutexGuard<'a, T>,
+    event: Option<EventId>,
+}
+
+impl<T> Deref for AsyncMutexGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for AsyncMutexGuard<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<T> Drop for AsyncMutexGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        get().leave(self.event);
+    }
+}
+
+/// Wrapper for [`tokio::sync::RwLock<T>`].
+///
+/// Waiting for the lock is recorded across the `.await`, so the wait includes
+/// any time the task spent yielded to the scheduler.
+pub struct AsyncRwLock<T> {
+    lock: LockId,
+    type_name: Name,
+    waiters: AtomicU32,
+    inner: tokio::sync::RwLock<T>,
+}
+
+impl<T> AsyncRwLock<T> {
+    /// Create a new `AsyncRwLock<T>`.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        let lock = LockId::next(LockKind::AsyncRwLock);
+        get().created(lock);
+
+        Self {
+            lock,
+            type_name: Name::new(type_name::<T>()),
+            waiters: AtomicU32::new(0),
+            inner: tokio::sync::RwLock::new(value),
+        }
+    }
+
+    /// Create a new `AsyncRwLock<T>` with a key which identifies it in captured
+    /// events.
+    ///
+    /// The index of a lock depends on the order in which locks are
+    /// constructed, which might vary between runs. The key on the other hand
+    /// is chosen by the user, so it can be used to identify the same lock
+    /// across separate runs and processes. See [`Events::lock_key`].
+    ///
+    /// This is only available if the `trace` feature is enabled, since the
+    /// lock types are otherwise re-exported as they are.
+    ///
+    /// [`Events::lock_key`]: crate::Events::lock_key
+    #[inline]
+    pub fn with_key(value: T, key: &'static str) -> Self {
+        let this = Self::new(value);
+        get().keyed(this.lock, key);
+        this
+    }
+
+    /// Lock the `AsyncRwLock<T>` for reading, waiting asynchronously until
+    /// it's available.
+    #[inline]
+    pub async fn read(&self) -> AsyncRwLockReadGuard<'_, T> {
+        let (inner, event) = get()
+            .acquire_async(
+                self.lock,
+                name!("read"),
+                self.type_name,
+                &self.waiters,
+                || self.inner.try_read().ok(),
+                self.inner.read(),
+            )
+            .await;
+
+        AsyncRwLockReadGuard { inner, event }
+    }
+
+    /// Lock the `AsyncRwLock<T>` for writing, waiting asynchronously until
+    /// it's available.
+    #[inline]
+    pub async fn write(&self) -> AsyncRwLockWriteGuard<'_, T> {
+        let (inner, event) = get()
+            .acquire_async(
+                self.lock,
+                name!("write"),
+                self.type_name,
+                &self.waiters,
+                || self.inner.try_write().ok(),
+                self.inner.write(),
+            )
+            .await;
+
+        AsyncRwLockWriteGuard { inner, event }
+    }
+}
+
+impl<T> fmt::Debug for AsyncRwLock<T>
+where
+    T: fmt::Debug,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// Wrapper for [`tokio::sync::RwLockReadGuard<T>`].
+///
+/// See [`AsyncMutexGuard`] for how guards which are released on another
+/// thread are recorded.
+pub struct AsyncRwLockReadGuard<'a, T> {
+    inner: tokio::sync::RwLockReadGuard<'a, T>,
+    event: Option<EventId>,
+}
+
+impl<T> Deref for AsyncRwLockReadGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> Drop for AsyncRwLockReadGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        get().leave(self.event);
+    }
+}
+
+/// Wrapper for [`tokio::sync::RwLockWriteGuard<T>`].
+///
+/// See [`AsyncMutexGuard`] for how guards which are released on another
+/// thread are recorded.
+pub struct AsyncRwLockWriteGuard<'a, T> {
+    inner: tokio::sync::RwLockWriteGuard<'a, T>,
+    event: Option<EventId>,
+}
+
+impl<T> Deref for AsyncRwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for AsyncRwLockWriteGuard<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<T> Drop for AsyncRwLockWriteGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        get().leave(self.event);
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{EventBacktrace, Events, LockId};
+
+/// The number of frames used to identify a call site.
+const CALL_SITE_FRAMES: usize = 8;
+
+/// Frames which are part of the capture machinery and are skipped when
+/// identifying a call site.
+pub(crate) const SKIPPED_FRAMES: &[&str] = &["std::backtrace", "unlock::", "<unlock::"];
+
+/// A call site from which a lock was acquired.
+///
+/// This is identified by the top frames of the backtrace captured when the
+/// lock was acquired, excluding frames belonging to this crate.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallSite {
+    frames: Vec<Box<str>>,
+}
+
+impl CallSite {
+    /// Construct a call site from a captured backtrace.
+    pub(crate) fn from_backtrace(backtrace: &EventBacktrace) -> Self {
+        let mut frames = Vec::new();
+        let mut lines = backtrace.as_str().lines().peekable();
+
+        while let Some(line) = lines.next() {
+            let Some(symbol) = frame_symbol(line) else {
+                continue;
+            };
+
+            let location = lines
+                .next_if(|line| frame_symbol(line).is_none())
+                .and_then(|line| line.trim().strip_prefix("at "));
+
+            if SKIPPED_FRAMES.iter().any(|skip| symbol.starts_with(skip)) {
+                continue;
+            }
+
+            let frame = match location {
+                Some(location) => format!("{symbol} at {location}"),
+                None => symbol.to_owned(),
+            };
+
+            frames.push(frame.into());
+
+            if frames.len() == CALL_SITE_FRAMES {
+                break;
+            }
+        }
+
+        Self { frames }
+    }
+
+    /// Iterate over the frames identifying this call site, innermost first.
+    pub fn frames(&self) -> impl Iterator<Item = &str> {
+        self.frames.iter().map(|frame| frame.as_ref())
+    }
+}
+
+impl fmt::Display for CallSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut it = self.frames.iter();
+
+        if let Some(frame) = it.next() {
+            frame.fmt(f)?;
+        }
+
+        for frame in it {
+            writeln!(f)?;
+            frame.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Events {
+    /// Group the acquisitions of the given lock by the call site they
+    /// originated from.
+    ///
+    /// Only acquisitions which captured a backtrace are counted. The returned
+    /// call sites are ordered by the number of acquisitions, most frequent
+    /// first.
+    pub fn call_sites(&self, lock: LockId) -> Vec<(CallSite, usize)> {
+        let mut counts = HashMap::<_, usize>::new();
+
+        for enter in &self.enters {
+            if enter.lock != lock || enter.parent.is_some() {
+                continue;
+            }
+
+            if let Some(backtrace) = enter.backtrace() {
+                *counts
+                    .entry(CallSite::from_backtrace(backtrace))
+                    .or_default() += 1;
+            }
+        }
+
+        let mut call_sites = counts.into_iter().collect::<Vec<_>>();
+        call_sites.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.frames.cmp(&b.0.frames)));
+        call_sites
+    }
+}
+
+/// Parse the symbol out of a backtrace line if it is the start of a frame,
+/// like `   0: symbol`.
+pub(crate) fn frame_symbol(line: &str) -> Option<&str> {
+    let (index, symbol) = line.trim_start().split_once(": ")?;
+
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(symbol)
+}
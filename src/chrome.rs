@@ -0,0 +1,152 @@
+//! Module to write captured lock events in the Chrome trace event format.
+//!
+//! The written file can be loaded into [Perfetto] or `chrome://tracing`. Each
+//! enter and leave event is written as a `B` and an `E` event on the timeline
+//! of the thread which entered it, named after the type wrapped by the lock.
+//! This includes the guards of async locks, which might be released on a
+//! different thread than they were acquired on.
+//!
+//! Note that the format expects the events of a thread to be properly nested.
+//! Locks which are released in a different order than they were acquired are
+//! therefore shown as being held for longer than they were.
+//!
+//! [Perfetto]: https://ui.perfetto.dev
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::json::write_str;
+use crate::{Error, Event, Events};
+
+/// A single trace event to write.
+struct Record<'a> {
+    timestamp: u64,
+    thread_index: usize,
+    /// The order of events recorded at the same timestamp.
+    order: (u8, usize),
+    /// The enter event, which this record either enters or leaves.
+    event: &'a Event,
+    leave: bool,
+}
+
+/// Write events in the Chrome trace event format to the given path.
+///
+/// # Errors
+///
+/// If there are no events to write, such as when [`capture`] was never
+/// called, this returns [`Error::EmptyTrace`] without writing anything.
+///
+/// ```
+/// let events = unlock::drain();
+/// let result = unlock::chrome::write("trace.json", &events);
+/// assert!(matches!(result, Err(unlock::Error::EmptyTrace)));
+/// ```
+///
+/// [`capture`]: crate::capture
+pub fn write<P>(path: P, events: &Events) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    if events.is_empty() {
+        return Err(Error::EmptyTrace);
+    }
+
+    let mut out = BufWriter::new(File::create(path)?);
+    write_to(&mut out, events)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn write_to<W>(out: &mut W, events: &Events) -> io::Result<()>
+where
+    W: io::Write,
+{
+    let enters = events
+        .enters
+        .iter()
+        .map(|event| (event.id, event))
+        .collect::<HashMap<_, _>>();
+
+    let mut records = Vec::with_capacity(events.enters.len() + events.leaves.len());
+
+    for event in &events.enters {
+        records.push(Record {
+            timestamp: event.timestamp,
+            thread_index: event.thread_index,
+            order: (1, event.id.get()),
+            event,
+            leave: false,
+        });
+    }
+
+    for leave in &events.leaves {
+        let Some(event) = enters.get(&leave.sibling).copied() else {
+            continue;
+        };
+
+        // Leaves at the same timestamp are written before enters so that
+        // adjacent sections don't overlap, unless the section is empty in
+        // which case it has to be entered first. Nested sections are left
+        // before their parents.
+        let group = if leave.timestamp == event.timestamp {
+            2
+        } else {
+            0
+        };
+
+        records.push(Record {
+            timestamp: leave.timestamp,
+            // The section is left on the timeline it was entered on, even if
+            // it was released by another thread.
+            thread_index: event.thread_index,
+            order: (group, usize::MAX - leave.sibling.get()),
+            event,
+            leave: true,
+        });
+    }
+
+    records.sort_by(|a, b| {
+        (a.timestamp, a.thread_index, a.order).cmp(&(b.timestamp, b.thread_index, b.order))
+    });
+
+    write!(out, "{{\"traceEvents\":[")?;
+
+    for (n, record) in records.iter().enumerate() {
+        if n > 0 {
+            write!(out, ",")?;
+        }
+
+        let event = record.event;
+        let phase = if record.leave { "E" } else { "B" };
+
+        write!(out, "{{\"name\":")?;
+        write_str(out, event.type_name())?;
+        write!(out, ",\"cat\":")?;
+        write_str(out, event.name())?;
+
+        write!(
+            out,
+            ",\"ph\":\"{phase}\",\"ts\":{}.{:03},\"pid\":{},\"tid\":{}",
+            record.timestamp / 1000,
+            record.timestamp % 1000,
+            event.process,
+            record.thread_index,
+        )?;
+
+        if !record.leave {
+            write!(
+                out,
+                ",\"args\":{{\"lock\":{},\"event\":{}}}",
+                event.lock.index(),
+                event.id
+            )?;
+        }
+
+        write!(out, "}}")?;
+    }
+
+    write!(out, "],\"displayTimeUnit\":\"ns\"}}")?;
+    Ok(())
+}
@@ -0,0 +1,333 @@
+use std::time::Duration;
+
+/// Configuration used when capturing events.
+///
+/// This is passed to [`capture_with`][crate::capture_with] to start a capture.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let config = unlock::CaptureConfig::new().contended_only(Duration::from_micros(10));
+/// unlock::capture_with(config);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    pub(crate) contended_only: Option<Duration>,
+    pub(crate) max_events: Option<usize>,
+    pub(crate) ring_buffer: bool,
+    pub(crate) sample_one_in: u32,
+    pub(crate) creation_sites: bool,
+    pub(crate) resolution: Duration,
+    pub(crate) record_reads: bool,
+    pub(crate) record_writes: bool,
+    pub(crate) record_mutexes: bool,
+    pub(crate) record_failed_tries: bool,
+    pub(crate) backtraces: bool,
+    pub(crate) contended_backtraces: bool,
+    pub(crate) max_backtrace_len: Option<usize>,
+    pub(crate) stack_depth: bool,
+}
+
+impl CaptureConfig {
+    /// Construct the default capture configuration.
+    ///
+    /// This is the configuration used by [`capture`][crate::capture].
+    pub const fn new() -> Self {
+        Self {
+            contended_only: None,
+            max_events: None,
+            ring_buffer: false,
+            sample_one_in: 1,
+            creation_sites: false,
+            resolution: Duration::from_nanos(1),
+            record_reads: true,
+            record_writes: true,
+            record_mutexes: true,
+            record_failed_tries: false,
+            backtraces: true,
+            contended_backtraces: false,
+            max_backtrace_len: None,
+            stack_depth: false,
+        }
+    }
+
+    /// Only record acquisitions which were contended and had to wait at least
+    /// `threshold` to acquire the lock.
+    ///
+    /// Uncontended acquisitions take a fast path which doesn't record any
+    /// events, which drastically cuts down on the number of events recorded
+    /// for workloads which rarely contend.
+    pub const fn contended_only(mut self, threshold: Duration) -> Self {
+        self.contended_only = Some(threshold);
+        self
+    }
+
+    /// Limit the number of enter events buffered for each thread to `max`.
+    ///
+    /// Once the limit is reached, further lock acquisitions on that thread are
+    /// not recorded and are instead counted as dropped, which is reported
+    /// through [`Events::dropped`][crate::Events::dropped]. Note that threads
+    /// might share buffers.
+    pub const fn max_events(mut self, max: usize) -> Self {
+        self.max_events = Some(max);
+        self
+    }
+
+    /// Overwrite the oldest events once the limit set through [`max_events`]
+    /// is reached, rather than dropping new ones.
+    ///
+    /// This keeps the memory used by a long-running capture bounded while
+    /// always retaining its most recent events, so that the capture can be
+    /// drained once something interesting has happened. Overwritten events
+    /// are counted as dropped. Critical sections whose enter event was
+    /// overwritten are left out, while those which were entered but whose
+    /// leave event wasn't recorded yet are shown as never released.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config = unlock::CaptureConfig::new()
+    ///     .max_events(100_000)
+    ///     .ring_buffer(true);
+    ///
+    /// unlock::capture_with(config);
+    /// ```
+    ///
+    /// [`max_events`]: Self::max_events
+    pub const fn ring_buffer(mut self, enabled: bool) -> Self {
+        self.ring_buffer = enabled;
+        self
+    }
+
+    /// Only record a random sample of one in every `n` lock operations.
+    ///
+    /// Whether an operation is sampled is decided before anything else is
+    /// done for it, so operations which aren't sampled are about as cheap as
+    /// when nothing is being captured. Everything recorded for a sampled
+    /// operation is kept together, up until the lock is released. This is
+    /// useful for always-on captures in production where the overhead of
+    /// recording every operation can't be afforded, while still catching
+    /// locks which are persistently contended.
+    ///
+    /// A value of `0` or `1` records every operation, which is the default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config = unlock::CaptureConfig::new().sample_one_in(100);
+    /// unlock::capture_with(config);
+    /// ```
+    pub const fn sample_one_in(mut self, n: u32) -> Self {
+        self.sample_one_in = n;
+        self
+    }
+
+    /// Capture a backtrace whenever a lock is constructed, which can be
+    /// retrieved through
+    /// [`Events::lock_creation_site`][crate::Events::lock_creation_site].
+    ///
+    /// Like other backtraces, these are only captured if `RUST_BACKTRACE=1` or
+    /// `RUST_LIB_BACKTRACE=1` is set. This only affects locks constructed
+    /// after the capture has been
+    /// started, so to cover long-lived locks the capture has to be started
+    /// early. It stays in effect until the next capture is started.
+    pub const fn creation_sites(mut self, enabled: bool) -> Self {
+        self.creation_sites = enabled;
+        self
+    }
+
+    /// Record timestamps with the given resolution instead of in nanoseconds.
+    ///
+    /// Timestamps are rounded down to a multiple of `resolution`, so drained
+    /// events report times which are exact multiples of it. For long captures
+    /// where sub-microsecond precision isn't needed this makes serialized
+    /// traces compress better, since they no longer carry noise in the low
+    /// digits, and makes captures easier to compare. Whether a lock was
+    /// contended for at least the [`contended_only`] threshold is still
+    /// decided using full precision.
+    ///
+    /// Note that this only affects the values of timestamps. They are still
+    /// stored as full nanosecond timestamps, so this doesn't reduce the memory
+    /// used by a capture.
+    ///
+    /// A zero resolution is treated as one nanosecond, which is the default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let resolution = Duration::from_micros(100);
+    /// unlock::capture_with(unlock::CaptureConfig::new().resolution(resolution));
+    ///
+    /// let mutex = unlock::Mutex::new(0);
+    /// *mutex.lock() += 1;
+    ///
+    /// for event in unlock::drain().enters() {
+    ///     assert_eq!(event.timestamp().as_nanos() % resolution.as_nanos(), 0);
+    /// }
+    /// ```
+    ///
+    /// [`contended_only`]: Self::contended_only
+    pub const fn resolution(mut self, resolution: Duration) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Set whether read acquisitions of [`RwLock`][crate::RwLock] are
+    /// recorded.
+    ///
+    /// This covers [`read`], [`try_read`], [`read_recursive`] and
+    /// [`upgradable_read`], along with the upgrades through [`with_upgraded`]
+    /// nested in an upgradable read. Acquisitions which aren't recorded don't produce any events, so
+    /// disabling the kinds of acquisitions which aren't under investigation is
+    /// an effective way to reduce the volume of a capture.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Only investigate contention between writers.
+    /// let config = unlock::CaptureConfig::new()
+    ///     .record_reads(false)
+    ///     .record_mutexes(false);
+    ///
+    /// unlock::capture_with(config);
+    /// ```
+    ///
+    /// [`read`]: crate::RwLock::read
+    /// [`try_read`]: crate::RwLock::try_read
+    /// [`read_recursive`]: crate::RwLock::read_recursive
+    /// [`upgradable_read`]: crate::RwLock::upgradable_read
+    /// [`with_upgraded`]: crate::RwLockUpgradableReadGuard::with_upgraded
+    pub const fn record_reads(mut self, enabled: bool) -> Self {
+        self.record_reads = enabled;
+        self
+    }
+
+    /// Set whether write acquisitions of [`RwLock`][crate::RwLock] are
+    /// recorded, including [`try_write`][crate::RwLock::try_write] and upgrades
+    /// through [`upgrade`][crate::RwLockUpgradableReadGuard::upgrade].
+    ///
+    /// Upgrades through
+    /// [`with_upgraded`][crate::RwLockUpgradableReadGuard::with_upgraded] are
+    /// recorded nested in their upgradable read instead, so they're only
+    /// recorded if both reads and writes are.
+    ///
+    /// Defaults to `true`.
+    pub const fn record_writes(mut self, enabled: bool) -> Self {
+        self.record_writes = enabled;
+        self
+    }
+
+    /// Set whether acquisitions of [`Mutex`][crate::Mutex] are recorded.
+    ///
+    /// Defaults to `true`.
+    pub const fn record_mutexes(mut self, enabled: bool) -> Self {
+        self.record_mutexes = enabled;
+        self
+    }
+
+    /// Set whether failed attempts to acquire a lock without blocking, such
+    /// as through [`Mutex::try_lock`], are recorded.
+    ///
+    /// Failed attempts are recorded as critical sections which hold nothing,
+    /// with the [`Outcome::TryFailed`] outcome. This is useful to see how
+    /// often code which probes a lock finds it held, but code which probes in
+    /// a loop can produce a lot of events. When disabled, a failed attempt
+    /// doesn't allocate or record anything.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config = unlock::CaptureConfig::new().record_failed_tries(true);
+    /// unlock::capture_with(config);
+    /// ```
+    ///
+    /// [`Mutex::try_lock`]: crate::Mutex::try_lock
+    /// [`Outcome::TryFailed`]: crate::Outcome::TryFailed
+    pub const fn record_failed_tries(mut self, enabled: bool) -> Self {
+        self.record_failed_tries = enabled;
+        self
+    }
+
+    /// Set whether backtraces are captured for acquisitions.
+    ///
+    /// Capturing a backtrace is expensive even if backtraces end up not being
+    /// enabled through the environment, so disabling them entirely is
+    /// worthwhile for captures of frequently acquired locks. When disabled,
+    /// [`Event::backtrace`] is always `None`. This doesn't affect
+    /// [`creation_sites`].
+    ///
+    /// Defaults to `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config = unlock::CaptureConfig::new().backtraces(false);
+    /// unlock::capture_with(config);
+    /// ```
+    ///
+    /// [`Event::backtrace`]: crate::Event::backtrace
+    /// [`creation_sites`]: Self::creation_sites
+    pub const fn backtraces(mut self, enabled: bool) -> Self {
+        self.backtraces = enabled;
+        self
+    }
+
+    /// Only capture backtraces for acquisitions which were contended.
+    ///
+    /// Uncontended acquisitions are still recorded, but skip the cost of
+    /// capturing a backtrace, which dominates the overhead of recording for
+    /// most workloads. Whether an acquisition was contended is reported
+    /// through [`Event::contended`].
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`Event::contended`]: crate::Event::contended
+    pub const fn contended_backtraces(mut self, enabled: bool) -> Self {
+        self.contended_backtraces = enabled;
+        self
+    }
+
+    /// Limit the length of each captured backtrace to `max` bytes.
+    ///
+    /// Backtraces of deeply nested code, such as async tasks, can be enormous
+    /// and dominate the memory used by a capture. Longer backtraces are cut
+    /// before the first frame which doesn't fit, and end with a `...` line to
+    /// indicate that they were truncated.
+    pub const fn max_backtrace_len(mut self, max: usize) -> Self {
+        self.max_backtrace_len = Some(max);
+        self
+    }
+
+    /// Set whether the approximate stack depth of acquisitions is recorded.
+    ///
+    /// This is a cheap alternative to capturing backtraces, which is available
+    /// regardless of whether backtraces are enabled. It can be used to tell
+    /// apart acquisitions made from different contexts in captures which are
+    /// too large to afford backtraces. See [`Event::stack_depth`] for what is
+    /// recorded.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`Event::stack_depth`]: crate::Event::stack_depth
+    pub const fn stack_depth(mut self, enabled: bool) -> Self {
+        self.stack_depth = enabled;
+        self
+    }
+}
+
+impl Default for CaptureConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::{Event, Events, LockId};
+
+/// A potential deadlock, in the form of a cycle of locks which have been
+/// acquired in conflicting orders.
+///
+/// Returned by [`Events::deadlock_report`].
+#[derive(Debug, Clone)]
+pub struct DeadlockCycle<'a> {
+    edges: Vec<DeadlockEdge<'a>>,
+}
+
+impl<'a> DeadlockCycle<'a> {
+    /// The locks participating in the cycle, in the order in which they are
+    /// acquired along the cycle.
+    pub fn locks(&self) -> impl Iterator<Item = LockId> + '_ {
+        self.edges.iter().map(|edge| edge.held.lock)
+    }
+
+    /// The edges making up the cycle.
+    ///
+    /// Each edge acquires the lock held by the next one, and the last edge
+    /// acquires the lock held by the first one.
+    pub fn edges(&self) -> &[DeadlockEdge<'a>] {
+        &self.edges
+    }
+}
+
+/// A lock which was acquired while another lock was held by the same thread.
+#[derive(Debug, Clone, Copy)]
+pub struct DeadlockEdge<'a> {
+    held: &'a Event,
+    acquired: &'a Event,
+}
+
+impl<'a> DeadlockEdge<'a> {
+    /// The event of the acquisition of the lock which was held.
+    ///
+    /// Its [`backtrace`][Event::backtrace] points to where the held lock was
+    /// acquired.
+    pub fn held(&self) -> &'a Event {
+        self.held
+    }
+
+    /// The event of the acquisition which happened while the other lock was
+    /// held.
+    ///
+    /// Its [`backtrace`][Event::backtrace] points to the code which acquired
+    /// the lock in a conflicting order.
+    pub fn acquired(&self) -> &'a Event {
+        self.acquired
+    }
+}
+
+impl Events {
+    /// Detect potential deadlocks by finding cycles in the order in which
+    /// locks are acquired.
+    ///
+    /// A lock is considered to be acquired while holding another lock if it
+    /// was requested by the same thread while the critical section of the
+    /// other lock was open. If locks are ever acquired in orders which form a
+    /// cycle, the program can deadlock even if it didn't during the capture.
+    ///
+    /// Each edge in a reported cycle refers to the first acquisition which
+    /// established it, so that backtraces captured for them lead straight to
+    /// the offending code.
+    pub fn deadlock_report(&self) -> Vec<DeadlockCycle<'_>> {
+        let edges = self.held_while_acquiring();
+        let graph = graph(&edges);
+
+        let mut cycles = Vec::new();
+
+        for component in strongly_connected(&graph) {
+            if component.len() < 2 {
+                continue;
+            }
+
+            let Some(path) = shortest_cycle(&graph, &component) else {
+                continue;
+            };
+
+            let edges = path
+                .windows(2)
+                .map(|w| {
+                    let (held, acquired) = edges[&(w[0], w[1])];
+                    DeadlockEdge { held, acquired }
+                })
+                .collect();
+
+            cycles.push(DeadlockCycle { edges });
+        }
+
+        cycles
+    }
+
+    /// Build the graph of the order in which locks are acquired.
+    ///
+    /// Every lock maps to the locks which were acquired while it was held by
+    /// the same thread, as described in [`Events::deadlock_report`]. Locks
+    /// which were acquired in both orders by different threads point to each
+    /// other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let events = unlock::drain();
+    ///
+    /// for (held, acquired) in events.lock_order_graph() {
+    ///     println!("{held:?} held while acquiring {acquired:?}");
+    /// }
+    /// ```
+    pub fn lock_order_graph(&self) -> BTreeMap<LockId, BTreeSet<LockId>> {
+        graph(&self.held_while_acquiring())
+    }
+
+    /// Find the cycles in the [order in which locks are acquired], which
+    /// could lead to deadlocks.
+    ///
+    /// Each cycle lists the locks in the order in which they are acquired
+    /// along it. See [`Events::deadlock_report`] for the acquisitions which
+    /// established each cycle.
+    ///
+    /// [order in which locks are acquired]: Events::lock_order_graph
+    pub fn deadlock_cycles(&self) -> Vec<Vec<LockId>> {
+        self.deadlock_report()
+            .iter()
+            .map(|cycle| cycle.locks().collect())
+            .collect()
+    }
+
+    /// Collect the earliest acquisition for every pair of locks where the
+    /// second was acquired while the first was held.
+    fn held_while_acquiring(&self) -> BTreeMap<(LockId, LockId), (&Event, &Event)> {
+        let closes = self.closes();
+
+        let mut threads = BTreeMap::<_, Vec<_>>::new();
+
+        for event in self.enters.iter().filter(|e| e.parent.is_none()) {
+            threads.entry(event.thread_index).or_default().push(event);
+        }
+
+        let mut edges = BTreeMap::<_, (&Event, &Event)>::new();
+
+        for (_, mut events) in threads {
+            events.sort_by_key(|e| (e.timestamp, e.id));
+
+            let mut held = Vec::<(u64, &Event)>::new();
+
+            for event in events {
+                held.retain(|(close, _)| *close > event.timestamp);
+
+                for &(_, h) in &held {
+                    if h.lock == event.lock {
+                        continue;
+                    }
+
+                    let edge = edges.entry((h.lock, event.lock)).or_insert((h, event));
+
+                    if (event.timestamp, event.id) < (edge.1.timestamp, edge.1.id) {
+                        *edge = (h, event);
+                    }
+                }
+
+                let close = closes.get(&event.id).copied().unwrap_or(u64::MAX);
+                held.push((close, event));
+            }
+        }
+
+        edges
+    }
+}
+
+/// Construct a graph from every lock to the locks acquired while it was held.
+fn graph(
+    edges: &BTreeMap<(LockId, LockId), (&Event, &Event)>,
+) -> BTreeMap<LockId, BTreeSet<LockId>> {
+    let mut graph = BTreeMap::<_, BTreeSet<_>>::new();
+
+    for &(from, to) in edges.keys() {
+        graph.entry(from).or_default().insert(to);
+        graph.entry(to).or_default();
+    }
+
+    graph
+}
+
+/// Find the strongly connected components of the graph using Tarjan's
+/// algorithm.
+fn strongly_connected(graph: &BTreeMap<LockId, BTreeSet<LockId>>) -> Vec<Vec<LockId>> {
+    struct State<'a> {
+        graph: &'a BTreeMap<LockId, BTreeSet<LockId>>,
+        index: usize,
+        indexes: HashMap<LockId, (usize, usize)>,
+        stack: Vec<LockId>,
+        on_stack: BTreeSet<LockId>,
+        components: Vec<Vec<LockId>>,
+    }
+
+    fn visit(state: &mut State<'_>, node: LockId) {
+        let index = state.index;
+        state.index += 1;
+        state.indexes.insert(node, (index, index));
+        state.stack.push(node);
+        state.on_stack.insert(node);
+
+        for &next in &state.graph[&node] {
+            let low = match state.indexes.get(&next) {
+                None => {
+                    visit(state, next);
+                    state.indexes[&next].1
+                }
+                Some(&(next_index, _)) if state.on_stack.contains(&next) => next_index,
+                Some(..) => continue,
+            };
+
+            let entry = state.indexes.get_mut(&node).expect("visited node");
+            entry.1 = entry.1.min(low);
+        }
+
+        let (index, low) = state.indexes[&node];
+
+        if index == low {
+            let mut component = Vec::new();
+
+            while let Some(other) = state.stack.pop() {
+                state.on_stack.remove(&other);
+                component.push(other);
+
+                if other == node {
+                    break;
+                }
+            }
+
+            component.sort();
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        graph,
+        index: 0,
+        indexes: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        components: Vec::new(),
+    };
+
+    for &node in graph.keys() {
+        if !state.indexes.contains_key(&node) {
+            visit(&mut state, node);
+        }
+    }
+
+    state.components.sort();
+    state.components
+}
+
+/// Find the shortest cycle through the first node of a strongly connected
+/// component, returning the nodes along it with the first node repeated at
+/// the end.
+fn shortest_cycle(
+    graph: &BTreeMap<LockId, BTreeSet<LockId>>,
+    component: &[LockId],
+) -> Option<Vec<LockId>> {
+    let start = *component.first()?;
+    let mut previous = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(node) = queue.pop_front() {
+        for &next in &graph[&node] {
+            if component.binary_search(&next).is_err() {
+                continue;
+            }
+
+            if next == start {
+                let mut path = vec![start, node];
+                let mut current = node;
+
+                while let Some(&prev) = previous.get(&current) {
+                    path.push(prev);
+                    current = prev;
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            if previous.contains_key(&next) {
+                continue;
+            }
+
+            previous.insert(next, node);
+            queue.push_back(next);
+        }
+    }
+
+    None
+}
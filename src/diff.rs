@@ -0,0 +1,231 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io;
+use std::time::Duration;
+
+use crate::{Events, LockId, Summary};
+
+/// The percentile of hold times compared in a [`Diff`].
+const HOLD_PERCENTILE: f64 = 99.0;
+
+/// The key, type name and ordinal used to match a lock between captures.
+type Identity = (Option<&'static str>, &'static str, usize);
+
+/// A comparison of the locks in two captures.
+///
+/// Returned by [`Events::diff`].
+#[derive(Debug, Clone)]
+pub struct Diff {
+    locks: Vec<LockDiff>,
+}
+
+impl Diff {
+    /// The compared locks, ordered by key, type name and ordinal.
+    pub fn locks(&self) -> &[LockDiff] {
+        &self.locks
+    }
+
+    /// Write the comparison as a text report to the given output.
+    ///
+    /// If `color` is set, regressions are highlighted in red and improvements
+    /// in green using ANSI escape codes.
+    pub fn write<W>(&self, mut out: W, color: bool) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        for lock in &self.locks {
+            let type_name = lock.type_name;
+            let ordinal = lock.ordinal;
+
+            match lock.key {
+                Some(key) => writeln!(out, "{key} ({type_name})")?,
+                None => writeln!(out, "{type_name} #{ordinal}")?,
+            }
+
+            let (before, after) = lock.acquisitions;
+            let delta = after as i128 - before as i128;
+            writeln!(out, "  acquisitions: {before} -> {after} ({delta:+})")?;
+
+            let (before, after) = lock.total_wait;
+            let change = paint(color, before, after);
+            writeln!(out, "  total wait:   {before:?} -> {after:?} ({change})")?;
+
+            let (before, after) = lock.p99_hold;
+            let change = paint(color, before.unwrap_or_default(), after.unwrap_or_default());
+            writeln!(out, "  p99 hold:     {before:?} -> {after:?} ({change})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The comparison of a single lock between two captures.
+///
+/// Locks are matched by the type they wrap and their ordinal among the locks
+/// wrapping the same type which were used in the capture, in the order in
+/// which they were constructed. This matches the same locks across separate
+/// runs of a program, as long as they are constructed and used in the same
+/// order.
+///
+/// Locks which were constructed with a key, such as through
+/// [`Mutex::with_key`], are instead matched by their key, which doesn't depend
+/// on the order in which they were constructed.
+///
+/// [`Mutex::with_key`]: crate::Mutex::with_key
+#[derive(Debug, Clone)]
+pub struct LockDiff {
+    key: Option<&'static str>,
+    type_name: &'static str,
+    ordinal: usize,
+    baseline: Option<LockId>,
+    lock: Option<LockId>,
+    acquisitions: (usize, usize),
+    total_wait: (Duration, Duration),
+    p99_hold: (Option<Duration>, Option<Duration>),
+}
+
+impl LockDiff {
+    /// The key of the lock, if it was constructed with one.
+    pub fn key(&self) -> Option<&'static str> {
+        self.key
+    }
+
+    /// The name of the type wrapped by the lock.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The ordinal of the lock among locks wrapping the same type and with
+    /// the same key.
+    pub fn ordinal(&self) -> usize {
+        self.ordinal
+    }
+
+    /// The lock in the baseline, if it was used there.
+    pub fn baseline_lock(&self) -> Option<LockId> {
+        self.baseline
+    }
+
+    /// The lock in the compared capture, if it was used there.
+    pub fn lock(&self) -> Option<LockId> {
+        self.lock
+    }
+
+    /// The number of acquisitions in the baseline and the compared capture.
+    pub fn acquisitions(&self) -> (usize, usize) {
+        self.acquisitions
+    }
+
+    /// The total time spent waiting for the lock in the baseline and the
+    /// compared capture.
+    pub fn total_wait(&self) -> (Duration, Duration) {
+        self.total_wait
+    }
+
+    /// The 99th percentile of hold times in the baseline and the compared
+    /// capture.
+    pub fn p99_hold(&self) -> (Option<Duration>, Option<Duration>) {
+        self.p99_hold
+    }
+}
+
+impl Events {
+    /// Compare this capture against a `baseline`, such as one captured before
+    /// an optimization.
+    ///
+    /// See [`LockDiff`] for how locks are matched between captures.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let baseline = unlock::drain();
+    /// let events = unlock::drain();
+    ///
+    /// let diff = events.diff(&baseline);
+    /// diff.write(std::io::stdout(), false)?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn diff(&self, baseline: &Events) -> Diff {
+        let before = baseline.summary();
+        let after = self.summary();
+
+        let mut keys = BTreeMap::<_, (Option<LockId>, Option<LockId>)>::new();
+
+        for (key, lock) in baseline.ordinals() {
+            keys.entry(key).or_default().0 = Some(lock);
+        }
+
+        for (key, lock) in self.ordinals() {
+            keys.entry(key).or_default().1 = Some(lock);
+        }
+
+        let locks = keys
+            .into_iter()
+            .map(|((key, type_name, ordinal), (baseline, lock))| {
+                let stats = |summary: &Summary, lock: Option<LockId>| match lock {
+                    Some(lock) => (
+                        summary.hold_count(lock),
+                        summary.wait_total(lock),
+                        summary.hold_percentile(lock, HOLD_PERCENTILE),
+                    ),
+                    None => (0, Duration::ZERO, None),
+                };
+
+                let (count_before, wait_before, hold_before) = stats(&before, baseline);
+                let (count_after, wait_after, hold_after) = stats(&after, lock);
+
+                LockDiff {
+                    key,
+                    type_name,
+                    ordinal,
+                    baseline,
+                    lock,
+                    acquisitions: (count_before, count_after),
+                    total_wait: (wait_before, wait_after),
+                    p99_hold: (hold_before, hold_after),
+                }
+            })
+            .collect();
+
+        Diff { locks }
+    }
+
+    /// Identify every lock by its key, the type it wraps and its ordinal
+    /// among locks with the same key wrapping the same type.
+    fn ordinals(&self) -> Vec<(Identity, LockId)> {
+        let mut locks = BTreeMap::new();
+
+        for event in &self.enters {
+            locks
+                .entry(event.lock)
+                .or_insert_with(|| (self.lock_key(event.lock), event.type_name()));
+        }
+
+        let mut counts = BTreeMap::<_, usize>::new();
+        let mut output = Vec::with_capacity(locks.len());
+
+        for (lock, (key, type_name)) in locks {
+            let ordinal = counts.entry((key, type_name)).or_default();
+            output.push(((key, type_name, *ordinal), lock));
+            *ordinal += 1;
+        }
+
+        output
+    }
+}
+
+/// Describe the change from `before` to `after`, where an increase is a
+/// regression.
+fn paint(color: bool, before: Duration, after: Duration) -> String {
+    let (sign, delta, code) = match after.cmp(&before) {
+        Ordering::Greater => ("+", after - before, "31"),
+        Ordering::Less => ("-", before - after, "32"),
+        Ordering::Equal => return String::from("no change"),
+    };
+
+    if !color {
+        return format!("{sign}{delta:?}");
+    }
+
+    format!("\x1b[{code}m{sign}{delta:?}\x1b[0m")
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use crate::{Event, Events, LockId};
+
+/// The acquisition of a lock which waited the longest, together with the holds
+/// by other threads which kept it waiting.
+///
+/// Returned by [`Events::worst_episode`].
+#[derive(Debug, Clone)]
+pub struct Episode<'a> {
+    acquisition: &'a Event,
+    requested: u64,
+    acquired: u64,
+    holders: Vec<EpisodeHold<'a>>,
+}
+
+impl<'a> Episode<'a> {
+    /// The critical section of the acquisition which waited.
+    ///
+    /// Its [`backtrace`][Event::backtrace] points to the code which waited.
+    pub fn acquisition(&self) -> &'a Event {
+        self.acquisition
+    }
+
+    /// The time at which the lock was requested, relative to when the capture
+    /// was started.
+    pub fn requested(&self) -> Duration {
+        Duration::from_nanos(self.requested)
+    }
+
+    /// The time at which the lock was acquired, or at which the attempt to
+    /// acquire it timed out.
+    pub fn acquired(&self) -> Duration {
+        Duration::from_nanos(self.acquired)
+    }
+
+    /// How long the acquisition waited.
+    pub fn wait(&self) -> Duration {
+        Duration::from_nanos(self.acquired - self.requested)
+    }
+
+    /// The holds by other threads which overlapped with the wait, in the order
+    /// in which they acquired the lock.
+    pub fn holders(&self) -> &[EpisodeHold<'a>] {
+        &self.holders
+    }
+}
+
+/// A hold of a lock which overlapped with the wait of an [`Episode`].
+#[derive(Debug, Clone, Copy)]
+pub struct EpisodeHold<'a> {
+    event: &'a Event,
+    acquired: u64,
+    released: u64,
+}
+
+impl<'a> EpisodeHold<'a> {
+    /// The critical section of the hold.
+    ///
+    /// Its [`backtrace`][Event::backtrace] points to the code which held the
+    /// lock.
+    pub fn event(&self) -> &'a Event {
+        self.event
+    }
+
+    /// The time at which the lock was acquired.
+    pub fn acquired(&self) -> Duration {
+        Duration::from_nanos(self.acquired)
+    }
+
+    /// The time at which the lock was released.
+    ///
+    /// Holds which were never released end at the end of the trace.
+    pub fn released(&self) -> Duration {
+        Duration::from_nanos(self.released)
+    }
+}
+
+impl Events {
+    /// Find the acquisition of the given lock which waited the longest, and
+    /// the holds by other threads which overlapped with its wait.
+    ///
+    /// This answers what caused the worst tail latency of a lock, as opposed
+    /// to aggregate measures such as [`utilization`][Events::utilization].
+    ///
+    /// Returns `None` if no acquisition of the lock had to wait.
+    pub fn worst_episode(&self, lock: LockId) -> Option<Episode<'_>> {
+        let times = self.holds();
+        let end = self.span().map_or(0, |(_, end)| end);
+
+        let mut holds = Vec::new();
+        let mut worst = None::<(u64, &Event)>;
+
+        for enter in &self.enters {
+            if enter.lock != lock || enter.parent.is_some() {
+                continue;
+            }
+
+            let open = times.acquired(enter.id).unwrap_or(enter.timestamp);
+            let wait = open.saturating_sub(enter.timestamp);
+
+            if wait > 0 && worst.map_or(true, |(worst, _)| wait > worst) {
+                worst = Some((wait, enter));
+            }
+
+            let Some((open, close)) = times.hold(enter) else {
+                continue;
+            };
+
+            let released = close.unwrap_or(end);
+
+            holds.push(EpisodeHold {
+                event: enter,
+                acquired: open,
+                released: released.max(open),
+            });
+        }
+
+        let (wait, acquisition) = worst?;
+        let requested = acquisition.timestamp;
+        let acquired = requested + wait;
+
+        holds.retain(|hold| {
+            hold.event.thread_index != acquisition.thread_index
+                && hold.acquired < acquired
+                && hold.released > requested
+        });
+
+        holds.sort_by_key(|hold| (hold.acquired, hold.event.id));
+
+        Some(Episode {
+            acquisition,
+            requested,
+            acquired,
+            holders: holds,
+        })
+    }
+}
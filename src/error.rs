@@ -0,0 +1,100 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::EventId;
+
+/// Error raised when exporting events.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O error occurred.
+    Io(io::Error),
+    /// The collection of events being exported doesn't contain anything.
+    EmptyTrace,
+    /// The specified output path can't be used.
+    InvalidPath(PathBuf),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(..) => write!(f, "I/O error"),
+            Error::EmptyTrace => write!(f, "No events captured"),
+            Error::InvalidPath(path) => write!(f, "Invalid output path: {}", path.display()),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    #[inline]
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// Error raised by [`try_drain`][crate::try_drain].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DrainError {
+    /// Events are not being captured, because [`capture`][crate::capture]
+    /// was never called or the capture has already been drained.
+    NotCapturing,
+    /// Draining would have to wait for another thread which is currently
+    /// recording events. Nothing was drained.
+    WouldBlock,
+}
+
+impl fmt::Display for DrainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrainError::NotCapturing => write!(f, "Events are not being captured"),
+            DrainError::WouldBlock => write!(f, "Draining events would block"),
+        }
+    }
+}
+
+impl error::Error for DrainError {}
+
+/// Error raised by [`Events::sort_and_validate`][crate::Events::sort_and_validate]
+/// when a collection of events is inconsistent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidateError {
+    /// More than one enter event has the given identifier.
+    DuplicateEvent(EventId),
+    /// The first event refers to the second one as its parent, but it's
+    /// missing.
+    MissingParent(EventId, EventId),
+    /// A leave event refers to an enter event which is missing.
+    UnmatchedLeave(EventId),
+    /// More than one leave event refers to the given enter event.
+    DuplicateLeave(EventId),
+}
+
+impl fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidateError::DuplicateEvent(id) => write!(f, "Duplicate event {id}"),
+            ValidateError::MissingParent(id, parent) => {
+                write!(f, "Event {id} refers to missing parent {parent}")
+            }
+            ValidateError::UnmatchedLeave(id) => {
+                write!(f, "Leave event refers to missing event {id}")
+            }
+            ValidateError::DuplicateLeave(id) => write!(f, "Event {id} is left more than once"),
+        }
+    }
+}
+
+impl error::Error for ValidateError {}
@@ -1,53 +1,142 @@
-#[cfg(feature = "trace")]
+#[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
 use std::backtrace::{Backtrace, BacktraceStatus};
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::num::{NonZeroU32, NonZeroUsize};
-#[cfg(feature = "trace")]
+#[cfg(all(feature = "trace", feature = "parking_lot"))]
 use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+#[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+use std::sync::Arc;
+use std::time::Duration;
 
 #[cfg(feature = "serde")]
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeStruct;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+use crate::call_sites::frame_symbol;
+use crate::intern::Name;
+#[cfg(feature = "serde")]
+use crate::intern::{deserialize_table, TableGuard};
+use crate::{Outcome, Process};
 
+/// The mask of the index of a lock.
+///
+/// The top two bits of a lock identifier are its kind. Since only three kinds
+/// fit, the last one is extended with two more bits. This keeps the
+/// identifiers of the original kinds the same.
 const LOCK_ID_MASK: u32 = 0x3FFFFFFF;
+/// The mask of the index of a lock with an extended kind.
+const EXTENDED_LOCK_ID_MASK: u32 = 0x0FFFFFFF;
 const LOCK_KIND_SHIFT: u32 = 30;
+const EXTENDED_LOCK_KIND_SHIFT: u32 = 28;
+const EXTENDED_LOCK_KIND: u32 = 3;
 
-#[derive(Debug)]
+/// The kind of a lock.
+///
+/// This can be used to tell locks apart when analyzing a collection of
+/// [`Events`], such as to only look at reader-writer locks. More kinds might
+/// be added as more lock types are supported.
+///
+/// # Examples
+///
+/// ```
+/// use unlock::LockKind;
+///
+/// let events = unlock::drain();
+///
+/// let rw_locks = events.filter(|event| matches!(event.lock().kind(), LockKind::RwLock));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u32)]
-pub(super) enum LockKind {
-    RwLock = 1,
-    Mutex = 2,
+#[non_exhaustive]
+pub enum LockKind {
+    /// A `RwLock`.
+    RwLock = 1 << LOCK_KIND_SHIFT,
+    /// A `Mutex`.
+    Mutex = 2 << LOCK_KIND_SHIFT,
+    /// An `AsyncMutex`, which is available with the `tokio` feature.
+    AsyncMutex = EXTENDED_LOCK_KIND << LOCK_KIND_SHIFT,
+    /// An `AsyncRwLock`, which is available with the `tokio` feature.
+    AsyncRwLock = (EXTENDED_LOCK_KIND << LOCK_KIND_SHIFT) | (1 << EXTENDED_LOCK_KIND_SHIFT),
+    /// A `Condvar`, whose waits and notifications are recorded.
+    Condvar = (EXTENDED_LOCK_KIND << LOCK_KIND_SHIFT) | (2 << EXTENDED_LOCK_KIND_SHIFT),
 }
 
+/// Get the mask of the index for the given raw lock identifier.
+#[inline]
+const fn lock_id_mask(id: u32) -> u32 {
+    if id >> LOCK_KIND_SHIFT == EXTENDED_LOCK_KIND {
+        EXTENDED_LOCK_ID_MASK
+    } else {
+        LOCK_ID_MASK
+    }
+}
+
+/// The unique identifier of a lock.
+///
+/// This can be used to refer to a particular lock when analyzing a collection
+/// of [`Events`].
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 #[repr(transparent)]
-pub(super) struct LockId(NonZeroU32);
+pub struct LockId(NonZeroU32);
 
 impl LockId {
     /// Create a new unique identifier.
-    #[cfg(feature = "trace")]
+    #[cfg(all(feature = "trace", feature = "parking_lot"))]
     pub(super) fn next(kind: LockKind) -> Self {
         static LOCK_ID: AtomicU32 = AtomicU32::new(1);
 
         loop {
             if let Some(id) = NonZeroU32::new(LOCK_ID.fetch_add(1, Ordering::Relaxed)) {
-                assert!(LOCK_ID_MASK >= id.get(), "wgpu-sync: Too many locks");
-                return Self(((kind as u32) << LOCK_KIND_SHIFT) | id);
+                let kind = kind as u32;
+                assert!(lock_id_mask(kind) >= id.get(), "wgpu-sync: Too many locks");
+                return Self(kind | id);
             }
         }
     }
 
     /// Get the index of this lock.
-    pub(super) fn index(self) -> usize {
-        (self.0.get() & LOCK_ID_MASK) as usize
+    ///
+    /// Indexes are handed out sequentially as locks are constructed.
+    pub fn index(self) -> usize {
+        (self.0.get() & lock_id_mask(self.0.get())) as usize
+    }
+
+    /// Offset the index of the lock by the given amount, retaining its kind.
+    pub(super) fn offset(self, offset: usize) -> Self {
+        let index = self.index().saturating_add(offset);
+
+        let mask = lock_id_mask(self.0.get());
+
+        let Some(index) = u32::try_from(index).ok().filter(|index| *index <= mask) else {
+            panic!("unlock: Too many locks");
+        };
+
+        let kind = self.0.get() & !mask;
+
+        match NonZeroU32::new(kind | index) {
+            Some(id) => Self(id),
+            None => unreachable!(),
+        }
     }
 
     /// Get the kind of lock this is.
-    pub(super) fn kind(self) -> LockKind {
-        match self.0.get() >> LOCK_KIND_SHIFT {
+    pub fn kind(self) -> LockKind {
+        let id = self.0.get();
+
+        match id >> LOCK_KIND_SHIFT {
             1 => LockKind::RwLock,
             2 => LockKind::Mutex,
+            EXTENDED_LOCK_KIND => match (id >> EXTENDED_LOCK_KIND_SHIFT) & 0b11 {
+                0 => LockKind::AsyncMutex,
+                1 => LockKind::AsyncRwLock,
+                2 => LockKind::Condvar,
+                _ => unreachable!(),
+            },
             _ => unreachable!(),
         }
     }
@@ -68,14 +157,20 @@ impl fmt::Debug for LockId {
     }
 }
 
+/// The unique identifier of an event.
+///
+/// This can be used to refer to events returned from the analysis methods of
+/// [`Events`], such as to look them up again through [`Events::get`].
+/// Identifiers are handed out sequentially, so they also provide a total
+/// ordering of when events were recorded.
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
-pub(super) struct EventId(NonZeroUsize);
+pub struct EventId(NonZeroUsize);
 
 impl EventId {
     /// Create a new unique identifier.
-    #[cfg(feature = "trace")]
+    #[cfg(all(feature = "trace", feature = "parking_lot"))]
     pub(super) fn next() -> Self {
         // Provides a total ordering to events recorded. Note that this is not
         // guaranteed to be a globally observable order.
@@ -87,6 +182,32 @@ impl EventId {
 
         panic!("wgpu-sync: Too many events")
     }
+
+    /// Construct the identifier of the event at the given zero-based index.
+    pub(super) fn from_index(index: usize) -> Self {
+        Self::first().offset(index)
+    }
+
+    /// The first identifier.
+    fn first() -> Self {
+        match NonZeroUsize::new(1) {
+            Some(id) => Self(id),
+            None => unreachable!(),
+        }
+    }
+
+    /// Get the raw value of the identifier.
+    pub(super) fn get(self) -> usize {
+        self.0.get()
+    }
+
+    /// Offset the identifier by the given amount.
+    pub(super) fn offset(self, offset: usize) -> Self {
+        match self.0.checked_add(offset) {
+            Some(id) => Self(id),
+            None => panic!("unlock: Too many events"),
+        }
+    }
 }
 
 impl fmt::Display for EventId {
@@ -96,28 +217,155 @@ impl fmt::Display for EventId {
 }
 
 /// A backtrace that can be serialized.
-#[derive(Debug)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
-pub struct EventBacktrace(Box<str>);
+#[derive(Debug, Clone)]
+pub struct EventBacktrace(Repr);
+
+#[derive(Debug, Clone)]
+enum Repr {
+    /// A formatted backtrace.
+    #[allow(unused)]
+    Resolved(Box<str>),
+    /// A captured backtrace which hasn't been symbolized yet, and the length
+    /// to truncate it to once it is.
+    #[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+    Unresolved(Arc<Backtrace>, usize),
+}
 
 impl EventBacktrace {
-    #[cfg(feature = "trace")]
-    pub(super) fn from_capture(backtrace: Backtrace) -> Option<Self> {
+    /// Access the backtrace as a string.
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Resolved(string) => string,
+            // Backtraces are resolved before the events they belong to are
+            // drained, so this is never observed.
+            #[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+            Repr::Unresolved(..) => "",
+        }
+    }
+
+    /// Capture a backtrace of the current thread, if backtraces are enabled.
+    ///
+    /// Symbolizing the backtrace is deferred until [`resolve`] is called, so
+    /// that it happens on the thread draining the events rather than while
+    /// acquiring a lock.
+    ///
+    /// [`resolve`]: EventBacktrace::resolve
+    #[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+    pub(super) fn capture(max_len: usize) -> Option<Self> {
+        let backtrace = Backtrace::capture();
+
         match backtrace.status() {
-            BacktraceStatus::Captured => Some(Self(format!("{}", backtrace).into())),
+            BacktraceStatus::Captured => Some(Self(Repr::Unresolved(Arc::new(backtrace), max_len))),
             _ => None,
         }
     }
+
+    /// Symbolize and format the backtrace if it hasn't been already.
+    ///
+    /// The formatted backtrace is truncated to at most the length it was
+    /// captured with, not counting the marker indicating that it was
+    /// truncated.
+    #[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+    pub(super) fn resolve(&mut self) {
+        if let Repr::Unresolved(backtrace, max_len) = &self.0 {
+            let mut string = backtrace.to_string();
+            truncate_backtrace(&mut string, *max_len);
+            self.0 = Repr::Resolved(string.into());
+        }
+    }
+
+    /// Capture a backtrace of the current thread, which always fails since
+    /// the `backtrace` feature is disabled.
+    #[cfg(all(feature = "trace", feature = "parking_lot", not(feature = "backtrace")))]
+    #[inline(always)]
+    pub(super) fn capture(_: usize) -> Option<Self> {
+        None
+    }
+}
+
+/// Marker appended to backtraces which have been truncated.
+#[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+const TRUNCATED: &str = "   ...\n";
+
+/// Truncate a formatted backtrace to at most `max_len` bytes.
+///
+/// The backtrace is preferably cut right before a frame, or else at the start
+/// of a line, so that no frame is cut in half.
+#[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+fn truncate_backtrace(string: &mut String, max_len: usize) {
+    if string.len() <= max_len {
+        return;
+    }
+
+    let mut frame = None;
+    let mut line = None;
+    let mut start = 0;
+
+    for current in string.split_inclusive('\n') {
+        if start > max_len {
+            break;
+        }
+
+        if start > 0 {
+            line = Some(start);
+
+            if frame_symbol(current).is_some() {
+                frame = Some(start);
+            }
+        }
+
+        start += current.len();
+    }
+
+    let mut cut = match frame.or(line) {
+        Some(cut) => cut,
+        // Leave room for the line break which ends the kept part.
+        None => max_len.saturating_sub(1),
+    };
+
+    while !string.is_char_boundary(cut) {
+        cut -= 1;
+    }
+
+    string.truncate(cut);
+
+    if !string.is_empty() && !string.ends_with('\n') {
+        string.push('\n');
+    }
+
+    string.push_str(TRUNCATED);
 }
 
 impl fmt::Display for EventBacktrace {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for EventBacktrace {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.as_str().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for EventBacktrace {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self(Repr::Resolved(Box::<str>::deserialize(deserializer)?)))
     }
 }
 
 /// A recorded opening event.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Event {
     /// The unique identifier of this event.
@@ -129,19 +377,235 @@ pub struct Event {
     /// The parent event this event is a child of.
     pub(super) parent: Option<EventId>,
     /// The name of the event.
-    pub(super) name: Cow<'static, str>,
+    pub(super) name: Name,
     /// The type name which is wrapped in the lock.
-    pub(super) type_name: Cow<'static, str>,
+    pub(super) type_name: Name,
     /// The unique sequential identifier and kind of the lock.
     pub(super) lock: LockId,
     /// Capture backtrace if RUST_BACKTRACE=1 or RUST_LIB_BACKTRACE=1 is
     /// set.
+    ///
+    /// This is compiled out if the `backtrace` feature is disabled, in which
+    /// case it's omitted when serializing and ignored when deserializing.
+    #[cfg(feature = "backtrace")]
     #[cfg_attr(feature = "serde", serde(default))]
     pub(super) backtrace: Option<EventBacktrace>,
+    /// The approximate stack depth in bytes at which the event was recorded,
+    /// if enabled.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub(super) stack_depth: Option<u32>,
+    /// The number of other threads which were waiting to acquire the lock
+    /// when this event was recorded.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) waiters: u32,
+    /// The number of other locks held by the thread when the lock was
+    /// requested.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) held: u32,
+    /// Attributes attached to the event through
+    /// [`with_context`][crate::with_context].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub(super) attributes: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    /// The timeout in nanoseconds used when acquiring the lock, if any.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) timeout: Option<u64>,
+    /// The outcome of the attempt to acquire the lock.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) outcome: Outcome,
+    /// Whether the lock couldn't be acquired without blocking.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub(super) contended: bool,
+    /// The task the event was recorded in, as set through
+    /// [`set_task_id`][crate::set_task_id].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) task_id: Option<u64>,
+    /// The identifier of the process the event was recorded in.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) process: u32,
+}
+
+impl Event {
+    /// Construct a new event which has not yet been recorded.
+    ///
+    /// The timestamp and thread index are filled in once it's recorded.
+    #[cfg(all(feature = "trace", feature = "parking_lot"))]
+    pub(super) fn new(
+        id: EventId,
+        lock: LockId,
+        name: Name,
+        type_name: Name,
+        parent: Option<EventId>,
+    ) -> Self {
+        Self {
+            id,
+            timestamp: 0,
+            thread_index: 0,
+            parent,
+            name,
+            type_name,
+            lock,
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+            stack_depth: None,
+            waiters: 0,
+            held: 0,
+            attributes: Vec::new(),
+            timeout: None,
+            outcome: Outcome::Acquired,
+            contended: false,
+            task_id: None,
+            process: 0,
+        }
+    }
+
+    /// The time at which the event was recorded, relative to when the
+    /// capture was started.
+    pub fn timestamp(&self) -> Duration {
+        Duration::from_nanos(self.timestamp)
+    }
+
+    /// The approximate depth of the stack in bytes when this event was
+    /// recorded, if enabled through
+    /// [`CaptureConfig::stack_depth`][crate::CaptureConfig::stack_depth].
+    ///
+    /// This is measured from the shallowest point at which the same thread
+    /// recorded an event, so it is only comparable between events recorded on
+    /// the same thread, and the depth of the first events recorded on a
+    /// thread might be underestimated.
+    pub fn stack_depth(&self) -> Option<u32> {
+        self.stack_depth
+    }
+
+    /// The number of other threads which were waiting to acquire the lock
+    /// when this event was recorded.
+    pub fn waiters(&self) -> u32 {
+        self.waiters
+    }
+
+    /// The number of other locks held by the same thread when the lock of a
+    /// critical section was requested.
+    ///
+    /// This is counted from the captured events when they're drained, so
+    /// only locks which were acquired during the capture are counted. It's
+    /// always zero for events nested in a critical section.
+    pub fn held(&self) -> u32 {
+        self.held
+    }
+
+    /// The index of the thread the event was recorded on.
+    pub fn thread_index(&self) -> usize {
+        self.thread_index
+    }
+
+    /// The task the event was recorded in, as set through
+    /// [`set_task_id`][crate::set_task_id].
+    pub fn task_id(&self) -> Option<u64> {
+        self.task_id
+    }
+
+    /// The timeout used when acquiring the lock, if it was acquired with one.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_nanos)
+    }
+
+    /// The outcome of the attempt to acquire the lock.
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// Test if the lock couldn't be acquired without blocking.
+    ///
+    /// While recording, locks are first attempted to be acquired without
+    /// blocking, and the acquisition is contended if that fails. Acquisitions
+    /// which never block, such as through [`Mutex::try_lock`], are never
+    /// contended.
+    ///
+    /// [`Mutex::try_lock`]: crate::Mutex::try_lock
+    pub fn contended(&self) -> bool {
+        self.contended
+    }
+
+    /// Iterate over the key-value attributes attached to this event through
+    /// [`with_context`][crate::with_context].
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_ref()))
+    }
+
+    /// The name of the event, such as `read` or `write`.
+    pub fn name(&self) -> &'static str {
+        self.name.as_str()
+    }
+
+    /// The name of the type which is wrapped in the lock.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name.as_str()
+    }
+
+    /// The unique identifier of this event.
+    pub fn id(&self) -> EventId {
+        self.id
+    }
+
+    /// The identifier of the event this event is nested in, if any.
+    ///
+    /// Every lock acquisition is recorded as a root event spanning the
+    /// critical section, with a nested event spanning the time spent waiting
+    /// for the lock.
+    pub fn parent(&self) -> Option<EventId> {
+        self.parent
+    }
+
+    /// The lock this event is associated with.
+    pub fn lock(&self) -> LockId {
+        self.lock
+    }
+
+    /// The backtrace of where this event was recorded, if one was captured.
+    ///
+    /// This is always `None` if the `backtrace` feature is disabled.
+    pub fn backtrace(&self) -> Option<&EventBacktrace> {
+        #[cfg(feature = "backtrace")]
+        {
+            self.backtrace.as_ref()
+        }
+
+        #[cfg(not(feature = "backtrace"))]
+        {
+            None
+        }
+    }
+
+    /// Capture a backtrace for this event, if backtraces are enabled.
+    ///
+    /// See [`EventBacktrace::capture`] for how `max_len` is used.
+    #[cfg(all(feature = "trace", feature = "parking_lot"))]
+    #[inline]
+    pub(super) fn capture_backtrace(&mut self, max_len: usize) {
+        #[cfg(feature = "backtrace")]
+        {
+            self.backtrace = EventBacktrace::capture(max_len);
+        }
+
+        #[cfg(not(feature = "backtrace"))]
+        {
+            _ = max_len;
+        }
+    }
 }
 
 /// A recorded leaving event.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Leave {
     /// Event emitted when a particular section has been left.
@@ -153,16 +617,213 @@ pub struct Leave {
     pub(super) thread_index: usize,
     /// The timestamp when the event was left.
     pub(super) timestamp: u64,
+    /// Whether the lock was leaked rather than released.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub(super) leaked: bool,
+}
+
+impl Leave {
+    /// The identifier of the enter event which opened the section this
+    /// leaves.
+    pub fn sibling(&self) -> EventId {
+        self.sibling
+    }
+
+    /// The index of the thread the event was recorded on.
+    ///
+    /// This is the thread which released the lock, which for the guards of
+    /// async locks can be a different thread than the one which acquired it.
+    pub fn thread_index(&self) -> usize {
+        self.thread_index
+    }
+
+    /// The time at which the section was left, relative to when the capture
+    /// was started.
+    pub fn timestamp(&self) -> Duration {
+        Duration::from_nanos(self.timestamp)
+    }
+
+    /// Whether the lock was leaked rather than released, such as through
+    /// [`MutexGuard::leak`][crate::MutexGuard::leak].
+    pub fn leaked(&self) -> bool {
+        self.leaked
+    }
+}
+
+/// The order in which drained events are sorted.
+///
+/// Ties are always broken by the identifier of the event, so the order is
+/// deterministic.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SortKey {
+    /// Sort events by their identifier, which is the order in which they were
+    /// created.
+    #[default]
+    Id,
+    /// Sort events by their timestamp.
+    Timestamp,
+    /// Sort events by the thread they were recorded on, and then by their
+    /// timestamp.
+    Thread,
 }
 
 /// Collection of collected events.
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+///
+/// This can be cloned, so that the same collection can be analyzed, rendered
+/// and serialized independently.
+#[derive(Clone)]
 pub struct Events {
     pub(super) enters: Vec<Event>,
     pub(super) leaves: Vec<Leave>,
+    /// The number of enter events which were dropped during capture.
+    pub(super) dropped: u64,
+    /// The number of times a thread was assigned an index already used by
+    /// another thread.
+    pub(super) thread_index_conflicts: u64,
+    /// The processes the events were captured in.
+    pub(super) processes: Vec<Process>,
+    /// Backtraces captured when the locks in the collection were constructed.
+    pub(super) creation_sites: HashMap<LockId, EventBacktrace>,
+    /// Keys assigned to the locks in the collection.
+    pub(super) keys: HashMap<LockId, Name>,
+    /// Names of the threads in the collection, by the identifier of their
+    /// process and their thread index, since thread indexes are only unique
+    /// within a process.
+    pub(super) thread_names: HashMap<u32, HashMap<usize, String>>,
+}
+
+/// Events are serialized with a table of the names they use, so that each
+/// event only refers to its names by index.
+#[cfg(feature = "serde")]
+impl Serialize for Events {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (names, _table) = TableGuard::serialize(self.names());
+
+        let mut s = serializer.serialize_struct("Events", 9)?;
+
+        if names.is_empty() {
+            s.skip_field("names")?;
+        } else {
+            s.serialize_field("names", &names)?;
+        }
+
+        s.serialize_field("enters", &self.enters)?;
+        s.serialize_field("leaves", &self.leaves)?;
+        s.serialize_field("dropped", &self.dropped)?;
+        s.serialize_field("thread_index_conflicts", &self.thread_index_conflicts)?;
+
+        if self.processes.is_empty() {
+            s.skip_field("processes")?;
+        } else {
+            s.serialize_field("processes", &self.processes)?;
+        }
+
+        if self.creation_sites.is_empty() {
+            s.skip_field("creation_sites")?;
+        } else {
+            s.serialize_field("creation_sites", &self.creation_sites)?;
+        }
+
+        if self.keys.is_empty() {
+            s.skip_field("keys")?;
+        } else {
+            s.serialize_field("keys", &self.keys)?;
+        }
+
+        if self.thread_names.is_empty() {
+            s.skip_field("thread_names")?;
+        } else {
+            s.serialize_field("thread_names", &self.thread_names)?;
+        }
+
+        s.end()
+    }
+}
+
+/// Names can either be strings or indexes into the table of names, which has
+/// to come before any event which refers to it.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Events {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename = "Events")]
+        struct Repr {
+            #[serde(default, rename = "names", deserialize_with = "deserialize_table")]
+            _names: (),
+            enters: Vec<Event>,
+            leaves: Vec<Leave>,
+            #[serde(default)]
+            dropped: u64,
+            #[serde(default)]
+            thread_index_conflicts: u64,
+            #[serde(default)]
+            processes: Vec<Process>,
+            #[serde(default)]
+            creation_sites: HashMap<LockId, EventBacktrace>,
+            #[serde(default)]
+            keys: HashMap<LockId, Name>,
+            #[serde(default)]
+            thread_names: HashMap<u32, HashMap<usize, String>>,
+        }
+
+        let _table = TableGuard::deserialize();
+        let repr = Repr::deserialize(deserializer)?;
+
+        Ok(Self {
+            enters: repr.enters,
+            leaves: repr.leaves,
+            dropped: repr.dropped,
+            thread_index_conflicts: repr.thread_index_conflicts,
+            processes: repr.processes,
+            creation_sites: repr.creation_sites,
+            keys: repr.keys,
+            thread_names: repr.thread_names,
+        })
+    }
 }
 
 impl Events {
+    /// The distinct names used by the events in the collection, in the order
+    /// they're first used.
+    pub(crate) fn names(&self) -> Vec<Name> {
+        let mut seen = HashSet::new();
+        let mut names = Vec::new();
+
+        for event in &self.enters {
+            for name in [event.name, event.type_name] {
+                if seen.insert(name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        names
+    }
+
+    /// Symbolize the backtraces in the collection which haven't been already.
+    #[cfg(all(feature = "trace", feature = "parking_lot", feature = "backtrace"))]
+    pub(super) fn resolve_backtraces(&mut self) {
+        for enter in &mut self.enters {
+            if let Some(backtrace) = &mut enter.backtrace {
+                backtrace.resolve();
+            }
+        }
+
+        for backtrace in self.creation_sites.values_mut() {
+            backtrace.resolve();
+        }
+    }
+
     /// The number of enter events in the collection.
     pub fn len(&self) -> usize {
         self.enters.len()
@@ -173,10 +834,303 @@ impl Events {
         self.enters.is_empty()
     }
 
+    /// The enter events in the collection, in the order it's sorted by.
+    ///
+    /// Together with [`leaves`], this allows for writing custom analyses and
+    /// exporters. The leave event closing a section refers to the enter event
+    /// which opened it through [`Leave::sibling`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// let events = unlock::drain();
+    ///
+    /// let mut acquisitions = HashMap::<_, usize>::new();
+    ///
+    /// for event in events.enters() {
+    ///     if event.parent().is_none() {
+    ///         *acquisitions.entry(event.lock()).or_default() += 1;
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`leaves`]: Events::leaves
+    pub fn enters(&self) -> &[Event] {
+        &self.enters
+    }
+
+    /// The leave events in the collection, in the order it's sorted by.
+    ///
+    /// See [`Events::enters`] for details.
+    pub fn leaves(&self) -> &[Leave] {
+        &self.leaves
+    }
+
+    /// The number of enter events which were dropped during capture because
+    /// the limit configured through
+    /// [`CaptureConfig::max_events`][crate::CaptureConfig::max_events] was
+    /// reached.
+    ///
+    /// If this is non-zero, the collection of events is incomplete.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// The number of times a thread was assigned an index which was in use by
+    /// another thread at the same time.
+    ///
+    /// This can only happen if a custom source of thread indexes is set
+    /// through [`set_thread_index_source`] which doesn't return values which
+    /// are unique among running threads. If this is non-zero, the sections of
+    /// several threads might have been merged into the same timeline.
+    ///
+    /// [`set_thread_index_source`]: crate::set_thread_index_source
+    pub fn thread_index_conflicts(&self) -> u64 {
+        self.thread_index_conflicts
+    }
+
+    /// Look up the event with the given identifier.
+    pub fn get(&self, id: EventId) -> Option<&Event> {
+        // Events are sorted by identifier unless they've been sorted by
+        // something else, in which case we fall back to a linear search.
+        match self.enters.binary_search_by_key(&id, |event| event.id) {
+            Ok(index) => Some(&self.enters[index]),
+            Err(..) => self.enters.iter().find(|event| event.id == id),
+        }
+    }
+
+    /// The backtrace captured when the given lock was constructed.
+    ///
+    /// This is only available if the capture was started with
+    /// [`CaptureConfig::creation_sites`][crate::CaptureConfig::creation_sites]
+    /// enabled before the lock was constructed.
+    pub fn lock_creation_site(&self, lock: LockId) -> Option<&EventBacktrace> {
+        self.creation_sites.get(&lock)
+    }
+
+    /// The key assigned to the given lock when it was constructed, such as
+    /// through [`Mutex::with_key`].
+    ///
+    /// Unlike the [index][LockId::index] of a lock, the key identifies the
+    /// same lock across separate runs and processes.
+    ///
+    /// [`Mutex::with_key`]: crate::Mutex::with_key
+    pub fn lock_key(&self, lock: LockId) -> Option<&'static str> {
+        Some(self.keys.get(&lock)?.as_str())
+    }
+
+    /// The name of the thread which recorded the given event, if it was
+    /// named.
+    ///
+    /// The name is captured the first time a thread records an event, see
+    /// [`std::thread::Builder::name`]. If the index of threads is determined
+    /// through [`set_thread_index_source`], this is the name of the last
+    /// thread which was assigned the index. Threads of different processes
+    /// in a merged collection are told apart by the process the event was
+    /// captured in, see [`Events::process`].
+    ///
+    /// [`set_thread_index_source`]: crate::set_thread_index_source
+    pub fn thread_name(&self, event: &Event) -> Option<&str> {
+        self.thread_names
+            .get(&event.process)?
+            .get(&event.thread_index)
+            .map(String::as_str)
+    }
+
     pub(super) fn new() -> Self {
         Self {
             enters: Vec::new(),
             leaves: Vec::new(),
+            dropped: 0,
+            thread_index_conflicts: 0,
+            processes: Vec::new(),
+            creation_sites: HashMap::new(),
+            keys: HashMap::new(),
+            thread_names: HashMap::new(),
+        }
+    }
+
+    /// Construct a collection with the given events, which otherwise shares
+    /// the properties of this collection.
+    pub(crate) fn with_events(&self, enters: Vec<Event>, leaves: Vec<Leave>) -> Self {
+        Self {
+            enters,
+            leaves,
+            dropped: self.dropped,
+            thread_index_conflicts: self.thread_index_conflicts,
+            processes: self.processes.clone(),
+            creation_sites: self.creation_sites.clone(),
+            keys: self.keys.clone(),
+            thread_names: self.thread_names.clone(),
+        }
+    }
+
+    /// Sort events by the given key.
+    ///
+    /// Events returned by [`drain`][crate::drain] are sorted by
+    /// [`SortKey::Id`], use [`drain_sorted`][crate::drain_sorted] to drain
+    /// them in another order directly.
+    pub fn sort(&mut self, key: SortKey) {
+        match key {
+            SortKey::Id => {
+                self.enters.sort_by_key(|event| event.id);
+                self.leaves.sort_by_key(|event| event.sibling);
+            }
+            SortKey::Timestamp => {
+                self.enters.sort_by_key(|event| (event.timestamp, event.id));
+                self.leaves
+                    .sort_by_key(|event| (event.timestamp, event.sibling));
+            }
+            SortKey::Thread => {
+                self.enters
+                    .sort_by_key(|event| (event.thread_index, event.timestamp, event.id));
+                self.leaves
+                    .sort_by_key(|event| (event.thread_index, event.timestamp, event.sibling));
+            }
+        }
+    }
+
+    /// Renumber events so that their identifiers are assigned in a stable
+    /// order.
+    ///
+    /// Identifiers are allocated from a global counter as events are recorded,
+    /// so concurrently recording threads interleave them differently from run
+    /// to run. This assigns new identifiers starting at `1`, ordered by
+    /// timestamp, thread index and finally the original identifier, and
+    /// updates all references to them. Events are left sorted by
+    /// [`SortKey::Id`].
+    ///
+    /// This is intended for tests and reproductions, such as comparing the
+    /// output of a trace against a golden file. Two captures only renumber the
+    /// same way if their events are recorded at the same timestamps, which
+    /// typically requires a coarse [`resolution`].
+    ///
+    /// [`resolution`]: crate::CaptureConfig::resolution
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut events = unlock::drain();
+    /// events.canonicalize();
+    /// ```
+    pub fn canonicalize(&mut self) {
+        self.enters
+            .sort_by_key(|event| (event.timestamp, event.thread_index, event.id));
+
+        let ids = self
+            .enters
+            .iter()
+            .enumerate()
+            .map(|(index, event)| (event.id, EventId::from_index(index)))
+            .collect::<HashMap<_, _>>();
+
+        for event in &mut self.enters {
+            event.id = ids[&event.id];
+
+            // A parent which is missing, such as from a deserialized trace,
+            // could otherwise collide with a renumbered event.
+            event.parent = event.parent.and_then(|parent| ids.get(&parent).copied());
+        }
+
+        // The same goes for leaves whose enter event is missing.
+        self.leaves
+            .retain_mut(|leave| match ids.get(&leave.sibling) {
+                Some(id) => {
+                    leave.sibling = *id;
+                    true
+                }
+                None => false,
+            });
+
+        self.leaves.sort_by_key(|event| event.sibling);
+    }
+
+    /// Map each event to the timestamp at which it was closed.
+    pub(crate) fn closes(&self) -> HashMap<EventId, u64> {
+        self.leaves
+            .iter()
+            .map(|leave| (leave.sibling, leave.timestamp))
+            .collect()
+    }
+
+    /// Iterate over the critical sections of locks which were leaked through
+    /// [`MutexGuard::leak`][crate::MutexGuard::leak] rather than released.
+    ///
+    /// Leaked critical sections are closed at the point they were leaked.
+    pub fn leaked(&self) -> impl Iterator<Item = &Event> + '_ {
+        let leaked = self
+            .leaves
+            .iter()
+            .filter(|leave| leave.leaked)
+            .map(|leave| leave.sibling)
+            .collect::<HashSet<_>>();
+
+        self.enters
+            .iter()
+            .filter(move |event| leaked.contains(&event.id))
+    }
+
+    /// The span of time covered by the trace, from the first enter to the last
+    /// leave.
+    pub(crate) fn span(&self) -> Option<(u64, u64)> {
+        let start = self.enters.iter().map(|enter| enter.timestamp).min()?;
+        let end = self.leaves.iter().map(|leave| leave.timestamp).max()?;
+        Some((start, end))
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "trace",
+    feature = "parking_lot",
+    feature = "backtrace"
+))]
+mod tests {
+    use super::{truncate_backtrace, TRUNCATED};
+
+    /// Truncate `string` and return the kept part, checking that it's marked
+    /// as truncated.
+    fn truncate(string: &str, max_len: usize) -> String {
+        let mut string = string.to_owned();
+        truncate_backtrace(&mut string, max_len);
+
+        let kept = string
+            .strip_suffix(TRUNCATED)
+            .expect("truncated backtraces end with the marker");
+
+        assert!(kept.len() <= max_len, "{kept:?} is longer than {max_len}");
+        kept.to_owned()
+    }
+
+    #[test]
+    fn short_backtraces_are_kept() {
+        let mut string = String::from("   0: main\n");
+        truncate_backtrace(&mut string, 64);
+        assert_eq!(string, "   0: main\n");
+    }
+
+    #[test]
+    fn cuts_before_the_frame_which_does_not_fit() {
+        let backtrace = "   0: ünlöck::first\n             at src/lib.rs:1:1\n   1: ünlöck::second\n             at src/lib.rs:2:1\n";
+        let second = backtrace.find("   1:").unwrap();
+
+        assert_eq!(truncate(backtrace, second + 4), &backtrace[..second]);
+        assert_eq!(truncate(backtrace, second), &backtrace[..second]);
+    }
+
+    #[test]
+    fn cuts_at_a_char_boundary() {
+        // Without any line to cut at, the backtrace is cut in the middle of a
+        // two-byte character, which must be left out.
+        let kept = truncate("ééééé", 4);
+        assert_eq!(kept, "é\n");
+
+        for max_len in 0..10 {
+            let kept = truncate("ééééé", max_len);
+            let kept = kept.strip_suffix('\n').unwrap_or(&kept);
+            assert!("ééééé".starts_with(kept));
         }
     }
 }
@@ -1,4 +1,10 @@
-use crate::event::Events;
+use std::borrow::Cow;
+use std::time::Duration;
+
+use crate::config::CaptureConfig;
+use crate::event::{Events, SortKey};
+use crate::session::SessionId;
+use crate::{DrainError, LockState, TimedCapture};
 
 /// Enable tracing.
 ///
@@ -8,6 +14,198 @@ use crate::event::Events;
 #[allow(unused)]
 pub fn capture() {}
 
+/// Enable tracing with the given configuration.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn capture_with(config: CaptureConfig) {}
+
+/// Pause or resume recording of events.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn set_enabled(enabled: bool) {}
+
+/// Test if lock acquisitions are currently being recorded.
+///
+/// This is the fake version and will always return `false`. To enable the
+/// real version, set the `trace` feature.
+#[inline(always)]
+pub fn is_capturing() -> bool {
+    false
+}
+
+/// Start a capture session.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+pub fn capture_session() -> SessionId {
+    SessionId::new(1, 0)
+}
+
+/// Stop the given capture session and drain its events.
+///
+/// This is the fake version and will always return an empty collection. To
+/// enable the real version, set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn drain_session(id: SessionId) -> Events {
+    Events::new()
+}
+
+/// Enable tracing for the given duration.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn capture_for(duration: Duration) -> TimedCapture {
+    TimedCapture::new(None)
+}
+
+/// Start capturing events until the returned guard is dropped.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn capture_scope() -> CaptureGuard {
+    CaptureGuard { on_drain: None }
+}
+
+/// A capture started with [`capture_scope`], which is drained when the guard
+/// is dropped.
+///
+/// This is the fake version and will only call the callback set through
+/// [`CaptureGuard::on_drain`] with an empty collection. To enable the real
+/// version, set the `trace` feature.
+#[must_use = "the capture is drained as soon as the guard is dropped"]
+pub struct CaptureGuard {
+    on_drain: Option<Box<dyn FnOnce(Events) + Send>>,
+}
+
+impl CaptureGuard {
+    /// Set a callback which is called with the drained events once the guard
+    /// is dropped.
+    ///
+    /// This is the fake version and the callback will be called with an
+    /// empty collection. To enable the real version, set the `trace` feature.
+    #[inline(always)]
+    pub fn on_drain<F>(mut self, f: F) -> Self
+    where
+        F: 'static + Send + FnOnce(Events),
+    {
+        self.on_drain = Some(Box::new(f));
+        self
+    }
+
+    /// Drain the capture now and return its events.
+    ///
+    /// This is the fake version and will do nothing. To enable the real
+    /// version, set the `trace` feature.
+    #[inline(always)]
+    pub fn into_events(mut self) -> Events {
+        self.on_drain = None;
+        Events::new()
+    }
+}
+
+impl Drop for CaptureGuard {
+    #[inline(always)]
+    fn drop(&mut self) {
+        if let Some(on_drain) = self.on_drain.take() {
+            on_drain(Events::new());
+        }
+    }
+}
+
+/// Attach the given key-value attribute to every lock acquisition recorded on
+/// the current thread while `f` is running.
+///
+/// This is the fake version and will only call `f`. To enable the real
+/// version, set the `trace` feature.
+#[inline(always)]
+pub fn with_context<K, V, F, R>(_: K, _: V, f: F) -> R
+where
+    K: Into<Cow<'static, str>>,
+    V: Into<Cow<'static, str>>,
+    F: FnOnce() -> R,
+{
+    f()
+}
+
+/// Set the label of the current process.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn set_process_label<L>(label: L)
+where
+    L: Into<Cow<'static, str>>,
+{
+}
+
+/// Set the identifier of the task currently running on this thread.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn set_task_id(task_id: u64) {}
+
+/// Clear the identifier of the task running on this thread.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+pub fn clear_task_id() {}
+
+/// Override how the index of the current thread is determined.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn set_thread_index_source(source: fn() -> usize) {}
+
+/// Restore the built-in thread index source.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+pub fn clear_thread_index_source() {}
+
+/// Release the memory used to buffer events.
+///
+/// This is the fake version and will do nothing. To enable the real version,
+/// set the `trace` feature.
+#[inline(always)]
+pub fn release_buffers() {}
+
+/// Copy the events captured so far.
+///
+/// This is the fake version and will always return an empty collection. To
+/// enable the real version, set the `trace` feature.
+#[inline(always)]
+pub fn snapshot() -> Events {
+    Events::new()
+}
+
+/// Report the locks which are currently held or being waited for.
+///
+/// This is the fake version and will always return an empty vector. To enable
+/// the real version, set the `trace` feature.
+#[inline(always)]
+pub fn held_locks() -> Vec<LockState> {
+    Vec::new()
+}
+
 /// Drain the current capture of events since the last time `capture` was
 /// called.
 ///
@@ -17,3 +215,32 @@ pub fn capture() {}
 pub fn drain() -> Events {
     Events::new()
 }
+
+/// Drain the current capture of events, failing if events are not being
+/// captured.
+///
+/// This is the fake version and will always return an empty collection, since
+/// capturing is disabled altogether. To enable the real version, set the
+/// `trace` feature.
+#[inline(always)]
+pub fn try_drain() -> Result<Events, DrainError> {
+    Ok(Events::new())
+}
+
+/// Replace the global tracing context with a fresh one.
+///
+/// This is the fake version and does nothing. To enable the real version, set
+/// the `trace` feature.
+#[cfg(feature = "test-util")]
+#[inline(always)]
+pub fn reset_for_test() {}
+
+/// Drain the current capture of events, sorted by the given key.
+///
+/// This is the fake version and will always return an empty vector. To enable
+/// the real version, set the `trace` feature.
+#[inline(always)]
+#[allow(unused)]
+pub fn drain_sorted(key: SortKey) -> Events {
+    Events::new()
+}
@@ -0,0 +1,193 @@
+//! Module to export captured lock events as folded stacks.
+//!
+//! Folded stacks are the input format of flamegraph tools such as [`inferno`]
+//! and [`flamegraph.pl`]. Every line is a semicolon-separated stack, outermost
+//! frame first, followed by a weight:
+//!
+//! ```text
+//! main;app::worker;app::update 1520000
+//! ```
+//!
+//! Stacks are taken from the backtraces captured when locks were acquired,
+//! excluding frames belonging to this crate. Acquisitions which didn't capture
+//! a backtrace use the type of the lock and its index as their only frame.
+//!
+//! [`inferno`]: https://github.com/jonhoo/inferno
+//! [`flamegraph.pl`]: https://github.com/brendangregg/FlameGraph
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::call_sites::{frame_symbol, SKIPPED_FRAMES};
+use crate::{Error, Event, Events};
+
+/// Write stacks weighted by the number of nanoseconds a lock was held to the
+/// given path.
+///
+/// This shows which call sites held locks the longest. Hold times of
+/// identical stacks are summed, and critical sections which weren't released
+/// or which failed are left out.
+///
+/// ```no_run
+/// let events = unlock::drain();
+/// unlock::folded::write("locks.folded", &events)?;
+/// # Ok::<_, unlock::Error>(())
+/// ```
+pub fn write<P>(path: P, events: &Events) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    write_file(path, |out| write_to(out, events))
+}
+
+/// Write stacks weighted by the number of nanoseconds a lock was held to the
+/// given output.
+///
+/// See [`write()`] for details.
+///
+/// ```
+/// let events = unlock::drain();
+/// let mut out = Vec::new();
+/// unlock::folded::write_to(&mut out, &events)?;
+/// # Ok::<_, unlock::Error>(())
+/// ```
+pub fn write_to<W>(out: W, events: &Events) -> Result<(), Error>
+where
+    W: io::Write,
+{
+    let holds = events.holds();
+
+    write_weighted(out, events, |event| {
+        let (open, close) = holds.hold(event)?;
+        Some(close?.saturating_sub(open))
+    })
+}
+
+/// Write stacks weighted by the number of nanoseconds spent waiting to
+/// acquire a lock to the given path.
+///
+/// This highlights where threads were blocked, as opposed to where they held
+/// locks, which is usually the more actionable view when looking into
+/// latency. Waits of identical stacks are summed, and acquisitions which
+/// didn't wait are left out.
+///
+/// ```no_run
+/// let events = unlock::drain();
+/// unlock::folded::write_wait("waits.folded", &events)?;
+/// # Ok::<_, unlock::Error>(())
+/// ```
+pub fn write_wait<P>(path: P, events: &Events) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    write_file(path, |out| write_wait_to(out, events))
+}
+
+/// Write stacks weighted by the number of nanoseconds spent waiting to
+/// acquire a lock to the given output.
+///
+/// See [`write_wait`] for details.
+///
+/// ```
+/// let events = unlock::drain();
+/// let mut out = Vec::new();
+/// unlock::folded::write_wait_to(&mut out, &events)?;
+/// # Ok::<_, unlock::Error>(())
+/// ```
+pub fn write_wait_to<W>(out: W, events: &Events) -> Result<(), Error>
+where
+    W: io::Write,
+{
+    let holds = events.holds();
+
+    write_weighted(out, events, |event| {
+        let open = holds.acquired(event.id)?;
+        Some(open.saturating_sub(event.timestamp))
+    })
+}
+
+/// Create the file at `path` and write to it through a buffer.
+fn write_file<P, F>(path: P, write: F) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+{
+    let mut out = BufWriter::new(File::create(path)?);
+    write(&mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Write the stack of every critical section with the weight returned by
+/// `weight`, summing identical stacks.
+fn write_weighted<W, F>(mut out: W, events: &Events, mut weight: F) -> Result<(), Error>
+where
+    W: io::Write,
+    F: FnMut(&Event) -> Option<u64>,
+{
+    let mut stacks = BTreeMap::<String, u64>::new();
+
+    for event in &events.enters {
+        if event.parent.is_some() {
+            continue;
+        }
+
+        let Some(weight) = weight(event).filter(|weight| *weight > 0) else {
+            continue;
+        };
+
+        let total = stacks.entry(stack(event)).or_default();
+        *total = total.saturating_add(weight);
+    }
+
+    for (stack, weight) in stacks {
+        writeln!(out, "{stack} {weight}")?;
+    }
+
+    Ok(())
+}
+
+/// Construct the folded stack of the given event.
+fn stack(event: &Event) -> String {
+    let mut frames = Vec::new();
+
+    if let Some(backtrace) = event.backtrace() {
+        for line in backtrace.as_str().lines() {
+            let Some(symbol) = frame_symbol(line) else {
+                continue;
+            };
+
+            if SKIPPED_FRAMES.iter().any(|skip| symbol.starts_with(skip)) {
+                continue;
+            }
+
+            frames.push(symbol);
+        }
+    }
+
+    if frames.is_empty() {
+        let type_name = event.type_name();
+        let index = event.lock.index();
+        return sanitize(&format!("{type_name} #{index}"));
+    }
+
+    let mut stack = String::new();
+
+    // Backtraces list the innermost frame first.
+    for frame in frames.into_iter().rev() {
+        if !stack.is_empty() {
+            stack.push(';');
+        }
+
+        stack.push_str(&sanitize(frame));
+    }
+
+    stack
+}
+
+/// Replace characters which have a special meaning in folded stacks.
+fn sanitize(frame: &str) -> String {
+    frame.replace([';', '\n'], ",")
+}
@@ -0,0 +1,196 @@
+//! Module to format captured lock events as a text Gantt chart.
+//!
+//! This is useful for quickly viewing a trace in a terminal, such as over SSH
+//! or in CI logs, without a browser. Every thread is rendered as a row of
+//! characters, where each column covers an equally long period of time and the
+//! character indicates which lock was held or waited for during it.
+//!
+//! ```text
+//! thread 0 |AAAAaaBBB.....|
+//! thread 1 |...aaaaAAAbBB.|
+//! ```
+//!
+//! Upper case letters mark that a lock was held and lower case letters that
+//! the thread was waiting for it. A `*` marks that several locks were involved
+//! during the same period of time, and `.` that no lock was.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::time::Duration;
+
+use crate::layout::position;
+use crate::{Error, Event, Events, LockId};
+
+/// Symbols used to identify locks.
+const SYMBOLS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// The state of a single column in a row.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    /// No lock was involved.
+    Idle,
+    /// A single lock was involved, and whether it was held at some point.
+    Lock(usize, bool),
+    /// Several locks were involved.
+    Many,
+}
+
+impl Cell {
+    /// Merge a lock into the cell.
+    fn merge(self, lock: usize, held: bool) -> Self {
+        match self {
+            Cell::Idle => Cell::Lock(lock, held),
+            Cell::Lock(current, was_held) if current == lock => Cell::Lock(lock, was_held || held),
+            _ => Cell::Many,
+        }
+    }
+
+    /// The character representing the cell.
+    fn to_char(self) -> char {
+        match self {
+            Cell::Idle => '.',
+            Cell::Lock(lock, held) => {
+                let symbol = SYMBOLS.get(lock).map_or('?', |&b| char::from(b));
+
+                if held {
+                    symbol
+                } else {
+                    symbol.to_ascii_lowercase()
+                }
+            }
+            Cell::Many => '*',
+        }
+    }
+}
+
+/// Write events as a text Gantt chart, `width` columns wide, to the given
+/// output.
+///
+/// # Errors
+///
+/// If there are no events to render, such as when [`capture`] was never
+/// called, this returns [`Error::EmptyTrace`] without writing anything.
+///
+/// ```
+/// let events = unlock::drain();
+/// let mut out = Vec::new();
+/// let result = unlock::gantt::write(&mut out, &events, 80);
+/// assert!(matches!(result, Err(unlock::Error::EmptyTrace)));
+/// ```
+///
+/// [`capture`]: crate::capture
+pub fn write<W>(mut out: W, events: &Events, width: usize) -> Result<(), Error>
+where
+    W: io::Write,
+{
+    let Some(span) = events.span() else {
+        return Err(Error::EmptyTrace);
+    };
+
+    let width = width.max(1);
+    let closes = events.closes();
+
+    let mut locks = BTreeMap::<LockId, &'static str>::new();
+    let mut children = HashMap::<_, Vec<&Event>>::new();
+
+    for event in &events.enters {
+        locks.insert(event.lock, event.type_name());
+
+        if let Some(parent) = event.parent {
+            children.entry(parent).or_default().push(event);
+        }
+    }
+
+    let symbols = locks
+        .keys()
+        .enumerate()
+        .map(|(n, lock)| (*lock, n))
+        .collect::<HashMap<_, _>>();
+
+    let mut rows = BTreeMap::<usize, Vec<Cell>>::new();
+
+    for event in events.enters.iter().filter(|e| e.parent.is_none()) {
+        let Some(close) = closes.get(&event.id).copied() else {
+            continue;
+        };
+
+        let row = rows
+            .entry(event.thread_index)
+            .or_insert_with(|| vec![Cell::Idle; width]);
+
+        let symbol = symbols[&event.lock];
+
+        // The lock is held from when it was acquired, which is when the last
+        // nested wait was closed.
+        let mut acquired = event.timestamp;
+
+        for child in children.get(&event.id).into_iter().flatten() {
+            let Some(child_close) = closes.get(&child.id).copied() else {
+                continue;
+            };
+
+            fill(row, span, child.timestamp, child_close, symbol, false);
+            acquired = acquired.max(child_close);
+        }
+
+        fill(row, span, acquired, close, symbol, true);
+    }
+
+    let (start, end) = span;
+    let duration = Duration::from_nanos(end - start);
+    let bucket = Duration::from_nanos((end - start) / width as u64);
+
+    writeln!(
+        out,
+        "{duration:?} across {width} columns ({bucket:?} per column)"
+    )?;
+
+    let headings = rows
+        .keys()
+        .map(|thread_index| (*thread_index, format!("thread {thread_index}")))
+        .collect::<Vec<_>>();
+
+    let heading_width = headings.iter().map(|(_, h)| h.len()).max().unwrap_or(0);
+
+    for (thread_index, heading) in headings {
+        let row = rows[&thread_index]
+            .iter()
+            .map(|c| c.to_char())
+            .collect::<String>();
+        writeln!(out, "{heading:>heading_width$} |{row}|")?;
+    }
+
+    writeln!(out)?;
+
+    for (n, (lock, type_name)) in locks.iter().enumerate() {
+        let symbol = Cell::Lock(n, true).to_char();
+        let kind = lock.kind();
+        let index = lock.index();
+        writeln!(
+            out,
+            "{symbol} = {kind:?}<{type_name}> (lock index: {index})"
+        )?;
+    }
+
+    writeln!(
+        out,
+        "upper case = held, lower case = waiting, * = several locks, . = idle"
+    )?;
+
+    Ok(())
+}
+
+/// Fill the columns of the row covered by the interval `open` to `close`.
+fn fill(row: &mut [Cell], span: (u64, u64), open: u64, close: u64, lock: usize, held: bool) {
+    let Some((left, width)) = position(span, open, close) else {
+        return;
+    };
+
+    let columns = row.len();
+    let first = ((left * columns as f32) as usize).min(columns - 1);
+    let last = (((left + width) * columns as f32).ceil() as usize).clamp(first + 1, columns);
+
+    for cell in &mut row[first..last] {
+        *cell = cell.merge(lock, held);
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use crate::LockId;
+
+/// The state of a lock acquisition which is currently in progress, as
+/// reported by [`held_locks`][crate::held_locks].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LockState {
+    /// The lock is held by a thread.
+    Held {
+        /// The lock which is held.
+        lock: LockId,
+        /// The index of the thread holding the lock.
+        thread: usize,
+        /// When the lock was acquired, relative to when the capture was
+        /// started.
+        since: Duration,
+    },
+    /// A thread is blocked waiting to acquire the lock.
+    Waiting {
+        /// The lock being waited for.
+        lock: LockId,
+        /// The index of the thread which is waiting.
+        thread: usize,
+        /// When the thread started waiting, relative to when the capture was
+        /// started.
+        since: Duration,
+        /// The index of a thread which currently holds the lock, if any.
+        blocked_by: Option<usize>,
+    },
+}
@@ -1,159 +1,565 @@
 //! Module to format captured lock events as html.
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
 use std::path::Path;
 use std::time::Duration;
 
 use crate::event::EventId;
-use crate::{Event, Events};
+use crate::layout::{position, row_span, Layout};
+use crate::park::DEFAULT_PARK_THRESHOLD;
+use crate::{CallSite, Episode, Error, Event, Events, LockId, Outcome};
+
+/// The default minimum width of a section in pixels.
+const DEFAULT_MIN_SECTION_WIDTH: u32 = 1;
+
+/// The number of backtrace frames shown when hovering a section.
+const HOVER_FRAMES: usize = 4;
 
 const STYLE: &[u8] = include_bytes!("trace.css");
 const SCRIPT: &[u8] = include_bytes!("trace.js");
 
-/// Write events to the given path.
-pub fn write<P>(path: P, events: &Events) -> io::Result<()>
+/// The style used to render the details of each event.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DetailStyle {
+    /// Render one row per event with separate columns for when the event was
+    /// opened, closed and its duration.
+    #[default]
+    Full,
+    /// Render a single compact row per event, with only its name, duration
+    /// and a toggle to expand its backtrace.
+    Compact,
+}
+
+impl DetailStyle {
+    /// The number of columns used by the style.
+    fn columns(self) -> usize {
+        match self {
+            DetailStyle::Full => 6,
+            DetailStyle::Compact => 3,
+        }
+    }
+}
+
+/// How events are grouped into timelines.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GroupBy {
+    /// Render one timeline per thread.
+    #[default]
+    Thread,
+    /// Render one timeline per task, as set through
+    /// [`set_task_id`][crate::set_task_id].
+    Task,
+}
+
+/// Options used when writing html.
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    detail_style: DetailStyle,
+    coalesce: Option<u32>,
+    group_by: GroupBy,
+    window: Option<(u64, u64)>,
+    park_threshold: Option<Duration>,
+    skip_assets: bool,
+    min_section_width: Option<u32>,
+    expand_all: bool,
+}
+
+impl Options {
+    /// Construct the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the style used to render event details.
+    pub fn detail_style(mut self, detail_style: DetailStyle) -> Self {
+        self.detail_style = detail_style;
+        self
+    }
+
+    /// Coalesce runs of events which are too short to be visible into a
+    /// single block, assuming that each timeline is rendered `width` pixels
+    /// wide.
+    ///
+    /// Events shorter than a pixel which are separated by less than a pixel
+    /// are merged into one block showing the number of events and their
+    /// aggregate timing. This drastically reduces the size of the document for
+    /// large traces.
+    pub fn coalesce(mut self, width: u32) -> Self {
+        self.coalesce = Some(width.max(1));
+        self
+    }
+
+    /// Set how events are grouped into timelines.
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    /// Only render the window of time from `start` to `end`, in nanoseconds
+    /// since the capture was started.
+    ///
+    /// Sections which straddle the edges of the window are truncated.
+    pub fn window(mut self, start: u64, end: u64) -> Self {
+        self.window = Some((start, end));
+        self
+    }
+
+    /// Set the threshold above which a wait is marked as having likely parked
+    /// the waiting thread, as opposed to only spinning briefly.
+    ///
+    /// Defaults to 10 microseconds. See [`Events::park_rate`] for details.
+    pub fn park_threshold(mut self, threshold: Duration) -> Self {
+        self.park_threshold = Some(threshold);
+        self
+    }
+
+    /// Set whether the `.css` and `.js` files referenced by the html file are
+    /// written next to it.
+    ///
+    /// Defaults to `true`. If disabled, only the html file is written, which
+    /// is useful if the directory it's written to is shared or the assets are
+    /// served from elsewhere. The html file still references the assets by
+    /// the same file names, so they have to be provided separately. To
+    /// include the assets in the document instead, use [`write_to`].
+    pub fn write_assets(mut self, write_assets: bool) -> Self {
+        self.skip_assets = !write_assets;
+        self
+    }
+
+    /// Set the minimum width of a section in pixels.
+    ///
+    /// Sections are positioned as a fraction of the trace, so short sections
+    /// in a long trace would otherwise be too narrow to be seen or hovered.
+    /// Sections are widened to at least this width, which might make them
+    /// appear to last longer than they did. A width of zero renders sections
+    /// at their exact width.
+    ///
+    /// Defaults to 1 pixel.
+    pub fn min_section_width(mut self, pixels: u32) -> Self {
+        self.min_section_width = Some(pixels);
+        self
+    }
+
+    /// Set whether the timelines and details of every lock are expanded
+    /// when the document is opened.
+    ///
+    /// Defaults to `false`, in which case each lock is collapsed into its
+    /// title and a one-line summary of its statistics, and can be expanded by
+    /// clicking on its title. This makes traces with many locks easier to
+    /// navigate.
+    pub fn expand_all(mut self, expand_all: bool) -> Self {
+        self.expand_all = expand_all;
+        self
+    }
+
+    /// Construct the style positioning a section at `left` percent of the
+    /// trace, covering `width` percent of it.
+    fn style(&self, left: f32, width: f32) -> String {
+        let min_width = self.min_section_width.unwrap_or(DEFAULT_MIN_SECTION_WIDTH);
+        format!("width: {width:.4}%; left: {left:.4}%; min-width: {min_width}px;")
+    }
+
+    /// The threshold above which a wait is considered to have parked.
+    fn park_threshold_or_default(&self) -> Duration {
+        self.park_threshold.unwrap_or(DEFAULT_PARK_THRESHOLD)
+    }
+}
+
+/// Write events to the given path using the default [`Options`].
+///
+/// This also writes a `.css` and a `.js` file with the same file stem next to
+/// the html file, unless disabled through [`Options::write_assets`].
+///
+/// # Errors
+///
+/// If there are no events to render, such as when [`capture`] was never
+/// called, this returns [`Error::EmptyTrace`] without writing any files.
+///
+/// ```
+/// let events = unlock::drain();
+/// let result = unlock::html::write("trace.html", &events);
+/// assert!(matches!(result, Err(unlock::Error::EmptyTrace)));
+/// ```
+///
+/// [`capture`]: crate::capture
+pub fn write<P>(path: P, events: &Events) -> Result<(), Error>
 where
     P: AsRef<Path>,
 {
-    let path = path.as_ref();
+    write_with(path, events, &Options::new())
+}
 
-    let file_stem = path.file_stem().ok_or_else(|| {
-        io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "Missing file stem from the specified path",
-        )
-    })?;
+/// Write the events within the window of time from `start` to `end` to the
+/// given path.
+///
+/// Timestamps are in nanoseconds since the capture was started. Sections which
+/// straddle the edges of the window are truncated. See [`write()`] for more
+/// details.
+///
+/// # Errors
+///
+/// If the window is empty, this returns [`Error::EmptyTrace`].
+pub fn write_window<P>(path: P, events: &Events, start: u64, end: u64) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    write_with(path, events, &Options::new().window(start, end))
+}
 
-    let parent = path.parent().ok_or_else(|| {
-        io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "Missing parent from the specified path",
-        )
-    })?;
+/// Write events to the given path using the specified [`Options`].
+///
+/// See [`write()`] for more details.
+pub fn write_with<P>(path: P, events: &Events, options: &Options) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    write_filtered(path, events, options, |_| true)
+}
+
+/// Write only the critical sections matching `keep` to the given path, using
+/// the specified [`Options`].
+///
+/// Unlike filtering with [`Events::filter`] first, this renders directly from
+/// the borrowed collection without copying any events, which is useful when
+/// producing many focused reports from one large capture. Events nested in a
+/// kept critical section are always included. All reports share the time axis
+/// of the whole collection, and the header and per-lock statistics describe
+/// the whole collection.
+///
+/// See [`write()`] for more details.
+///
+/// ```
+/// use unlock::html::{self, Options};
+///
+/// let events = unlock::drain();
+///
+/// let result = html::write_filtered("trace.html", &events, &Options::new(), |event| {
+///     event.thread_index() == 0
+/// });
+///
+/// assert!(matches!(result, Err(unlock::Error::EmptyTrace)));
+/// ```
+pub fn write_filtered<P, F>(
+    path: P,
+    events: &Events,
+    options: &Options,
+    mut keep: F,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Event) -> bool,
+{
+    let Some(layout) = Layout::new(events, options.group_by, options.window, &mut keep) else {
+        return Err(Error::EmptyTrace);
+    };
+
+    let path = path.as_ref();
+    let invalid_path = || Error::InvalidPath(path.to_owned());
+
+    let file_stem = path.file_stem().ok_or_else(invalid_path)?;
+    let parent = path.parent().ok_or_else(invalid_path)?;
 
     let css = parent.join(file_stem).with_extension("css");
     let script = parent.join(file_stem).with_extension("js");
 
-    std::fs::write(&css, STYLE)?;
-    std::fs::write(&script, SCRIPT)?;
+    if !options.skip_assets {
+        std::fs::write(&css, STYLE)?;
+        std::fs::write(&script, SCRIPT)?;
+    }
 
     let css = css
         .file_name()
         .and_then(|name| name.to_str())
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid css file name"))?;
+        .ok_or_else(invalid_path)?;
 
     let script = script
         .file_name()
         .and_then(|name| name.to_str())
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid script file name"))?;
+        .ok_or_else(invalid_path)?;
 
     let mut out = std::fs::File::create(path)?;
+    write_document(
+        &mut out,
+        events,
+        options,
+        layout,
+        Assets::Linked { css, script },
+    )?;
+    Ok(())
+}
 
-    // Start of trace.
-    let mut start = u64::MAX;
-    // End of trace.
-    let mut end = u64::MIN;
-
-    let mut opens = BTreeMap::<_, BTreeMap<_, Vec<_>>>::new();
-    let mut children = HashMap::<_, Vec<_>>::new();
-    let mut closes = HashMap::new();
-
-    for enter in &events.enters {
-        start = start.min(enter.timestamp);
-
-        if let Some(parent) = enter.parent {
-            children.entry(parent).or_default().push(enter);
-        } else {
-            opens
-                .entry((enter.lock, enter.type_name.as_ref()))
-                .or_default()
-                .entry(enter.thread_index)
-                .or_default()
-                .push(enter);
-        }
-    }
+/// Write events as a single self-contained html document to the given
+/// output, using the specified [`Options`].
+///
+/// Unlike [`write()`], the style and script used by the document are included
+/// in it rather than written to separate files, so nothing is written to the
+/// filesystem. This is useful for writing to an in-memory buffer or the body
+/// of an HTTP response.
+///
+/// # Errors
+///
+/// If there are no events to render, this returns [`Error::EmptyTrace`]
+/// without writing anything.
+///
+/// ```
+/// use unlock::html::{self, Options};
+///
+/// let events = unlock::drain();
+/// let mut out = Vec::new();
+///
+/// let result = html::write_to(&mut out, &events, &Options::new());
+/// assert!(matches!(result, Err(unlock::Error::EmptyTrace)));
+/// ```
+pub fn write_to<W>(mut out: W, events: &Events, options: &Options) -> Result<(), Error>
+where
+    W: io::Write,
+{
+    let Some(layout) = Layout::new(events, options.group_by, options.window, &mut |_| true) else {
+        return Err(Error::EmptyTrace);
+    };
 
-    for leave in &events.leaves {
-        end = end.max(leave.timestamp);
-        closes.insert(leave.sibling, leave.timestamp);
-    }
+    write_document(&mut out, events, options, layout, Assets::Inline)?;
+    Ok(())
+}
 
-    if start == u64::MAX || end == u64::MIN {
-        return Ok(());
-    }
+/// How the style and script of a document are provided.
+enum Assets<'a> {
+    /// Referenced by file name, next to the document.
+    Linked { css: &'a str, script: &'a str },
+    /// Included in the document.
+    Inline,
+}
+
+fn write_document(
+    out: &mut dyn io::Write,
+    events: &Events,
+    options: &Options,
+    layout: Layout<'_>,
+    assets: Assets<'_>,
+) -> io::Result<()> {
+    let Layout {
+        span: (start, end),
+        locks: opens,
+        children,
+        closes,
+    } = layout;
 
     writeln!(out, "<!DOCTYPE html>")?;
     writeln!(out, "<html>")?;
     writeln!(out, "<head>")?;
-    writeln!(out, r#"<link href="{css}" rel="stylesheet">"#)?;
+
+    match assets {
+        Assets::Linked { css, .. } => {
+            writeln!(out, r#"<link href="{css}" rel="stylesheet">"#)?;
+        }
+        Assets::Inline => {
+            writeln!(out, "<style>")?;
+            out.write_all(STYLE)?;
+            writeln!(out, "</style>")?;
+        }
+    }
+
     writeln!(out, "</head>")?;
 
+    let max_duration = events
+        .enters
+        .iter()
+        .flat_map(|ev| Some(closes.get(&ev.id)?.saturating_sub(ev.timestamp)))
+        .max()
+        .unwrap_or(0);
+
     writeln!(out, "<body>")?;
+    write_header(out, events, (start, end))?;
+    writeln!(out, r#"<div id="controls">"#)?;
+    writeln!(
+        out,
+        r#"<label for="duration-filter">Hide sections shorter than</label>"#
+    )?;
+    writeln!(
+        out,
+        r#"<input type="range" id="duration-filter" min="0" max="1000" value="0" data-max="{max_duration}">"#
+    )?;
+    writeln!(out, r#"<span id="duration-filter-value"></span>"#)?;
+    writeln!(out, "</div>")?;
+
+    if events.dropped > 0 {
+        let dropped = events.dropped;
+
+        writeln!(
+            out,
+            r#"<div class="warning">{dropped} events dropped; results incomplete</div>"#
+        )?;
+    }
+
+    if events.thread_index_conflicts > 0 {
+        let conflicts = events.thread_index_conflicts;
+
+        writeln!(
+            out,
+            r#"<div class="warning">{conflicts} thread index conflicts; some threads might be merged</div>"#
+        )?;
+    }
+
     writeln!(out, "<div id=\"traces\">")?;
 
-    for ((lock, type_name), events) in opens {
-        writeln!(out, "<div class=\"lock-instance\">")?;
+    let episodes = opens
+        .keys()
+        .filter_map(|&(_, lock, _)| Some((lock, events.worst_episode(lock)?)))
+        .collect::<HashMap<_, _>>();
+
+    // Sections which are part of the worst episode of their lock are
+    // highlighted.
+    let mut highlighted = HashSet::new();
+
+    for episode in episodes.values() {
+        highlighted.insert(episode.acquisition().id);
+        highlighted.extend(episode.holders().iter().map(|hold| hold.event().id));
+    }
+
+    let summary = events.summary();
+
+    let lock_class = if options.expand_all {
+        "lock-instance"
+    } else {
+        "lock-instance collapsed"
+    };
+
+    let mut current_process = None;
+
+    for ((process, lock, type_name), lock_events) in opens {
+        // Group the locks of each process, if there's information about it.
+        if current_process != Some(process) {
+            if current_process.take().is_some() {
+                writeln!(out, "</div>")?;
+            }
+
+            if let Some(info) = events.processes.iter().find(|p| p.id == process) {
+                let label = match info.label() {
+                    Some(label) => format!(" ({})", escape(label)),
+                    None => String::new(),
+                };
+
+                writeln!(out, r#"<div class="process">"#)?;
+                writeln!(
+                    out,
+                    r#"<div class="process-title">Process {process}{label}</div>"#
+                )?;
+                current_process = Some(process);
+            }
+        }
+
+        writeln!(out, r#"<div class="{lock_class}">"#)?;
 
         let kind = lock.kind();
-        let index = lock.index();
 
-        let type_name = type_name.replace('<', "&lt;").replace('>', "&gt");
+        // Prefer the key of the lock since it's stable across runs.
+        let identity = match events.lock_key(lock) {
+            Some(key) => format!("key: {}", escape(key)),
+            None => format!("lock index: {}", lock.index()),
+        };
+
+        let type_name = escape(type_name);
+
+        let utilization = events.utilization(lock) * 100.0;
+        let park_rate = events.park_rate(lock, options.park_threshold_or_default()) * 100.0;
+
+        let tooltip = match events.lock_creation_site(lock) {
+            Some(backtrace) => format!(" title=\"Created at:\n{}\"", escape(backtrace.as_str())),
+            None => String::new(),
+        };
+
+        writeln!(
+            out,
+            r#"<div class="title lock-title"{tooltip}>{kind:?}&lt;{type_name}&gt; ({identity}, utilization: {utilization:.1}%, parked: {park_rate:.1}%)</div>"#
+        )?;
+
+        let acquisitions = summary.hold_count(lock);
+        let hold_total = summary.hold_total(lock);
+        let wait_total = summary.wait_total(lock);
+        let wait_max = summary.wait_max(lock).unwrap_or_default();
 
         writeln!(
             out,
-            r#"<div class="title">{kind:?}&lt;{type_name}&gt; (lock index: {index})</div>"#
+            r#"<div class="lock-summary">{acquisitions} acquisitions, held for {hold_total:?}, waited for {wait_total:?} (max {wait_max:?})</div>"#
         )?;
 
+        writeln!(out, r#"<div class="lock-body">"#)?;
+
+        write_call_sites(out, events, lock)?;
+
+        if let Some(episode) = episodes.get(&lock) {
+            write_worst_episode(out, episode)?;
+        }
+
         writeln!(out, "<div class=\"lock-session\">")?;
 
-        for (thread_index, events) in events.into_iter() {
-            let start = events.iter().map(|e| e.timestamp).min().unwrap_or(0);
+        let thread_names = events.thread_names.get(&process);
 
-            let end = events
-                .iter()
-                .flat_map(|ev| closes.get(&ev.id).copied())
-                .max()
-                .unwrap_or(0);
+        for (row, events) in lock_events.into_iter() {
+            let (row_start, row_end) = row_span(&events, &closes, end);
+            let (start, end) = (row_start.max(start), row_end.min(end));
 
             writeln!(
                 out,
-                r#"<div data-toggle="event-{lock}-{thread_index}-details" data-start="{start}" data-end="{end}" class="timeline">"#
+                r#"<div data-toggle="event-{lock}-{row}-details" data-start="{start}" data-end="{end}" class="timeline">"#
             )?;
 
+            let heading = escape(&row.heading(thread_names));
+
             writeln!(
                 out,
-                r#"<div class="timeline-heading"><span>{thread_index}</span></div>"#
+                r#"<div class="timeline-heading"><span>{heading}</span></div>"#
             )?;
 
             writeln!(out, r#"<div class="timeline-data">"#)?;
 
             let mut details = Vec::new();
 
-            for ev in events {
+            let threshold = options
+                .coalesce
+                .map(|width| end.saturating_sub(start) / u64::from(width))
+                .unwrap_or(0);
+
+            for item in coalesce(events, &closes, threshold) {
+                let ev = match item {
+                    Item::Event(ev) => ev,
+                    Item::Coalesced(run) => {
+                        write_coalesced(out, &run, (start, end), options, &mut details)?;
+                        continue;
+                    }
+                };
+
                 let open = ev.timestamp;
                 let id = ev.id;
 
-                let Some(close) = closes.get(&ev.id).copied() else {
-                    return Ok(());
-                };
+                // Sections which were never closed, such as when a thread
+                // panicked while holding a lock, are still held at the end.
+                let close = closes.get(&ev.id).copied().unwrap_or(end);
+
+                let columns = options.detail_style.columns();
 
                 writeln! {
                     details,
                     r#"
                     <tr data-entry data-entry-start="{open}" data-entry-close="{close}">
-                        <td class="title" colspan="6">Event: {id}</td>
+                        <td class="title" colspan="{columns}">Event: {id}</td>
                     </tr>
                     "#
                 }?;
 
                 write_section(
-                    &mut out,
+                    out,
                     ev,
                     (start, end),
                     close,
                     &children,
                     &closes,
+                    &highlighted,
+                    options,
+                    0,
                     &mut details,
                 )?;
             }
@@ -165,7 +571,7 @@ where
             if !details.is_empty() {
                 writeln!(
                     out,
-                    r#"<table id="event-{lock}-{thread_index}-details" class="details">"#
+                    r#"<table id="event-{lock}-{row}-details" class="details">"#
                 )?;
 
                 out.write_all(&details)?;
@@ -175,82 +581,502 @@ where
 
         writeln!(out, "</div>")?;
         writeln!(out, "</div>")?;
+        writeln!(out, "</div>")?;
+    }
+
+    if current_process.is_some() {
+        writeln!(out, "</div>")?;
     }
 
     writeln!(out, "</div>")?;
+
+    match assets {
+        Assets::Linked { script, .. } => {
+            writeln!(
+                out,
+                r#"<script type="text/javascript" src="{script}"></script>"#
+            )?;
+        }
+        Assets::Inline => {
+            writeln!(out, r#"<script type="text/javascript">"#)?;
+            out.write_all(SCRIPT)?;
+            writeln!(out, "</script>")?;
+        }
+    }
+
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    Ok(())
+}
+
+/// Classes of sections which are explained in the legend.
+const LEGEND: &[(&str, &str)] = &[
+    ("critical", "critical section, from request to release"),
+    (
+        "contended",
+        "critical section which had to wait for the lock",
+    ),
+    ("read", "waiting for read"),
+    ("read_recursive", "waiting for recursive read"),
+    ("write", "waiting for write"),
+    ("try_read", "read acquired without blocking"),
+    ("try_write", "write acquired without blocking"),
+    ("upgradable_read", "waiting for upgradable read"),
+    ("lock", "waiting for lock"),
+    ("try_lock", "lock acquired without blocking"),
+    ("try_lock_for", "waiting for lock with timeout"),
+    ("wait", "waiting on condition variable"),
+    ("timed-out", "timed out"),
+    ("try-failed", "attempt without blocking failed"),
+    ("unclosed", "never released before the end of the trace"),
+    ("parked", "wait which likely parked the thread"),
+    ("coalesced", "coalesced events"),
+];
+
+fn write_header(out: &mut dyn io::Write, events: &Events, span: (u64, u64)) -> io::Result<()> {
+    let (start, end) = span;
+
+    let threads = events
+        .enters
+        .iter()
+        .map(|ev| ev.thread_index)
+        .collect::<HashSet<_>>()
+        .len();
+
+    let locks = events
+        .enters
+        .iter()
+        .map(|ev| ev.lock)
+        .collect::<HashSet<_>>()
+        .len();
+
+    let count = events.len();
+    let duration = Duration::from_nanos(end.saturating_sub(start));
+
+    writeln!(out, r#"<div id="header">"#)?;
+    writeln!(out, r#"<table class="statistics">"#)?;
+    writeln!(out, "<tr><td>Events:</td><td>{count}</td></tr>")?;
+    writeln!(out, "<tr><td>Threads:</td><td>{threads}</td></tr>")?;
+    writeln!(out, "<tr><td>Locks:</td><td>{locks}</td></tr>")?;
+    writeln!(out, "<tr><td>Duration:</td><td>{duration:?}</td></tr>")?;
+    writeln!(out, "</table>")?;
+
+    writeln!(out, r#"<div class="legend">"#)?;
+
+    for (class, description) in LEGEND {
+        writeln!(
+            out,
+            r#"<div class="legend-item"><span class="section {class}"></span>{description}</div>"#
+        )?;
+    }
+
     writeln!(
         out,
-        r#"<script type="text/javascript" src="{script}"></script>"#
+        r#"<div class="legend-item"><span class="section waiters-3"></span>contended (more waiters are hotter)</div>"#
     )?;
-    writeln!(out, "</body>")?;
-    writeln!(out, "</html>")?;
+    writeln!(out, "</div>")?;
+    writeln!(out, "</div>")?;
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-fn write_section(
-    out: &mut dyn io::Write,
-    ev: &Event,
-    span: (u64, u64),
+/// An item to render in a timeline.
+enum Item<'a> {
+    /// A single event.
+    Event(&'a Event),
+    /// A run of coalesced events.
+    Coalesced(Run),
+}
+
+/// A run of short events which have been coalesced.
+struct Run {
+    count: usize,
+    open: u64,
     close: u64,
-    children: &HashMap<EventId, Vec<&Event>>,
+    total: u64,
+}
+
+/// Coalesce runs of events shorter than `threshold` which are separated by
+/// less than `threshold`.
+fn coalesce<'a>(
+    events: Vec<&'a Event>,
     closes: &HashMap<EventId, u64>,
-    d: &mut Vec<u8>,
-) -> io::Result<()> {
-    let id = ev.id;
-    let title = ev.name.as_ref();
-    let open = ev.timestamp;
+    threshold: u64,
+) -> Vec<Item<'a>> {
+    if threshold == 0 {
+        return events.into_iter().map(Item::Event).collect();
+    }
 
-    let (start, end) = span;
+    let mut items = Vec::with_capacity(events.len());
+    let mut pending = Vec::new();
 
-    if start == end {
-        return Ok(());
+    fn flush<'a>(items: &mut Vec<Item<'a>>, pending: &mut Vec<(&'a Event, u64)>) {
+        match pending.as_slice() {
+            [] => {}
+            [(ev, _)] => items.push(Item::Event(ev)),
+            [(first, _), .., (_, close)] => items.push(Item::Coalesced(Run {
+                count: pending.len(),
+                open: first.timestamp,
+                close: *close,
+                total: pending.iter().map(|(ev, close)| close - ev.timestamp).sum(),
+            })),
+        }
+
+        pending.clear();
+    }
+
+    for ev in events {
+        let short = closes
+            .get(&ev.id)
+            .filter(|close| close.saturating_sub(ev.timestamp) < threshold);
+
+        let Some(close) = short.copied() else {
+            flush(&mut items, &mut pending);
+            items.push(Item::Event(ev));
+            continue;
+        };
+
+        if let Some((_, last)) = pending.last() {
+            if ev.timestamp.saturating_sub(*last) >= threshold {
+                flush(&mut items, &mut pending);
+            }
+        }
+
+        pending.push((ev, close));
     }
 
-    let total = (end - start) as f32;
+    flush(&mut items, &mut pending);
+    items
+}
 
-    let left = (((open - start) as f32 / total) * 100.0).round() as u32;
-    let width = (((close - open) as f32 / total) * 100.0).round() as u32;
+fn write_coalesced(
+    out: &mut dyn io::Write,
+    run: &Run,
+    span: (u64, u64),
+    options: &Options,
+    d: &mut Vec<u8>,
+) -> io::Result<()> {
+    let Run {
+        count,
+        open,
+        close,
+        total,
+    } = *run;
+
+    let Some((left, width)) = position(span, open, close) else {
+        return Ok(());
+    };
+
+    let (left, width) = (left * 100.0, width * 100.0);
 
     let s = Duration::from_nanos(open);
     let e = Duration::from_nanos(close);
-    let duration = Duration::from_nanos(close - open);
+    let total = Duration::from_nanos(total);
+    let nanos = close - open;
 
-    let style = format!("width: {width}%; left: {left}%;");
-    let hover_title = format!("{title} ({s:?}-{e:?})");
+    let style = options.style(left, width);
+    let hover_title = format!("{count} events ({s:?}-{e:?}, total {total:?})");
 
     writeln!(
         out,
-        "<div id=\"event-{id}\" class=\"section {title}\" style=\"{style}\" title=\"{hover_title}\"></div>"
+        "<div class=\"section coalesced\" style=\"{style}\" title=\"{hover_title}\" data-duration=\"{nanos}\"></div>"
     )?;
 
+    let columns = options.detail_style.columns();
+
     writeln! {
         d,
         r#"
         <tr data-entry data-entry-start="{open}" data-entry-close="{close}">
-            <td class="title {title}">{title}</td>
-            <td>{s:?}</td>
-            <td>&mdash;</td>
-            <td>{e:?}</td>
-            <td>({duration:?})</td>
-            <td width="100%"></td>
+            <td class="title coalesced" colspan="{columns}">{count} events coalesced ({s:?} &mdash; {e:?}, total {total:?})</td>
         </tr>
         "#
     }?;
 
-    if let Some(backtrace) = &ev.backtrace {
+    Ok(())
+}
+
+fn write_call_sites(out: &mut dyn io::Write, events: &Events, lock: LockId) -> io::Result<()> {
+    let call_sites = events.call_sites(lock);
+
+    if call_sites.is_empty() {
+        return Ok(());
+    }
+
+    let len = call_sites.len();
+
+    writeln!(out, r#"<details class="call-sites">"#)?;
+    writeln!(out, "<summary>Acquired from {len} call site(s)</summary>")?;
+    writeln!(out, "<table>")?;
+
+    for (call_site, count) in call_sites {
+        let call_site = escape(&call_site.to_string());
+
         writeln!(
-            d,
-            r#"<tr><td>Backtrace:</td><td class="backtrace" colspan="5">{backtrace}</td></tr>"#
+            out,
+            r#"<tr><td class="count">{count}</td><td class="backtrace">{call_site}</td></tr>"#
         )?;
     }
 
-    for ev in children.get(&ev.id).into_iter().flatten() {
-        let Some(child_close) = closes.get(&ev.id).copied() else {
-            continue;
+    writeln!(out, "</table>")?;
+    writeln!(out, "</details>")?;
+    Ok(())
+}
+
+fn write_worst_episode(out: &mut dyn io::Write, episode: &Episode<'_>) -> io::Result<()> {
+    let acquisition = episode.acquisition();
+    let wait = episode.wait();
+    let requested = episode.requested();
+    let thread = acquisition.thread_index;
+
+    writeln!(out, r#"<details class="worst-episode">"#)?;
+    writeln!(
+        out,
+        "<summary>Longest wait: {wait:?} on thread {thread} at {requested:?}</summary>"
+    )?;
+    writeln!(out, "<table>")?;
+
+    if let Some(backtrace) = acquisition.backtrace() {
+        let backtrace = escape(backtrace.as_str());
+
+        writeln!(
+            out,
+            r#"<tr><td>Waited:</td><td class="backtrace" colspan="2">{backtrace}</td></tr>"#
+        )?;
+    }
+
+    for hold in episode.holders() {
+        let thread = hold.event().thread_index;
+        let acquired = hold.acquired();
+        let released = hold.released();
+
+        let backtrace = match hold.event().backtrace() {
+            Some(backtrace) => escape(backtrace.as_str()),
+            None => String::new(),
         };
 
-        write_section(out, ev, span, child_close, children, closes, d)?;
+        writeln!(
+            out,
+            r#"<tr><td>Held:</td><td>thread {thread}, {acquired:?} &mdash; {released:?}</td><td class="backtrace">{backtrace}</td></tr>"#
+        )?;
+    }
+
+    writeln!(out, "</table>")?;
+    writeln!(out, "</details>")?;
+    Ok(())
+}
+
+/// Escape a string so that it can be included in html.
+pub(crate) fn escape(string: &str) -> String {
+    let mut out = String::with_capacity(string.len());
+
+    for c in string.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Convert a name into a class name, replacing characters which aren't
+/// allowed in one.
+fn class_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '-',
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_section(
+    out: &mut dyn io::Write,
+    ev: &Event,
+    span: (u64, u64),
+    close: u64,
+    children: &HashMap<EventId, Vec<&Event>>,
+    closes: &HashMap<EventId, u64>,
+    highlighted: &HashSet<EventId>,
+    options: &Options,
+    depth: usize,
+    d: &mut Vec<u8>,
+) -> io::Result<()> {
+    let id = ev.id;
+    let title = escape(ev.name());
+    let class = class_name(ev.name());
+    let open = ev.timestamp;
+
+    let Some((left, width)) = position(span, open, close) else {
+        return Ok(());
+    };
+
+    let (left, width) = (left * 100.0, width * 100.0);
+
+    let s = Duration::from_nanos(open);
+    let e = Duration::from_nanos(close);
+    let nanos = close.saturating_sub(open);
+    let duration = Duration::from_nanos(nanos);
+
+    // Color sections by how contended the lock was when they were recorded.
+    let heat = match ev.waiters {
+        0 => "",
+        1 => " waiters-1",
+        2..=3 => " waiters-2",
+        _ => " waiters-3",
+    };
+
+    let outcome = match ev.outcome {
+        Outcome::TimedOut => " timed-out",
+        Outcome::TryFailed => " try-failed",
+        _ => "",
+    };
+
+    let contended = if ev.contended && ev.parent.is_none() {
+        " contended"
+    } else {
+        ""
+    };
+
+    let park_threshold = options.park_threshold_or_default().as_nanos() as u64;
+
+    let parked = if ev.parent.is_some() && nanos >= park_threshold {
+        " parked"
+    } else {
+        ""
+    };
+
+    let episode = if highlighted.contains(&id) {
+        " episode"
+    } else {
+        ""
+    };
+
+    let closed = closes.contains_key(&id);
+    let unclosed = if closed { "" } else { " unclosed" };
+
+    let style = options.style(left, width);
+
+    let mut hover_title = if closed {
+        format!("{title} ({s:?}-{e:?})")
+    } else {
+        format!("{title} ({s:?}-, never released)")
+    };
+
+    // Show where the lock was acquired when hovering, one frame per line.
+    if let Some(backtrace) = ev.backtrace() {
+        for frame in CallSite::from_backtrace(backtrace)
+            .frames()
+            .take(HOVER_FRAMES)
+        {
+            hover_title.push_str("&#10;");
+            hover_title.push_str(&escape(frame));
+        }
+    }
+
+    writeln!(
+        out,
+        "<div id=\"event-{id}\" class=\"section {class}{contended}{heat}{outcome}{parked}{episode}{unclosed}\" style=\"{style}\" title=\"{hover_title}\" data-duration=\"{nanos}\"></div>"
+    )?;
+
+    let attributes = ev
+        .attributes()
+        .map(|(key, value)| format!("{}={}", escape(key), escape(value)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    match options.detail_style {
+        DetailStyle::Full => {
+            writeln! {
+                d,
+                r#"
+                <tr data-entry data-entry-start="{open}" data-entry-close="{close}" data-depth="{depth}">
+                    <td class="title {class}">{title}</td>
+                    <td>{s:?}</td>
+                    <td>&mdash;</td>
+                    <td>{e:?}</td>
+                    <td>({duration:?})</td>
+                    <td width="100%"></td>
+                </tr>
+                "#
+            }?;
+
+            if let Some(backtrace) = ev.backtrace() {
+                let backtrace = escape(backtrace.as_str());
+
+                writeln!(
+                    d,
+                    r#"<tr><td>Backtrace:</td><td class="backtrace" colspan="5">{backtrace}</td></tr>"#
+                )?;
+            }
+
+            if !attributes.is_empty() {
+                writeln!(
+                    d,
+                    r#"<tr><td>Attributes:</td><td class="attributes" colspan="5">{attributes}</td></tr>"#
+                )?;
+            }
+
+            if ev.held > 0 {
+                let held = ev.held;
+
+                writeln!(
+                    d,
+                    r#"<tr><td>Holding:</td><td class="held" colspan="5">{held} other locks</td></tr>"#
+                )?;
+            }
+        }
+        DetailStyle::Compact => {
+            writeln!(
+                d,
+                r#"<tr data-entry data-entry-start="{open}" data-entry-close="{close}" data-depth="{depth}" class="compact">"#
+            )?;
+            writeln!(d, r#"<td class="title {class}">{title}</td>"#)?;
+            writeln!(d, "<td>({duration:?})</td>")?;
+            writeln!(d, r#"<td width="100%">"#)?;
+
+            if !attributes.is_empty() {
+                writeln!(d, r#"<span class="attributes">{attributes}</span>"#)?;
+            }
+
+            if ev.held > 0 {
+                let held = ev.held;
+                writeln!(d, r#"<span class="held">holding {held} other locks</span>"#)?;
+            }
+
+            if let Some(backtrace) = ev.backtrace() {
+                let backtrace = escape(backtrace.as_str());
+
+                writeln!(
+                    d,
+                    r#"<details><summary>Backtrace</summary><div class="backtrace">{backtrace}</div></details>"#
+                )?;
+            }
+
+            writeln!(d, "</td>")?;
+            writeln!(d, "</tr>")?;
+        }
+    }
+
+    for ev in children.get(&ev.id).into_iter().flatten() {
+        let child_close = closes.get(&ev.id).copied().unwrap_or(close);
+
+        write_section(
+            out,
+            ev,
+            span,
+            child_close,
+            children,
+            closes,
+            highlighted,
+            options,
+            depth + 1,
+            d,
+        )?;
     }
 
     Ok(())
@@ -0,0 +1,350 @@
+//! Interning of names, so that events only need to store a small index.
+
+#[cfg(feature = "serde")]
+use std::cell::RefCell;
+#[cfg(any(all(feature = "trace", feature = "parking_lot"), feature = "serde"))]
+use std::collections::HashMap;
+use std::fmt;
+use std::ptr;
+#[cfg(all(feature = "trace", feature = "parking_lot"))]
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicPtr, Ordering};
+#[cfg(any(all(feature = "trace", feature = "parking_lot"), feature = "serde"))]
+use std::sync::{PoisonError, RwLock};
+
+#[cfg(feature = "serde")]
+use serde::de::{self, Error as _, Visitor};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The maximum number of interned names.
+const MAX_NAMES: usize = 1 << 16;
+
+/// The number of names in each chunk of [`TABLE`].
+const CHUNK_LEN: usize = 256;
+
+/// A chunk of interned strings, which is allocated once the first name in it
+/// is interned.
+struct Chunk([AtomicPtr<&'static str>; CHUNK_LEN]);
+
+#[cfg(any(all(feature = "trace", feature = "parking_lot"), feature = "serde"))]
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_STRING: AtomicPtr<&'static str> = AtomicPtr::new(ptr::null_mut());
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_CHUNK: AtomicPtr<Chunk> = AtomicPtr::new(ptr::null_mut());
+
+/// Global table of interned strings by index, which can be read without
+/// locking. Chunks and strings are leaked once they've been published, and
+/// are never modified after that.
+static TABLE: [AtomicPtr<Chunk>; MAX_NAMES / CHUNK_LEN] = [NO_CHUNK; MAX_NAMES / CHUNK_LEN];
+
+/// The interned names by string, which is locked for writing while
+/// publishing new names to [`TABLE`].
+#[cfg(any(all(feature = "trace", feature = "parking_lot"), feature = "serde"))]
+static NAMES: RwLock<Option<HashMap<&'static str, Name>>> = RwLock::new(None);
+
+/// An interned name.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(transparent)]
+pub(crate) struct Name(u16);
+
+impl Name {
+    /// Intern the given string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if too many distinct names have been interned.
+    #[cfg(all(feature = "trace", feature = "parking_lot"))]
+    pub(crate) fn new(string: &'static str) -> Self {
+        let Some(name) = Self::intern(string, |string| string) else {
+            panic!("unlock: Too many interned names");
+        };
+
+        name
+    }
+
+    /// Intern the given string, leaking it if it hasn't been seen before.
+    ///
+    /// Returns `None` if too many distinct names have been interned.
+    #[cfg(feature = "serde")]
+    fn from_owned(string: String) -> Option<Self> {
+        Self::intern(string, |string| Box::leak(string.into_boxed_str()))
+    }
+
+    #[cfg(any(all(feature = "trace", feature = "parking_lot"), feature = "serde"))]
+    fn intern<S>(string: S, leak: impl FnOnce(S) -> &'static str) -> Option<Self>
+    where
+        S: AsRef<str>,
+    {
+        let names = NAMES.read().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(name) = names.as_ref().and_then(|names| names.get(string.as_ref())) {
+            return Some(*name);
+        }
+
+        drop(names);
+
+        let mut names = NAMES.write().unwrap_or_else(PoisonError::into_inner);
+        let names = names.get_or_insert_with(HashMap::new);
+
+        if let Some(name) = names.get(string.as_ref()) {
+            return Some(*name);
+        }
+
+        let index = names.len();
+        let name = Self(u16::try_from(index).ok()?);
+        let string = leak(string);
+
+        let chunk = &TABLE[index / CHUNK_LEN];
+        let mut ptr = chunk.load(Ordering::Acquire);
+
+        if ptr.is_null() {
+            ptr = Box::into_raw(Box::new(Chunk([NO_STRING; CHUNK_LEN])));
+            chunk.store(ptr, Ordering::Release);
+        }
+
+        // SAFETY: Chunks are never freed once published.
+        let chunk = unsafe { &*ptr };
+        chunk.0[index % CHUNK_LEN].store(Box::into_raw(Box::new(string)), Ordering::Release);
+
+        names.insert(string, name);
+        Some(name)
+    }
+
+    /// Get the string of the interned name.
+    pub(crate) fn as_str(self) -> &'static str {
+        let index = usize::from(self.0);
+        let chunk = TABLE[index / CHUNK_LEN].load(Ordering::Acquire);
+
+        // SAFETY: Chunks and strings are never freed once published.
+        let string = unsafe { chunk.as_ref() }.and_then(|chunk| unsafe {
+            chunk.0[index % CHUNK_LEN].load(Ordering::Acquire).as_ref()
+        });
+
+        // A name can only be observed once its string has been published.
+        match string {
+            Some(string) => string,
+            None => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for Name {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl fmt::Debug for Name {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(feature = "serde")]
+thread_local! {
+    /// The table of names which is used while serializing or deserializing a
+    /// collection of events.
+    static SERDE_TABLE: RefCell<Option<Table>> = const { RefCell::new(None) };
+}
+
+/// A table of names, which allows names to be serialized as indexes into it.
+#[cfg(feature = "serde")]
+enum Table {
+    /// Indexes of names being serialized.
+    Serialize(HashMap<Name, u32>),
+    /// Names being deserialized, by index.
+    Deserialize(Vec<Name>),
+}
+
+/// Restores the previously active table of names when dropped.
+#[cfg(feature = "serde")]
+pub(crate) struct TableGuard {
+    previous: Option<Table>,
+}
+
+#[cfg(feature = "serde")]
+impl TableGuard {
+    fn install(table: Table) -> Self {
+        let previous = SERDE_TABLE.with(|current| current.borrow_mut().replace(table));
+        Self { previous }
+    }
+
+    /// Serialize the given names as indexes into a table for as long as the
+    /// guard is alive, returning the strings of the table.
+    pub(crate) fn serialize(names: impl IntoIterator<Item = Name>) -> (Vec<&'static str>, Self) {
+        let mut indexes = HashMap::new();
+        let mut strings = Vec::new();
+
+        for name in names {
+            indexes.entry(name).or_insert_with(|| {
+                strings.push(name.as_str());
+                (strings.len() - 1) as u32
+            });
+        }
+
+        (strings, Self::install(Table::Serialize(indexes)))
+    }
+
+    /// Deserialize indexes into the table read through [`deserialize_table`]
+    /// for as long as the guard is alive.
+    pub(crate) fn deserialize() -> Self {
+        Self::install(Table::Deserialize(Vec::new()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Drop for TableGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        SERDE_TABLE.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Deserialize a table of names, which later names can be deserialized as
+/// indexes into.
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_table<'de, D>(deserializer: D) -> Result<(), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut names = Vec::new();
+
+    for string in Vec::<String>::deserialize(deserializer)? {
+        match Name::from_owned(string) {
+            Some(name) => names.push(name),
+            None => return Err(D::Error::custom("too many distinct names")),
+        }
+    }
+
+    SERDE_TABLE.with(|table| {
+        if let Some(Table::Deserialize(table)) = &mut *table.borrow_mut() {
+            *table = names;
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Name {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let index = SERDE_TABLE.with(|table| match &*table.borrow() {
+            Some(Table::Serialize(indexes)) => indexes.get(self).copied(),
+            _ => None,
+        });
+
+        match index {
+            Some(index) => serializer.serialize_u32(index),
+            None => self.as_str().serialize(serializer),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Name {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct NameVisitor;
+
+        impl<'de> Visitor<'de> for NameVisitor {
+            type Value = Name;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a name or an index into the table of names")
+            }
+
+            fn visit_str<E>(self, string: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                self.visit_string(string.to_owned())
+            }
+
+            fn visit_string<E>(self, string: String) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match Name::from_owned(string) {
+                    Some(name) => Ok(name),
+                    None => Err(E::custom("too many distinct names")),
+                }
+            }
+
+            fn visit_u64<E>(self, index: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                let name = SERDE_TABLE.with(|table| match &*table.borrow() {
+                    Some(Table::Deserialize(names)) => {
+                        names.get(usize::try_from(index).ok()?).copied()
+                    }
+                    _ => None,
+                });
+
+                match name {
+                    Some(name) => Ok(name),
+                    None => Err(E::custom(format_args!(
+                        "name index {index} is not in the table of names"
+                    ))),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(NameVisitor)
+    }
+}
+
+/// A name which is lazily interned the first time it's used.
+#[cfg(all(feature = "trace", feature = "parking_lot"))]
+pub(crate) struct LazyName {
+    string: &'static str,
+    name: AtomicU32,
+}
+
+#[cfg(all(feature = "trace", feature = "parking_lot"))]
+impl LazyName {
+    /// Construct a new lazily interned name.
+    pub(crate) const fn new(string: &'static str) -> Self {
+        Self {
+            string,
+            name: AtomicU32::new(u32::MAX),
+        }
+    }
+
+    /// Get the interned name.
+    #[inline]
+    pub(crate) fn get(&self) -> Name {
+        let name = self.name.load(Ordering::Relaxed);
+
+        if name != u32::MAX {
+            return Name(name as u16);
+        }
+
+        let name = Name::new(self.string);
+        self.name.store(u32::from(name.0), Ordering::Relaxed);
+        name
+    }
+}
+
+/// Access a lazily interned name.
+#[cfg(all(feature = "trace", feature = "parking_lot"))]
+macro_rules! name {
+    ($string:literal) => {{
+        static NAME: $crate::intern::LazyName = $crate::intern::LazyName::new($string);
+        NAME.get()
+    }};
+}
+
+#[cfg(all(feature = "trace", feature = "parking_lot"))]
+pub(crate) use name;
@@ -0,0 +1,254 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crate::{Event, Events, LockId};
+
+/// A thread waiting for a lock held by a thread with a lower priority.
+///
+/// Returned by [`Events::priority_inversions`].
+#[derive(Debug, Clone, Copy)]
+pub struct Inversion<'a> {
+    waiter: &'a Event,
+    holder: &'a Event,
+    waiter_priority: u8,
+    holder_priority: u8,
+    overlap: u64,
+}
+
+impl<'a> Inversion<'a> {
+    /// The lock involved in the inversion.
+    pub fn lock(&self) -> LockId {
+        self.waiter.lock
+    }
+
+    /// The critical section of the acquisition by the high priority thread
+    /// which had to wait.
+    pub fn waiter(&self) -> &'a Event {
+        self.waiter
+    }
+
+    /// The critical section of the low priority thread which held the lock.
+    pub fn holder(&self) -> &'a Event {
+        self.holder
+    }
+
+    /// The priority of the thread which waited.
+    pub fn waiter_priority(&self) -> u8 {
+        self.waiter_priority
+    }
+
+    /// The priority of the thread which held the lock.
+    pub fn holder_priority(&self) -> u8 {
+        self.holder_priority
+    }
+
+    /// How long the high priority thread waited while the lock was held by
+    /// the low priority thread.
+    pub fn overlap(&self) -> Duration {
+        Duration::from_nanos(self.overlap)
+    }
+}
+
+/// An acquisition of a lock, used to find overlaps.
+struct Acquisition<'a> {
+    event: &'a Event,
+    access: Access,
+    priority: u8,
+    requested: u64,
+    acquired: u64,
+    released: u64,
+}
+
+/// How an acquisition accessed its lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Shared,
+    Upgradable,
+    Exclusive,
+}
+
+impl Access {
+    /// The access of an acquisition by the operation `name`.
+    fn from_name(name: &str) -> Self {
+        match name {
+            "read" | "try_read" | "read_recursive" => Access::Shared,
+            "upgradable_read" => Access::Upgradable,
+            _ => Access::Exclusive,
+        }
+    }
+
+    /// Test if the lock can be held with both accesses at the same time, in
+    /// which case neither blocks the other.
+    fn compatible(self, other: Self) -> bool {
+        matches!(
+            (self, other),
+            (Access::Shared, Access::Shared)
+                | (Access::Shared, Access::Upgradable)
+                | (Access::Upgradable, Access::Shared)
+        )
+    }
+}
+
+/// A point in time at which a wait or a hold starts, in the order they're
+/// swept in.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Start {
+    Wait,
+    Hold,
+}
+
+impl Events {
+    /// Find waits where a thread was kept from acquiring a lock by a thread
+    /// with a lower priority, which is known as priority inversion.
+    ///
+    /// The `priority` callback is called with the index of a thread and
+    /// returns its priority, where a higher value is a higher priority. This
+    /// allows priorities to be provided from wherever they are known, such as
+    /// by recording them when threads are spawned.
+    ///
+    /// One inversion is returned for every pair of a wait and a hold which
+    /// overlapped in time. Reads of an [`RwLock`] don't block each other, so
+    /// a read which waited while another was held isn't an inversion.
+    /// Inversions are ordered by how long they lasted, longest first.
+    ///
+    /// [`RwLock`]: crate::RwLock
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let events = unlock::drain();
+    ///
+    /// // Threads with a lower index have a higher priority.
+    /// let inversions = events.priority_inversions(|thread| u8::MAX.saturating_sub(thread as u8));
+    ///
+    /// for inversion in inversions {
+    ///     println!("{:?}: waited for {:?}", inversion.lock(), inversion.overlap());
+    /// }
+    /// ```
+    pub fn priority_inversions<F>(&self, priority: F) -> Vec<Inversion<'_>>
+    where
+        F: Fn(usize) -> u8,
+    {
+        let times = self.holds();
+        let end = self.span().map_or(0, |(_, end)| end);
+
+        // The operation of each critical section is the name of the event
+        // spent acquiring it, which is the first one nested in it.
+        let mut operations = HashMap::new();
+
+        for enter in &self.enters {
+            if let Some(parent) = enter.parent {
+                let entry = operations.entry(parent).or_insert(enter);
+
+                if enter.id < entry.id {
+                    *entry = enter;
+                }
+            }
+        }
+
+        let mut locks = BTreeMap::<LockId, Vec<Acquisition<'_>>>::new();
+
+        for enter in &self.enters {
+            if enter.parent.is_some() {
+                continue;
+            }
+
+            let open = times.acquired(enter.id).unwrap_or(enter.timestamp);
+
+            let released = match times.hold(enter) {
+                Some((open, close)) => close.unwrap_or(end).max(open),
+                None => open,
+            };
+
+            let access = operations
+                .get(&enter.id)
+                .map_or(Access::Exclusive, |operation| {
+                    Access::from_name(operation.name())
+                });
+
+            locks.entry(enter.lock).or_default().push(Acquisition {
+                event: enter,
+                access,
+                priority: priority(enter.thread_index),
+                requested: enter.timestamp,
+                acquired: open,
+                released,
+            });
+        }
+
+        let mut inversions = Vec::new();
+
+        for acquisitions in locks.values() {
+            // Waits and holds are swept in the order they start, so that each
+            // is only compared with the ones it overlaps.
+            let mut starts = Vec::new();
+
+            for (n, acquisition) in acquisitions.iter().enumerate() {
+                if acquisition.acquired > acquisition.requested {
+                    starts.push((acquisition.requested, Start::Wait, n));
+                }
+
+                if acquisition.released > acquisition.acquired {
+                    starts.push((acquisition.acquired, Start::Hold, n));
+                }
+            }
+
+            starts.sort_unstable();
+
+            let mut waits = Vec::<&Acquisition<'_>>::new();
+            let mut holds = Vec::<&Acquisition<'_>>::new();
+
+            for (at, start, n) in starts {
+                let current = &acquisitions[n];
+
+                let (waiter, others) = match start {
+                    Start::Wait => {
+                        holds.retain(|hold| hold.released > at);
+                        waits.push(current);
+                        (true, &holds)
+                    }
+                    Start::Hold => {
+                        waits.retain(|wait| wait.acquired > at);
+                        holds.push(current);
+                        (false, &waits)
+                    }
+                };
+
+                for other in others {
+                    let (waiter, holder) = if waiter {
+                        (current, *other)
+                    } else {
+                        (*other, current)
+                    };
+
+                    if holder.event.thread_index == waiter.event.thread_index
+                        || holder.priority >= waiter.priority
+                        || holder.access.compatible(waiter.access)
+                    {
+                        continue;
+                    }
+
+                    let start = holder.acquired.max(waiter.requested);
+                    let end = holder.released.min(waiter.acquired);
+
+                    inversions.push(Inversion {
+                        waiter: waiter.event,
+                        holder: holder.event,
+                        waiter_priority: waiter.priority,
+                        holder_priority: holder.priority,
+                        overlap: end - start,
+                    });
+                }
+            }
+        }
+
+        inversions.sort_by(|a, b| {
+            b.overlap
+                .cmp(&a.overlap)
+                .then_with(|| a.waiter.id.cmp(&b.waiter.id))
+                .then_with(|| a.holder.id.cmp(&b.holder.id))
+        });
+
+        inversions
+    }
+}
@@ -0,0 +1,324 @@
+//! Module to write captured lock events as JSON without depending on `serde`.
+//!
+//! The output uses the same schema as serializing [`Events`] with
+//! `serde_json`, so it can be read back through `serde` where it is
+//! available, such as to render it later. This is useful when events only
+//! need to be exported, since it doesn't require the `serde` feature.
+//!
+//! The names of events and the types of their locks are written once to a
+//! `names` table which comes first, and events refer to them by their index
+//! in it. Since a capture usually only uses a handful of distinct names, this
+//! keeps the output small.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use crate::event::Leave;
+use crate::intern::Name;
+use crate::{Error, Event, Events, Outcome, Process};
+
+/// Write events as JSON to the given output.
+///
+/// ```
+/// let events = unlock::drain();
+/// let mut out = Vec::new();
+/// unlock::json::write(&mut out, &events)?;
+/// # Ok::<_, unlock::Error>(())
+/// ```
+pub fn write<W>(mut out: W, events: &Events) -> Result<(), Error>
+where
+    W: io::Write,
+{
+    let names = events.names();
+
+    write!(out, "{{")?;
+
+    if !names.is_empty() {
+        write!(out, "\"names\":[")?;
+
+        for (n, name) in names.iter().enumerate() {
+            if n > 0 {
+                write!(out, ",")?;
+            }
+
+            write_str(&mut out, name.as_str())?;
+        }
+
+        write!(out, "],")?;
+    }
+
+    let indexes = names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| (name, index))
+        .collect::<HashMap<_, _>>();
+
+    write!(out, "\"enters\":[")?;
+
+    for (n, event) in events.enters.iter().enumerate() {
+        if n > 0 {
+            write!(out, ",")?;
+        }
+
+        write_event(&mut out, event, &indexes)?;
+    }
+
+    write!(out, "],\"leaves\":[")?;
+
+    for (n, leave) in events.leaves.iter().enumerate() {
+        if n > 0 {
+            write!(out, ",")?;
+        }
+
+        write_leave(&mut out, leave)?;
+    }
+
+    write!(out, "],\"dropped\":{}", events.dropped)?;
+    write!(
+        out,
+        ",\"thread_index_conflicts\":{}",
+        events.thread_index_conflicts
+    )?;
+
+    if !events.processes.is_empty() {
+        write!(out, ",\"processes\":[")?;
+
+        for (n, process) in events.processes.iter().enumerate() {
+            if n > 0 {
+                write!(out, ",")?;
+            }
+
+            write_process(&mut out, process)?;
+        }
+
+        write!(out, "]")?;
+    }
+
+    if !events.creation_sites.is_empty() {
+        write!(out, ",\"creation_sites\":{{")?;
+
+        // Sort the locks so that the output is deterministic.
+        let mut sites = events.creation_sites.iter().collect::<Vec<_>>();
+        sites.sort_by_key(|(lock, _)| **lock);
+
+        for (n, (lock, backtrace)) in sites.into_iter().enumerate() {
+            if n > 0 {
+                write!(out, ",")?;
+            }
+
+            write!(out, "\"{lock}\":")?;
+            write_str(&mut out, backtrace.as_str())?;
+        }
+
+        write!(out, "}}")?;
+    }
+
+    if !events.keys.is_empty() {
+        write!(out, ",\"keys\":{{")?;
+
+        let mut keys = events.keys.iter().collect::<Vec<_>>();
+        keys.sort_by_key(|(lock, _)| **lock);
+
+        for (n, (lock, key)) in keys.into_iter().enumerate() {
+            if n > 0 {
+                write!(out, ",")?;
+            }
+
+            write!(out, "\"{lock}\":")?;
+            write_str(&mut out, key.as_str())?;
+        }
+
+        write!(out, "}}")?;
+    }
+
+    if !events.thread_names.is_empty() {
+        write!(out, ",\"thread_names\":{{")?;
+
+        let mut processes = events.thread_names.iter().collect::<Vec<_>>();
+        processes.sort_by_key(|(process, _)| **process);
+
+        for (n, (process, names)) in processes.into_iter().enumerate() {
+            if n > 0 {
+                write!(out, ",")?;
+            }
+
+            write!(out, "\"{process}\":{{")?;
+
+            let mut names = names.iter().collect::<Vec<_>>();
+            names.sort_by_key(|(thread_index, _)| **thread_index);
+
+            for (n, (thread_index, name)) in names.into_iter().enumerate() {
+                if n > 0 {
+                    write!(out, ",")?;
+                }
+
+                write!(out, "\"{thread_index}\":")?;
+                write_str(&mut out, name)?;
+            }
+
+            write!(out, "}}")?;
+        }
+
+        write!(out, "}}")?;
+    }
+
+    write!(out, "}}")?;
+    Ok(())
+}
+
+fn write_event<W>(out: &mut W, event: &Event, names: &HashMap<Name, usize>) -> io::Result<()>
+where
+    W: io::Write,
+{
+    write!(
+        out,
+        "{{\"id\":{},\"timestamp\":{},\"thread_index\":{}",
+        event.id, event.timestamp, event.thread_index
+    )?;
+
+    write!(out, ",\"parent\":")?;
+    write_option(out, event.parent)?;
+    write!(out, ",\"name\":{}", names[&event.name])?;
+    write!(out, ",\"type_name\":{}", names[&event.type_name])?;
+    write!(out, ",\"lock\":{}", event.lock)?;
+
+    #[cfg(feature = "backtrace")]
+    {
+        write!(out, ",\"backtrace\":")?;
+
+        match &event.backtrace {
+            Some(backtrace) => write_str(out, backtrace.as_str())?,
+            None => write!(out, "null")?,
+        }
+    }
+
+    if let Some(stack_depth) = event.stack_depth {
+        write!(out, ",\"stack_depth\":{stack_depth}")?;
+    }
+
+    write!(out, ",\"waiters\":{}", event.waiters)?;
+    write!(out, ",\"held\":{}", event.held)?;
+
+    if !event.attributes.is_empty() {
+        write!(out, ",\"attributes\":[")?;
+
+        for (n, (key, value)) in event.attributes.iter().enumerate() {
+            if n > 0 {
+                write!(out, ",")?;
+            }
+
+            write!(out, "[")?;
+            write_str(out, key)?;
+            write!(out, ",")?;
+            write_str(out, value)?;
+            write!(out, "]")?;
+        }
+
+        write!(out, "]")?;
+    }
+
+    write!(out, ",\"timeout\":")?;
+    write_option(out, event.timeout)?;
+
+    let outcome = match event.outcome {
+        Outcome::Acquired => "Acquired",
+        Outcome::TimedOut => "TimedOut",
+        Outcome::TryFailed => "TryFailed",
+    };
+
+    write!(out, ",\"outcome\":\"{outcome}\"")?;
+
+    if event.contended {
+        write!(out, ",\"contended\":true")?;
+    }
+
+    write!(out, ",\"task_id\":")?;
+    write_option(out, event.task_id)?;
+    write!(out, ",\"process\":{}}}", event.process)?;
+    Ok(())
+}
+
+fn write_leave<W>(out: &mut W, leave: &Leave) -> io::Result<()>
+where
+    W: io::Write,
+{
+    write!(
+        out,
+        "{{\"sibling\":{},\"thread_index\":{},\"timestamp\":{}",
+        leave.sibling, leave.thread_index, leave.timestamp
+    )?;
+
+    if leave.leaked {
+        write!(out, ",\"leaked\":true")?;
+    }
+
+    write!(out, "}}")?;
+    Ok(())
+}
+
+fn write_process<W>(out: &mut W, process: &Process) -> io::Result<()>
+where
+    W: io::Write,
+{
+    write!(out, "{{\"id\":{},\"label\":", process.id)?;
+
+    match &process.label {
+        Some(label) => write_str(out, label)?,
+        None => write!(out, "null")?,
+    }
+
+    write!(out, ",\"start\":")?;
+    write_option(out, process.start)?;
+    write!(out, "}}")?;
+    Ok(())
+}
+
+/// Write an optional number, or `null` if it's missing.
+fn write_option<W, T>(out: &mut W, value: Option<T>) -> io::Result<()>
+where
+    W: io::Write,
+    T: fmt::Display,
+{
+    match value {
+        Some(value) => write!(out, "{value}"),
+        None => write!(out, "null"),
+    }
+}
+
+/// Write a string, escaping it the same way as `serde_json`.
+pub(crate) fn write_str<W>(out: &mut W, string: &str) -> io::Result<()>
+where
+    W: io::Write,
+{
+    write!(out, "\"")?;
+
+    let mut start = 0;
+
+    for (n, c) in string.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{08}' => "\\b",
+            '\u{0c}' => "\\f",
+            c if c < ' ' => "",
+            _ => continue,
+        };
+
+        out.write_all(&string.as_bytes()[start..n])?;
+        start = n + c.len_utf8();
+
+        if escape.is_empty() {
+            write!(out, "\\u{:04x}", c as u32)?;
+        } else {
+            out.write_all(escape.as_bytes())?;
+        }
+    }
+
+    out.write_all(&string.as_bytes()[start..])?;
+    write!(out, "\"")?;
+    Ok(())
+}
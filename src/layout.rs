@@ -0,0 +1,162 @@
+//! Layout computations shared between the renderers.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::event::EventId;
+use crate::html::GroupBy;
+use crate::{Event, Events, LockId};
+
+/// The key of a single timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Row {
+    Thread(usize),
+    Task(Option<u64>),
+}
+
+impl Row {
+    /// The human readable heading of the row, using the given names of the
+    /// threads in its process.
+    pub(crate) fn heading(self, thread_names: Option<&HashMap<usize, String>>) -> String {
+        match self {
+            Row::Thread(thread_index) => {
+                match thread_names.and_then(|names| names.get(&thread_index)) {
+                    Some(name) => format!("{name} ({thread_index})"),
+                    None => thread_index.to_string(),
+                }
+            }
+            Row::Task(Some(task_id)) => format!("task {task_id}"),
+            Row::Task(None) => String::from("no task"),
+        }
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Row::Thread(thread_index) => write!(f, "thread-{thread_index}"),
+            Row::Task(Some(task_id)) => write!(f, "task-{task_id}"),
+            Row::Task(None) => write!(f, "task-none"),
+        }
+    }
+}
+
+/// Events organized into per-lock timelines.
+pub(crate) struct Layout<'a> {
+    /// The start and end of the trace.
+    pub(crate) span: (u64, u64),
+    /// Root events grouped by process, lock and timeline.
+    pub(crate) locks: BTreeMap<(u32, LockId, &'static str), BTreeMap<Row, Vec<&'a Event>>>,
+    /// Child events indexed by their parent.
+    pub(crate) children: HashMap<EventId, Vec<&'a Event>>,
+    /// When each event was closed.
+    pub(crate) closes: HashMap<EventId, u64>,
+}
+
+impl<'a> Layout<'a> {
+    /// Organize the given events, returning `None` if there is nothing to
+    /// lay out.
+    ///
+    /// If a `window` is specified, only events overlapping it are included
+    /// and it is used as the span of the layout. Only critical sections
+    /// matching `keep` are included, but the span always covers the whole
+    /// collection.
+    pub(crate) fn new(
+        events: &'a Events,
+        group_by: GroupBy,
+        window: Option<(u64, u64)>,
+        keep: &mut dyn FnMut(&Event) -> bool,
+    ) -> Option<Self> {
+        // Start of trace.
+        let mut start = u64::MAX;
+        // End of trace.
+        let mut end = u64::MIN;
+
+        let mut locks = BTreeMap::<_, BTreeMap<_, Vec<_>>>::new();
+        let mut children = HashMap::<_, Vec<_>>::new();
+        let closes = events.closes();
+        end = closes.values().fold(end, |end, close| end.max(*close));
+
+        for enter in &events.enters {
+            start = start.min(enter.timestamp);
+            // Sections which are never closed extend to the end of the trace,
+            // which covers at least all enters.
+            end = end.max(enter.timestamp);
+
+            if let Some((window_start, window_end)) = window {
+                let close = closes.get(&enter.id).copied().unwrap_or(u64::MAX);
+
+                if enter.timestamp >= window_end || close <= window_start {
+                    continue;
+                }
+            }
+
+            if let Some(parent) = enter.parent {
+                children.entry(parent).or_default().push(enter);
+            } else if keep(enter) {
+                locks
+                    .entry((enter.process, enter.lock, enter.type_name()))
+                    .or_default()
+                    .entry(match group_by {
+                        GroupBy::Thread => Row::Thread(enter.thread_index),
+                        GroupBy::Task => Row::Task(enter.task_id),
+                    })
+                    .or_default()
+                    .push(enter);
+            }
+        }
+
+        if start == u64::MAX || end == u64::MIN {
+            return None;
+        }
+
+        let span = match window {
+            Some((start, end)) if start < end => (start, end),
+            Some(..) => return None,
+            None => (start, end),
+        };
+
+        Some(Self {
+            span,
+            locks,
+            children,
+            closes,
+        })
+    }
+}
+
+/// The span of a single timeline, from the first opened to the last closed
+/// event.
+///
+/// Events which were never closed are considered to be closed at `end`.
+pub(crate) fn row_span(events: &[&Event], closes: &HashMap<EventId, u64>, end: u64) -> (u64, u64) {
+    let start = events.iter().map(|e| e.timestamp).min().unwrap_or(0);
+
+    let end = events
+        .iter()
+        .map(|ev| closes.get(&ev.id).copied().unwrap_or(end))
+        .max()
+        .unwrap_or(0);
+
+    (start, end)
+}
+
+/// Calculate the offset and width of the interval `open` to `close` as
+/// fractions of `span`, truncating it at the edges of the span.
+///
+/// Returns `None` if the span is empty or the interval doesn't overlap it.
+pub(crate) fn position(span: (u64, u64), open: u64, close: u64) -> Option<(f32, f32)> {
+    let (start, end) = span;
+
+    if start == end || open > end || close < start {
+        return None;
+    }
+
+    let open = open.max(start);
+    let close = close.min(end);
+
+    let total = (end - start) as f32;
+    let left = (open - start) as f32 / total;
+    let width = (close - open) as f32 / total;
+    Some((left, width))
+}
@@ -45,8 +45,11 @@
 //!
 //! Once a workload has been instrumented, the `drain` function can be called to
 //! collect these events, which then can be formatted using either built-in
-//! methods such as [`html::write`], or serialized as you please using `serde`
-//! for processing later.
+//! methods such as [`html::write`] and [`svg::write`], written as JSON through
+//! [`json::write`] without depending on `serde`, exported to Perfetto through
+//! [`chrome::write`], or serialized as you please using `serde` for processing
+//! later. See the [`serialize` example] for how a trace can be saved and
+//! rendered later.
 //!
 //! <br>
 //!
@@ -58,13 +61,93 @@
 //!   feature is enabled and `trace` is disabled, this will re-export
 //!   `parking_lot` primitives.
 //! * `serde` - Enable serialization for events.
+//! * `backtrace` (default) - Enable capturing backtraces. If this feature is
+//!   disabled, backtrace capturing is compiled out and events don't have a
+//!   backtrace field. Serialized events stay compatible, since a missing
+//!   backtrace is ignored when deserializing and an unexpected one is
+//!   skipped.
+//! * `otel` - Enable exporting events as OpenTelemetry spans through
+//!   [`otel::export`].
+//! * `tokio` - Enable `AsyncMutex` and `AsyncRwLock`, which wrap the locks in
+//!   `tokio::sync` and record waits across `.await`. If `trace` is disabled,
+//!   these re-export the `tokio` primitives.
+//! * `test-util` - Enable `reset_for_test`, which replaces the global tracing
+//!   context with a fresh one so that tests don't observe each other's events.
 //!
 //! [`RwLock`]: https://docs.rs/unlock/latest/unlock/struct.RwLock.html
 //! [`Mutex`]: https://docs.rs/unlock/latest/unlock/struct.Mutex.html
 //! [`html::write`]: https://docs.rs/unlock/latest/unlock/html/fn.write.html
+//! [`svg::write`]: https://docs.rs/unlock/latest/unlock/svg/fn.write.html
+//! [`json::write`]: https://docs.rs/unlock/latest/unlock/json/fn.write.html
+//! [`chrome::write`]: https://docs.rs/unlock/latest/unlock/chrome/fn.write.html
+//! [`otel::export`]: https://docs.rs/unlock/latest/unlock/otel/fn.export.html
+//! [`serialize` example]: https://github.com/udoprog/unlock/blob/main/examples/serialize.rs
+
+mod intern;
+
+mod held;
+pub use self::held::LockState;
 
 mod event;
-pub use self::event::{Event, Events};
+pub use self::event::{Event, EventBacktrace, EventId, Events, Leave, LockId, LockKind, SortKey};
+
+mod append;
+
+mod call_sites;
+pub use self::call_sites::CallSite;
+
+mod config;
+pub use self::config::CaptureConfig;
+
+mod layout;
+
+mod deadlock;
+pub use self::deadlock::{DeadlockCycle, DeadlockEdge};
+
+mod diff;
+pub use self::diff::{Diff, LockDiff};
+
+mod episode;
+pub use self::episode::{Episode, EpisodeHold};
+
+mod error;
+pub use self::error::{DrainError, Error, ValidateError};
+
+mod inversion;
+pub use self::inversion::Inversion;
+
+mod nesting;
+
+mod outcome;
+pub use self::outcome::Outcome;
+
+mod park;
+
+mod process;
+pub use self::process::Process;
+
+mod rate;
+
+mod section;
+pub use self::section::{Section, Span};
+
+mod session;
+pub use self::session::SessionId;
+
+mod split;
+
+mod stats;
+pub use self::stats::{LockStats, Stats};
+
+mod summary;
+pub use self::summary::Summary;
+
+mod timed;
+pub use self::timed::TimedCapture;
+
+mod tasks;
+
+mod utilization;
 
 #[cfg(all(feature = "trace", feature = "parking_lot"))]
 mod sync;
@@ -72,6 +155,12 @@ mod sync;
 #[cfg(all(feature = "trace", feature = "parking_lot"))]
 pub use self::sync::*;
 
+#[cfg(all(feature = "trace", feature = "parking_lot", feature = "tokio"))]
+mod async_sync;
+#[doc(inline)]
+#[cfg(all(feature = "trace", feature = "parking_lot", feature = "tokio"))]
+pub use self::async_sync::*;
+
 #[cfg_attr(
     all(feature = "trace", feature = "parking_lot"),
     path = "tracing_context.rs"
@@ -82,9 +171,44 @@ pub use self::sync::*;
 )]
 mod tracing_context;
 
-pub use self::tracing_context::{capture, drain};
+pub use self::tracing_context::{
+    capture, capture_for, capture_scope, capture_session, capture_with, clear_task_id,
+    clear_thread_index_source, drain, drain_session, drain_sorted, held_locks, is_capturing,
+    release_buffers, set_enabled, set_process_label, set_task_id, set_thread_index_source,
+    snapshot, try_drain, with_context, CaptureGuard,
+};
+
+#[cfg(feature = "test-util")]
+pub use self::tracing_context::reset_for_test;
+
+pub mod prelude;
 
 pub mod html;
 
+pub mod svg;
+
+pub mod gantt;
+
+pub mod folded;
+
+pub mod json;
+
+pub mod chrome;
+
+#[cfg(feature = "otel")]
+pub mod otel;
+
 #[cfg(all(not(feature = "trace"), feature = "parking_lot"))]
-pub use parking_lot::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use parking_lot::{
+    Condvar, MappedMutexGuard, Mutex, MutexGuard, RwLock, RwLockReadGuard,
+    RwLockUpgradableReadGuard, RwLockWriteGuard,
+};
+
+#[cfg(all(
+    not(all(feature = "trace", feature = "parking_lot")),
+    feature = "tokio"
+))]
+pub use tokio::sync::{
+    Mutex as AsyncMutex, MutexGuard as AsyncMutexGuard, RwLock as AsyncRwLock,
+    RwLockReadGuard as AsyncRwLockReadGuard, RwLockWriteGuard as AsyncRwLockWriteGuard,
+};
@@ -0,0 +1,100 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::{Event, Events};
+
+/// A lock requested by a thread, used to count the locks it held at the time.
+struct Request {
+    index: usize,
+    timestamp: u64,
+    hold: Option<(u64, u64)>,
+}
+
+impl Events {
+    /// Find acquisitions which were made while the acquiring thread already
+    /// held at least `threshold` other locks.
+    ///
+    /// Acquiring a lock while holding many others is prone to deadlocks and
+    /// makes every one of the held locks wait for the new one, which is hard
+    /// to spot from aggregate statistics. See [`Event::held`] for how held
+    /// locks are counted.
+    ///
+    /// The returned critical sections are in the order of the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let events = unlock::drain();
+    ///
+    /// for event in events.deep_nesting(3) {
+    ///     println!("{} locks held at {:?}", event.held(), event.backtrace());
+    /// }
+    /// ```
+    pub fn deep_nesting(&self, threshold: usize) -> Vec<&Event> {
+        self.enters
+            .iter()
+            .filter(|event| event.parent.is_none() && event.held as usize >= threshold)
+            .collect()
+    }
+
+    /// Count the locks held by the thread of every critical section when it
+    /// was requested.
+    pub(crate) fn count_held(&mut self) {
+        let times = self.holds();
+        let mut threads = HashMap::<_, (Vec<(u64, u64)>, Vec<Request>)>::new();
+
+        for (index, enter) in self.enters.iter().enumerate() {
+            if enter.parent.is_some() {
+                continue;
+            }
+
+            let (holds, requests) = threads.entry(enter.thread_index).or_default();
+
+            let hold = times
+                .hold(enter)
+                .map(|(open, close)| (open, close.unwrap_or(u64::MAX)));
+
+            holds.extend(hold);
+
+            requests.push(Request {
+                index,
+                timestamp: enter.timestamp,
+                hold,
+            });
+        }
+
+        for (mut holds, mut requests) in threads.into_values() {
+            holds.sort_unstable();
+            requests.sort_unstable_by_key(|request| (request.timestamp, request.index));
+
+            let mut holds = holds.into_iter().peekable();
+            let mut open = BinaryHeap::new();
+
+            for request in requests {
+                let at = request.timestamp;
+
+                while let Some((start, end)) = holds.next_if(|(start, _)| *start <= at) {
+                    if end > start {
+                        open.push(Reverse(end));
+                    }
+                }
+
+                while open.peek().map_or(false, |Reverse(end)| *end <= at) {
+                    open.pop();
+                }
+
+                let mut held = open.len();
+
+                // The section itself counts as held if it was acquired at the
+                // same timestamp as it was requested.
+                if let Some((start, end)) = request.hold {
+                    if start <= at && end > at {
+                        held -= 1;
+                    }
+                }
+
+                self.enters[request.index].held = u32::try_from(held).unwrap_or(u32::MAX);
+            }
+        }
+    }
+}
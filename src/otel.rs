@@ -0,0 +1,102 @@
+//! Module to export captured lock events as [OpenTelemetry] spans.
+//!
+//! This is an offline export from a drained collection of [`Events`], so it
+//! doesn't add any overhead while events are being captured.
+//!
+//! [OpenTelemetry]: https://opentelemetry.io
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use opentelemetry::trace::{Span, SpanBuilder, SpanContext, SpanKind, TraceContextExt, Tracer};
+use opentelemetry::{Context, KeyValue};
+
+use crate::{Event, EventId, Events, Outcome};
+
+/// Export events as spans using the given tracer.
+///
+/// Every critical section is exported as a root span, with the time spent
+/// waiting for the lock as a child span. Spans are timed using the wall-clock
+/// time at which the capture was started in the process the events were
+/// recorded in, and carry attributes describing the lock they are associated
+/// with.
+///
+/// Spans which were never closed are ended at the end of the trace.
+pub fn export<T>(events: &Events, tracer: &T)
+where
+    T: Tracer,
+{
+    let closes = events.closes();
+    let end = events.span().map_or(0, |(_, end)| end);
+
+    // If it's not known when the capture was started, assume that it ended
+    // now.
+    let fallback = SystemTime::now()
+        .checked_sub(Duration::from_nanos(end))
+        .unwrap_or(UNIX_EPOCH);
+
+    // Parents must be exported before their children, which is the order in
+    // which they were recorded.
+    let mut enters = events.enters.iter().collect::<Vec<_>>();
+    enters.sort_by_key(|event| event.id);
+
+    let mut contexts = HashMap::<EventId, SpanContext>::new();
+
+    for event in enters {
+        let base = events
+            .process(event)
+            .and_then(|process| process.start)
+            .map_or(fallback, |start| UNIX_EPOCH + Duration::from_nanos(start));
+
+        let open = base + Duration::from_nanos(event.timestamp);
+        let close = closes.get(&event.id).copied().unwrap_or(end);
+        let close = base + Duration::from_nanos(close.max(event.timestamp));
+
+        let builder = SpanBuilder::from_name(event.name())
+            .with_kind(SpanKind::Internal)
+            .with_start_time(open)
+            .with_attributes(attributes(event));
+
+        let parent = match event.parent.and_then(|parent| contexts.get(&parent)) {
+            Some(span_context) => Context::new().with_remote_span_context(span_context.clone()),
+            None => Context::new(),
+        };
+
+        let mut span = tracer.build_with_context(builder, &parent);
+        contexts.insert(event.id, span.span_context().clone());
+        span.end_with_timestamp(close);
+    }
+}
+
+/// Construct the attributes of the span for the given event.
+fn attributes(event: &Event) -> Vec<KeyValue> {
+    let lock = event.lock;
+
+    let mut attributes = vec![
+        KeyValue::new("lock.index", lock.index() as i64),
+        KeyValue::new("lock.kind", format!("{:?}", lock.kind())),
+        KeyValue::new("lock.type", event.type_name()),
+        KeyValue::new("thread.index", event.thread_index as i64),
+        KeyValue::new("lock.waiters", i64::from(event.waiters)),
+    ];
+
+    if let Some(task_id) = event.task_id {
+        attributes.push(KeyValue::new("task.id", task_id as i64));
+    }
+
+    if let Some(timeout) = event.timeout {
+        attributes.push(KeyValue::new("lock.timeout_ns", timeout as i64));
+    }
+
+    match event.outcome {
+        Outcome::TimedOut => attributes.push(KeyValue::new("lock.timed_out", true)),
+        Outcome::TryFailed => attributes.push(KeyValue::new("lock.try_failed", true)),
+        _ => {}
+    }
+
+    for (key, value) in event.attributes() {
+        attributes.push(KeyValue::new(key.to_owned(), value.to_owned()));
+    }
+
+    attributes
+}
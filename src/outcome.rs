@@ -0,0 +1,52 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Events, LockId};
+
+/// The outcome of an attempt to acquire a lock.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Outcome {
+    /// The lock was acquired.
+    #[default]
+    Acquired,
+    /// The attempt to acquire the lock timed out.
+    TimedOut,
+    /// The attempt to acquire the lock without blocking failed because it was
+    /// held, such as through [`Mutex::try_lock`][crate::Mutex::try_lock].
+    ///
+    /// This is only recorded if enabled through
+    /// [`CaptureConfig::record_failed_tries`][crate::CaptureConfig::record_failed_tries].
+    TryFailed,
+}
+
+impl Events {
+    /// Calculate the fraction of timed acquisitions of the given lock which
+    /// timed out, such as through
+    /// [`Mutex::try_lock_for`][crate::Mutex::try_lock_for].
+    ///
+    /// Returns `None` if the lock was never acquired with a timeout.
+    pub fn deadline_miss_rate(&self, lock: LockId) -> Option<f32> {
+        let mut attempts = 0usize;
+        let mut missed = 0usize;
+
+        for enter in &self.enters {
+            if enter.lock != lock || enter.parent.is_some() || enter.timeout.is_none() {
+                continue;
+            }
+
+            attempts += 1;
+
+            if enter.outcome == Outcome::TimedOut {
+                missed += 1;
+            }
+        }
+
+        if attempts == 0 {
+            return None;
+        }
+
+        Some(missed as f32 / attempts as f32)
+    }
+}
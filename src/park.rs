@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::{Events, LockId, Outcome};
+
+/// The default threshold above which a wait is considered to have parked the
+/// thread.
+///
+/// The locks in `parking_lot` spin for a few microseconds at most before
+/// parking the thread.
+pub(crate) const DEFAULT_PARK_THRESHOLD: Duration = Duration::from_micros(10);
+
+/// Operations which never wait for the lock.
+const NON_WAITING: &[&str] = &[
+    "try_lock",
+    "try_read",
+    "try_write",
+    "notify_one",
+    "notify_all",
+];
+
+impl Events {
+    /// Calculate the fraction of waits for the given lock which likely parked
+    /// the waiting thread.
+    ///
+    /// Contended locks first spin briefly before parking the thread. Spinning
+    /// is cheap, while parking indicates heavy contention. Whether a thread
+    /// was parked isn't recorded, so this uses the heuristic that any wait
+    /// which took at least `threshold` parked the thread. A threshold of 10
+    /// microseconds is a reasonable starting point.
+    ///
+    /// Only the acquiring events of sections which acquired the lock are
+    /// counted as waits, so attempts without blocking, notifications and
+    /// events nested in a critical section, such as the write of
+    /// [`with_upgraded`], don't count.
+    ///
+    /// Returns `0.0` if there were no waits for the lock.
+    ///
+    /// [`with_upgraded`]: crate::RwLockUpgradableReadGuard::with_upgraded
+    pub fn park_rate(&self, lock: LockId, threshold: Duration) -> f32 {
+        let closes = self.closes();
+        let threshold = threshold.as_nanos() as u64;
+
+        // The acquiring event of each critical section is its first child.
+        let mut acquiring = HashMap::new();
+
+        for enter in &self.enters {
+            if enter.lock != lock {
+                continue;
+            }
+
+            if let Some(parent) = enter.parent {
+                let entry = acquiring.entry(parent).or_insert(enter);
+
+                if enter.id < entry.id {
+                    *entry = enter;
+                }
+            }
+        }
+
+        let mut waits = 0usize;
+        let mut parked = 0usize;
+
+        for section in &self.enters {
+            if section.lock != lock
+                || section.parent.is_some()
+                || section.outcome != Outcome::Acquired
+            {
+                continue;
+            }
+
+            let Some(enter) = acquiring.get(&section.id) else {
+                continue;
+            };
+
+            if NON_WAITING.contains(&enter.name()) {
+                continue;
+            }
+
+            let Some(close) = closes.get(&enter.id) else {
+                continue;
+            };
+
+            waits += 1;
+
+            if close.saturating_sub(enter.timestamp) >= threshold {
+                parked += 1;
+            }
+        }
+
+        if waits == 0 {
+            return 0.0;
+        }
+
+        parked as f32 / waits as f32
+    }
+}
@@ -0,0 +1,27 @@
+//! The unlock prelude.
+//!
+//! This re-exports the lock and guard types provided by this crate, together
+//! with the functions used to capture events. It is intended to be glob
+//! imported:
+//!
+//! ```
+//! use unlock::prelude::*;
+//!
+//! capture();
+//! let events = drain();
+//! ```
+//!
+//! Lock types are only available if the features providing them are enabled.
+
+pub use crate::{capture, capture_scope, drain, is_capturing, snapshot};
+
+#[cfg(feature = "parking_lot")]
+pub use crate::{
+    Condvar, MappedMutexGuard, Mutex, MutexGuard, RwLock, RwLockReadGuard,
+    RwLockUpgradableReadGuard, RwLockWriteGuard,
+};
+
+#[cfg(feature = "tokio")]
+pub use crate::{
+    AsyncMutex, AsyncMutexGuard, AsyncRwLock, AsyncRwLockReadGuard, AsyncRwLockWriteGuard,
+};
@@ -0,0 +1,168 @@
+use std::borrow::Cow;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::event::EventId;
+use crate::{Event, Events, LockId};
+
+/// A process which events were captured in.
+///
+/// A collection of events drained from a single process refers to one process,
+/// but collections drained from several processes can be combined with
+/// [`Events::merge`]. Events refer to the process they were captured in by its
+/// identifier.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Process {
+    /// The operating system identifier of the process.
+    pub(super) id: u32,
+    /// The label of the process, as set through
+    /// [`set_process_label`][crate::set_process_label].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) label: Option<Cow<'static, str>>,
+    /// The wall-clock time at which the capture was started, in nanoseconds
+    /// since the unix epoch.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) start: Option<u64>,
+}
+
+impl Process {
+    /// Construct a process description for the current process.
+    #[cfg(all(feature = "trace", feature = "parking_lot"))]
+    pub(super) fn current(label: Option<Cow<'static, str>>, start: Option<u64>) -> Self {
+        Self {
+            id: std::process::id(),
+            label,
+            start,
+        }
+    }
+
+    /// The operating system identifier of the process.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The label of the process, if one was set through
+    /// [`set_process_label`][crate::set_process_label].
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+impl Events {
+    /// The processes the events in this collection were captured in.
+    pub fn processes(&self) -> &[Process] {
+        &self.processes
+    }
+
+    /// The process the given event was captured in.
+    ///
+    /// This is `None` if the collection has no information about the
+    /// process, such as if it was deserialized from an older version.
+    pub fn process(&self, event: &Event) -> Option<&Process> {
+        self.processes.iter().find(|p| p.id == event.process)
+    }
+
+    /// Merge the events captured in another process into this collection.
+    ///
+    /// Event and lock identifiers in `other` are renumbered so that they
+    /// don't collide with the ones in this collection, and timestamps are
+    /// aligned using the wall-clock time at which each capture was started.
+    /// The events of `other` are appended after the events in this
+    /// collection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the merged collection would contain too many locks to be
+    /// identified.
+    pub fn merge(&mut self, mut other: Events) {
+        let base = self.start();
+        let other_base = other.start();
+
+        if let (Some(base), Some(other_base)) = (base, other_base) {
+            if other_base > base {
+                other.shift(other_base - base);
+            } else {
+                self.shift(base - other_base);
+            }
+        }
+
+        let event_offset = self.enters.iter().map(|e| e.id.get()).max().unwrap_or(0);
+        // Locks which were never entered can still have a creation site or a
+        // key.
+        let lock_offset = self
+            .enters
+            .iter()
+            .map(|e| e.lock)
+            .chain(self.creation_sites.keys().copied())
+            .chain(self.keys.keys().copied())
+            .map(|lock| lock.index())
+            .max()
+            .unwrap_or(0);
+
+        let event_id = |id: EventId| id.offset(event_offset);
+        let lock_id = |lock: LockId| lock.offset(lock_offset);
+
+        for mut enter in other.enters {
+            enter.id = event_id(enter.id);
+            enter.parent = enter.parent.map(event_id);
+            enter.lock = lock_id(enter.lock);
+            self.enters.push(enter);
+        }
+
+        for mut leave in other.leaves {
+            leave.sibling = event_id(leave.sibling);
+            self.leaves.push(leave);
+        }
+
+        self.creation_sites.extend(
+            other
+                .creation_sites
+                .into_iter()
+                .map(|(lock, backtrace)| (lock_id(lock), backtrace)),
+        );
+
+        self.keys.extend(
+            other
+                .keys
+                .into_iter()
+                .map(|(lock, key)| (lock_id(lock), key)),
+        );
+
+        // The names of threads in this collection take precedence if both
+        // collections were captured in the same process.
+        for (process, names) in other.thread_names {
+            let existing = self.thread_names.entry(process).or_default();
+
+            for (thread_index, name) in names {
+                existing.entry(thread_index).or_insert(name);
+            }
+        }
+
+        for process in other.processes {
+            if !self.processes.iter().any(|p| p.id == process.id) {
+                self.processes.push(process);
+            }
+        }
+        self.dropped += other.dropped;
+        self.thread_index_conflicts += other.thread_index_conflicts;
+    }
+
+    /// The earliest wall-clock time at which any capture in this collection
+    /// was started.
+    fn start(&self) -> Option<u64> {
+        self.processes.iter().flat_map(|p| p.start).min()
+    }
+
+    /// Shift all timestamps forward by `offset` nanoseconds.
+    fn shift(&mut self, offset: u64) {
+        for enter in &mut self.enters {
+            enter.timestamp += offset;
+        }
+
+        for leave in &mut self.leaves {
+            leave.timestamp += offset;
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use crate::{Events, LockId, Outcome};
+
+impl Events {
+    /// Count the acquisitions of the given lock in consecutive buckets of
+    /// time, each `bucket` long.
+    ///
+    /// Buckets are aligned to the start of the trace and cover all of it, so
+    /// the series of different locks in the same collection are comparable.
+    /// An acquisition is counted in the bucket in which it was requested, and
+    /// attempts which failed are not counted.
+    ///
+    /// Returns an empty series if the trace is empty or `bucket` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let events = unlock::drain();
+    ///
+    /// for (lock, _) in events.split_by_lock() {
+    ///     let series = events.acquisition_rate(lock, Duration::from_millis(10));
+    ///     println!("{lock:?}: {series:?}");
+    /// }
+    /// ```
+    pub fn acquisition_rate(&self, lock: LockId, bucket: Duration) -> Vec<u32> {
+        let bucket = bucket.as_nanos().min(u128::from(u64::MAX)) as u64;
+
+        let Some((start, end)) = self.span() else {
+            return Vec::new();
+        };
+
+        if bucket == 0 {
+            return Vec::new();
+        }
+
+        let len = ((end.saturating_sub(start) / bucket) + 1) as usize;
+        let mut series = vec![0u32; len];
+
+        for enter in &self.enters {
+            if enter.lock != lock || enter.parent.is_some() {
+                continue;
+            }
+
+            if enter.outcome != Outcome::Acquired {
+                continue;
+            }
+
+            let index = (enter.timestamp.saturating_sub(start) / bucket) as usize;
+
+            if let Some(count) = series.get_mut(index) {
+                *count = count.saturating_add(1);
+            }
+        }
+
+        series
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::{Event, EventId, Events, Outcome};
+
+/// An enter event paired with the point in time at which it was left.
+///
+/// Returned by [`Events::sections`].
+#[derive(Debug, Clone, Copy)]
+pub struct Section<'a> {
+    event: &'a Event,
+    close: Option<u64>,
+}
+
+impl<'a> Section<'a> {
+    /// The enter event of the section.
+    pub fn event(&self) -> &'a Event {
+        self.event
+    }
+
+    /// The timestamp at which the section was left, relative to when the
+    /// capture was started.
+    ///
+    /// This is `None` if the section wasn't left before the events were
+    /// drained.
+    pub fn close(&self) -> Option<Duration> {
+        self.close.map(Duration::from_nanos)
+    }
+
+    /// How long the section lasted, or `None` if it wasn't left.
+    pub fn duration(&self) -> Option<Duration> {
+        let close = self.close?;
+        Some(Duration::from_nanos(
+            close.saturating_sub(self.event.timestamp),
+        ))
+    }
+}
+
+/// When every event was left and every critical section acquired, used by
+/// analyses which need to know how long locks were held.
+pub(crate) struct Holds {
+    closes: HashMap<EventId, u64>,
+    acquired: HashMap<EventId, u64>,
+}
+
+impl Holds {
+    /// The raw timestamp at which the given event was left.
+    pub(crate) fn close(&self, id: EventId) -> Option<u64> {
+        self.closes.get(&id).copied()
+    }
+
+    /// The raw timestamp at which the given critical section was acquired,
+    /// which is when the last event nested in it was left.
+    ///
+    /// This is `None` if no nested event was left, such as when acquisitions
+    /// which don't wait aren't recorded.
+    pub(crate) fn acquired(&self, id: EventId) -> Option<u64> {
+        self.acquired.get(&id).copied()
+    }
+
+    /// The raw timestamps between which the given critical section held its
+    /// lock, where the end is `None` if it was never left.
+    ///
+    /// This is `None` for attempts which timed out or failed, since a failed
+    /// attempt holds nothing.
+    pub(crate) fn hold(&self, event: &Event) -> Option<(u64, Option<u64>)> {
+        if event.outcome != Outcome::Acquired {
+            return None;
+        }
+
+        let open = self.acquired(event.id).unwrap_or(event.timestamp);
+        Some((open, self.close(event.id)))
+    }
+}
+
+/// A critical section together with the events nested in it.
+///
+/// Returned by [`Events::spans`].
+#[derive(Debug, Clone)]
+pub struct Span<'a> {
+    section: Section<'a>,
+    children: Vec<Span<'a>>,
+}
+
+impl<'a> Span<'a> {
+    /// The enter event of the span.
+    pub fn event(&self) -> &'a Event {
+        self.section.event
+    }
+
+    /// The timestamp at which the span was left, relative to when the
+    /// capture was started.
+    ///
+    /// This is `None` if the span wasn't left before the events were drained.
+    pub fn close(&self) -> Option<Duration> {
+        self.section.close()
+    }
+
+    /// How long the span lasted, or `None` if it wasn't left.
+    pub fn duration(&self) -> Option<Duration> {
+        self.section.duration()
+    }
+
+    /// The spans nested in this span, in the order of the collection.
+    pub fn children(&self) -> &[Span<'a>] {
+        &self.children
+    }
+}
+
+impl Events {
+    /// Iterate over every enter event paired with the leave event which
+    /// closed it, in the order of the collection.
+    ///
+    /// This includes both critical sections and the events nested in them,
+    /// which can be told apart through [`Event::parent`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let events = unlock::drain();
+    ///
+    /// for section in events.sections() {
+    ///     if let Some(duration) = section.duration() {
+    ///         println!("{}: {duration:?}", section.event().name());
+    ///     }
+    /// }
+    /// ```
+    pub fn sections(&self) -> impl Iterator<Item = Section<'_>> + '_ {
+        let closes = self.closes();
+
+        self.enters.iter().map(move |event| Section {
+            event,
+            close: closes.get(&event.id).copied(),
+        })
+    }
+
+    /// Pair every event with when it was left, and every critical section
+    /// with when it was acquired.
+    pub(crate) fn holds(&self) -> Holds {
+        let closes = self.closes();
+        let mut acquired = HashMap::new();
+
+        for enter in &self.enters {
+            if let (Some(parent), Some(close)) = (enter.parent, closes.get(&enter.id)) {
+                let entry = acquired.entry(parent).or_insert(*close);
+                *entry = (*entry).max(*close);
+            }
+        }
+
+        Holds { closes, acquired }
+    }
+
+    /// Iterate over critical sections, with the events nested in them as
+    /// their children, in the order of the collection.
+    ///
+    /// This pairs every enter event with the leave event which closed it like
+    /// [`Events::sections`], but arranges them into trees. Events whose parent
+    /// isn't part of the collection are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let events = unlock::drain();
+    ///
+    /// for span in events.spans() {
+    ///     for child in span.children() {
+    ///         println!("{} {:?}", child.event().name(), child.duration());
+    ///     }
+    /// }
+    /// ```
+    pub fn spans(&self) -> impl Iterator<Item = Span<'_>> + '_ {
+        let mut children = HashMap::<EventId, Vec<Section<'_>>>::new();
+        let mut roots = Vec::new();
+
+        for section in self.sections() {
+            match section.event.parent {
+                Some(parent) => children.entry(parent).or_default().push(section),
+                None => roots.push(section),
+            }
+        }
+
+        roots
+            .into_iter()
+            .map(move |section| build(section, &mut children))
+    }
+}
+
+/// Build a span out of a section, taking its children out of `children`.
+fn build<'a>(section: Section<'a>, children: &mut HashMap<EventId, Vec<Section<'a>>>) -> Span<'a> {
+    let nested = children.remove(&section.event.id).unwrap_or_default();
+
+    Span {
+        section,
+        children: nested
+            .into_iter()
+            .map(|child| build(child, children))
+            .collect(),
+    }
+}
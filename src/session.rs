@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// The maximum number of capture sessions which can be active at the same
+/// time, not counting the capture started through [`capture`][crate::capture].
+pub(crate) const MAX_SESSIONS: usize = 63;
+
+/// The identifier of a capture session started with
+/// [`capture_session`][crate::capture_session].
+///
+/// The index of a session can be reused by another session once the session
+/// it identifies has been drained, but identifiers also carry the generation
+/// of the index so that a drained identifier never refers to a later session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[must_use = "the events of a session can only be drained through its identifier"]
+pub struct SessionId {
+    index: u8,
+    generation: u64,
+}
+
+impl SessionId {
+    /// Construct a session identifier from its index, which is between `1`
+    /// and [`MAX_SESSIONS`], and the number of times the index has been used.
+    pub(crate) fn new(index: usize, generation: u64) -> Self {
+        debug_assert!((1..=MAX_SESSIONS).contains(&index));
+
+        Self {
+            index: index as u8,
+            generation,
+        }
+    }
+
+    /// Get the index of the session.
+    #[allow(unused)]
+    pub(crate) fn index(self) -> usize {
+        usize::from(self.index)
+    }
+
+    /// Get the generation of the index of the session.
+    #[allow(unused)]
+    pub(crate) fn generation(self) -> u64 {
+        self.generation
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.index, self.generation)
+    }
+}
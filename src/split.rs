@@ -0,0 +1,167 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::{Event, Events, LockId};
+
+impl Events {
+    /// Construct a new collection containing only the events matching
+    /// `keep`.
+    ///
+    /// The resulting collection is self-contained, so the ancestors of every
+    /// kept event are included as well as the leave events matching every
+    /// included event.
+    pub fn filter<F>(&self, mut keep: F) -> Events
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        self.partition(|event| keep(event).then_some(()))
+            .remove(&())
+            .unwrap_or_else(|| self.with_events(Vec::new(), Vec::new()))
+    }
+
+    /// Split the collection into one self-contained collection per lock.
+    ///
+    /// See [`Events::filter`] for what is included in each collection.
+    pub fn split_by_lock(&self) -> HashMap<LockId, Events> {
+        self.partition(|event| Some(event.lock))
+    }
+
+    /// Split the collection into one self-contained collection per thread
+    /// index.
+    ///
+    /// See [`Events::filter`] for what is included in each collection.
+    pub fn split_by_thread(&self) -> HashMap<usize, Events> {
+        self.partition(|event| Some(event.thread_index))
+    }
+
+    /// Retain only the events recorded on threads matching `keep`, in place.
+    ///
+    /// Unlike [`Events::filter`], this doesn't clone any events. Leave events
+    /// of removed events are removed, and references to removed parents are
+    /// cleared.
+    pub fn retain_threads<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(usize) -> bool,
+    {
+        self.retain(|event| keep(event.thread_index));
+    }
+
+    /// Retain only the events associated with locks matching `keep`, in
+    /// place.
+    ///
+    /// See [`Events::retain_threads`] for details.
+    pub fn retain_locks<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(LockId) -> bool,
+    {
+        self.retain(|event| keep(event.lock));
+    }
+
+    /// Retain events matching `keep` and prune everything which refers to
+    /// removed events.
+    fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        self.enters.retain(keep);
+
+        let ids = self
+            .enters
+            .iter()
+            .map(|event| event.id)
+            .collect::<HashSet<_>>();
+
+        self.leaves.retain(|leave| ids.contains(&leave.sibling));
+
+        for event in &mut self.enters {
+            if event.parent.map_or(false, |parent| !ids.contains(&parent)) {
+                event.parent = None;
+            }
+        }
+
+        let locks = self
+            .enters
+            .iter()
+            .map(|event| event.lock)
+            .collect::<HashSet<_>>();
+
+        self.creation_sites.retain(|lock, _| locks.contains(lock));
+        self.keys.retain(|lock, _| locks.contains(lock));
+
+        let threads = self
+            .enters
+            .iter()
+            .map(|event| (event.process, event.thread_index))
+            .collect::<HashSet<_>>();
+
+        for (process, names) in &mut self.thread_names {
+            names.retain(|thread_index, _| threads.contains(&(*process, *thread_index)));
+        }
+
+        self.thread_names.retain(|_, names| !names.is_empty());
+    }
+
+    /// Partition events into groups by key, skipping events without a key.
+    fn partition<K, F>(&self, mut key: F) -> HashMap<K, Events>
+    where
+        K: Eq + Hash,
+        F: FnMut(&Event) -> Option<K>,
+    {
+        let index = self
+            .enters
+            .iter()
+            .enumerate()
+            .map(|(n, event)| (event.id, n))
+            .collect::<HashMap<_, _>>();
+
+        let mut groups = HashMap::<_, BTreeSet<_>>::new();
+
+        for (n, event) in self.enters.iter().enumerate() {
+            let Some(key) = key(event) else {
+                continue;
+            };
+
+            let group = groups.entry(key).or_default();
+
+            if !group.insert(n) {
+                continue;
+            }
+
+            let mut parent = event.parent;
+
+            // Include ancestors until we find one which is already included.
+            while let Some(n) = parent.and_then(|id| index.get(&id).copied()) {
+                if !group.insert(n) {
+                    break;
+                }
+
+                parent = self.enters[n].parent;
+            }
+        }
+
+        let leaves = self
+            .leaves
+            .iter()
+            .enumerate()
+            .map(|(n, leave)| (leave.sibling, n))
+            .collect::<HashMap<_, _>>();
+
+        let mut output = HashMap::with_capacity(groups.len());
+
+        for (key, group) in groups {
+            let mut included = group
+                .iter()
+                .flat_map(|&n| leaves.get(&self.enters[n].id).copied())
+                .collect::<Vec<_>>();
+
+            included.sort_unstable();
+
+            let enters = group.iter().map(|&n| self.enters[n].clone()).collect();
+            let leaves = included.iter().map(|&n| self.leaves[n].clone()).collect();
+
+            output.insert(key, self.with_events(enters, leaves));
+        }
+
+        output
+    }
+}
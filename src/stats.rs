@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::{Events, LockId, Outcome};
+
+/// Aggregate statistics of the acquisitions in a capture.
+///
+/// Returned by [`Events::stats`]. Unlike a [`Summary`], this only keeps
+/// running totals, so it's cheap to compute even for large captures.
+///
+/// [`Summary`]: crate::Summary
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Statistics of each lock.
+    pub locks: BTreeMap<LockId, LockStats>,
+    /// Statistics of all locks wrapping the same type, by the name of the
+    /// type.
+    pub types: BTreeMap<&'static str, LockStats>,
+}
+
+/// Aggregate statistics of the acquisitions of a lock, or of a group of
+/// locks.
+///
+/// Durations are zero if there was nothing to measure them from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockStats {
+    /// The number of times the lock was acquired, not counting attempts which
+    /// timed out or failed without blocking.
+    pub acquisitions: usize,
+    /// The number of acquisitions which found the lock held and had to wait
+    /// for it. See [`Event::contended`].
+    ///
+    /// [`Event::contended`]: crate::Event::contended
+    pub contended: usize,
+    /// The total duration for which the lock was held.
+    pub hold_total: Duration,
+    /// The mean duration for which the lock was held.
+    pub hold_mean: Duration,
+    /// The longest duration for which the lock was held.
+    pub hold_max: Duration,
+    /// The total duration for which the lock was waited for, including
+    /// attempts which timed out.
+    pub wait_total: Duration,
+    /// The mean duration for which the lock was waited for.
+    pub wait_mean: Duration,
+    /// The longest duration for which the lock was waited for.
+    pub wait_max: Duration,
+}
+
+/// Running totals of a lock, in nanoseconds.
+#[derive(Default)]
+struct Totals {
+    acquisitions: usize,
+    contended: usize,
+    holds: u64,
+    hold_total: u128,
+    hold_max: u64,
+    waits: u64,
+    wait_total: u128,
+    wait_max: u64,
+}
+
+impl Totals {
+    fn add(&mut self, acquired: bool, contended: bool, wait: Option<u64>, hold: Option<u64>) {
+        if acquired {
+            self.acquisitions += 1;
+            self.contended += usize::from(contended);
+        }
+
+        if let Some(wait) = wait {
+            self.waits += 1;
+            self.wait_total += u128::from(wait);
+            self.wait_max = self.wait_max.max(wait);
+        }
+
+        if let Some(hold) = hold {
+            self.holds += 1;
+            self.hold_total += u128::from(hold);
+            self.hold_max = self.hold_max.max(hold);
+        }
+    }
+
+    fn into_stats(self) -> LockStats {
+        LockStats {
+            acquisitions: self.acquisitions,
+            contended: self.contended,
+            hold_total: nanos(self.hold_total),
+            hold_mean: nanos(
+                self.hold_total
+                    .checked_div(u128::from(self.holds))
+                    .unwrap_or(0),
+            ),
+            hold_max: Duration::from_nanos(self.hold_max),
+            wait_total: nanos(self.wait_total),
+            wait_mean: nanos(
+                self.wait_total
+                    .checked_div(u128::from(self.waits))
+                    .unwrap_or(0),
+            ),
+            wait_max: Duration::from_nanos(self.wait_max),
+        }
+    }
+}
+
+impl Events {
+    /// Compute aggregate statistics of how often each lock was acquired, and
+    /// how long it was held and waited for.
+    ///
+    /// Statistics are computed both for each lock and for each type wrapped
+    /// by locks. Use [`Events::summary`] for percentiles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stats = unlock::drain().stats();
+    ///
+    /// for (lock, stats) in &stats.locks {
+    ///     println!("{lock:?}: {} acquisitions, {:?} max wait", stats.acquisitions, stats.wait_max);
+    /// }
+    /// ```
+    pub fn stats(&self) -> Stats {
+        let mut locks = BTreeMap::<_, Totals>::new();
+        let mut types = BTreeMap::<_, Totals>::new();
+
+        self.for_each_acquisition(|enter, wait, hold| {
+            let acquired = enter.outcome == Outcome::Acquired;
+
+            for totals in [
+                locks.entry(enter.lock).or_default(),
+                types.entry(enter.type_name()).or_default(),
+            ] {
+                totals.add(acquired, enter.contended, wait, hold);
+            }
+        });
+
+        Stats {
+            locks: locks
+                .into_iter()
+                .map(|(lock, totals)| (lock, totals.into_stats()))
+                .collect(),
+            types: types
+                .into_iter()
+                .map(|(type_name, totals)| (type_name, totals.into_stats()))
+                .collect(),
+        }
+    }
+}
+
+/// Convert a duration in nanoseconds, saturating if it's too large.
+fn nanos(nanos: u128) -> Duration {
+    Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+}
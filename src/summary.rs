@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::{Event, Events, LockId, Outcome};
+
+/// Summary of how long locks were held and waited for.
+///
+/// Returned by [`Events::summary`].
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    locks: BTreeMap<LockId, Durations>,
+}
+
+/// Hold and wait durations of a single lock, in nanoseconds.
+///
+/// These are sorted once they're part of a [`Summary`].
+#[derive(Debug, Clone, Default)]
+struct Durations {
+    holds: Vec<u64>,
+    waits: Vec<u64>,
+}
+
+impl Summary {
+    /// The locks covered by the summary.
+    pub fn locks(&self) -> impl Iterator<Item = LockId> + '_ {
+        self.locks.keys().copied()
+    }
+
+    /// The number of times the given lock was held.
+    pub fn hold_count(&self, lock: LockId) -> usize {
+        self.locks.get(&lock).map_or(0, |d| d.holds.len())
+    }
+
+    /// The total duration for which the given lock was held.
+    pub fn hold_total(&self, lock: LockId) -> Duration {
+        self.locks
+            .get(&lock)
+            .map_or(Duration::ZERO, |d| total(&d.holds))
+    }
+
+    /// The mean duration for which the given lock was held.
+    pub fn hold_mean(&self, lock: LockId) -> Option<Duration> {
+        mean(&self.locks.get(&lock)?.holds)
+    }
+
+    /// The longest duration for which the given lock was held.
+    pub fn hold_max(&self, lock: LockId) -> Option<Duration> {
+        let holds = &self.locks.get(&lock)?.holds;
+        holds.last().copied().map(Duration::from_nanos)
+    }
+
+    /// The `p`th percentile of the durations for which the given lock was
+    /// held, where `p` is between `0.0` and `100.0`.
+    ///
+    /// This uses the nearest-rank method, so the result is always one of the
+    /// recorded durations. Returns `None` if the lock was never held.
+    pub fn hold_percentile(&self, lock: LockId, p: f64) -> Option<Duration> {
+        percentile(&self.locks.get(&lock)?.holds, p)
+    }
+
+    /// The number of times the given lock was waited for, which includes
+    /// attempts which timed out.
+    pub fn wait_count(&self, lock: LockId) -> usize {
+        self.locks.get(&lock).map_or(0, |d| d.waits.len())
+    }
+
+    /// The total duration for which the given lock was waited for.
+    pub fn wait_total(&self, lock: LockId) -> Duration {
+        self.locks
+            .get(&lock)
+            .map_or(Duration::ZERO, |d| total(&d.waits))
+    }
+
+    /// The mean duration for which the given lock was waited for.
+    pub fn wait_mean(&self, lock: LockId) -> Option<Duration> {
+        mean(&self.locks.get(&lock)?.waits)
+    }
+
+    /// The longest duration for which the given lock was waited for.
+    pub fn wait_max(&self, lock: LockId) -> Option<Duration> {
+        let waits = &self.locks.get(&lock)?.waits;
+        waits.last().copied().map(Duration::from_nanos)
+    }
+
+    /// The `p`th percentile of the durations for which the given lock was
+    /// waited for, where `p` is between `0.0` and `100.0`.
+    ///
+    /// See [`Summary::hold_percentile`] for details.
+    pub fn wait_percentile(&self, lock: LockId, p: f64) -> Option<Duration> {
+        percentile(&self.locks.get(&lock)?.waits, p)
+    }
+}
+
+impl Events {
+    /// Summarize how long each lock was held and waited for.
+    ///
+    /// The summary keeps the duration of every acquisition, which takes a
+    /// small fraction of the memory used by the events themselves, so that
+    /// percentiles are exact.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let summary = unlock::drain().summary();
+    ///
+    /// for lock in summary.locks() {
+    ///     println!("{lock:?}: p99 wait {:?}", summary.wait_percentile(lock, 99.0));
+    /// }
+    /// ```
+    pub fn summary(&self) -> Summary {
+        let mut locks = self.durations();
+
+        for durations in locks.values_mut() {
+            durations.holds.sort_unstable();
+            durations.waits.sort_unstable();
+        }
+
+        Summary { locks }
+    }
+
+    /// The time spent waiting to acquire each lock, with one duration for
+    /// every acquisition in the order of the collection.
+    ///
+    /// This includes attempts which timed out. See [`Events::summary`] for
+    /// aggregate statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let events = unlock::drain();
+    ///
+    /// for (lock, waits) in events.wait_times() {
+    ///     println!("{lock:?}: {waits:?}");
+    /// }
+    /// ```
+    pub fn wait_times(&self) -> BTreeMap<LockId, Vec<Duration>> {
+        self.durations()
+            .into_iter()
+            .map(|(lock, durations)| (lock, to_durations(durations.waits)))
+            .collect()
+    }
+
+    /// The time each lock was held for once acquired, with one duration for
+    /// every acquisition in the order of the collection.
+    ///
+    /// Acquisitions which failed or were never released are not included.
+    /// See [`Events::summary`] for aggregate statistics.
+    pub fn hold_times(&self) -> BTreeMap<LockId, Vec<Duration>> {
+        self.durations()
+            .into_iter()
+            .map(|(lock, durations)| (lock, to_durations(durations.holds)))
+            .collect()
+    }
+
+    /// Collect the hold and wait durations of each lock.
+    fn durations(&self) -> BTreeMap<LockId, Durations> {
+        let mut locks = BTreeMap::<_, Durations>::new();
+
+        self.for_each_acquisition(|enter, wait, hold| {
+            let durations = locks.entry(enter.lock).or_default();
+            durations.waits.extend(wait);
+            durations.holds.extend(hold);
+        });
+
+        locks
+    }
+
+    /// Call `f` with every acquisition in the order of the collection, along
+    /// with how long it waited and how long it was held in nanoseconds.
+    ///
+    /// The wait is missing if the acquisition has no nested acquiring event or
+    /// if it was an attempt without blocking which failed, and the hold is
+    /// missing if it failed or was never released.
+    pub(crate) fn for_each_acquisition<F>(&self, mut f: F)
+    where
+        F: FnMut(&Event, Option<u64>, Option<u64>),
+    {
+        let holds = self.holds();
+
+        for enter in &self.enters {
+            if enter.parent.is_some() {
+                continue;
+            }
+
+            let wait = holds
+                .acquired(enter.id)
+                .filter(|_| enter.outcome != Outcome::TryFailed)
+                .map(|open| open.saturating_sub(enter.timestamp));
+
+            let hold = holds
+                .hold(enter)
+                .and_then(|(open, close)| Some(close?.saturating_sub(open)));
+
+            f(enter, wait, hold);
+        }
+    }
+}
+
+/// Convert durations in nanoseconds.
+fn to_durations(durations: Vec<u64>) -> Vec<Duration> {
+    durations.into_iter().map(Duration::from_nanos).collect()
+}
+
+/// The sum of the given durations.
+fn total(durations: &[u64]) -> Duration {
+    Duration::from_nanos(durations.iter().fold(0u64, |a, &d| a.saturating_add(d)))
+}
+
+/// The mean of the given durations.
+fn mean(durations: &[u64]) -> Option<Duration> {
+    if durations.is_empty() {
+        return None;
+    }
+
+    let total = durations.iter().map(|&d| u128::from(d)).sum::<u128>();
+    Some(Duration::from_nanos(
+        (total / durations.len() as u128) as u64,
+    ))
+}
+
+/// The nearest-rank percentile of sorted durations.
+fn percentile(sorted: &[u64], p: f64) -> Option<Duration> {
+    if sorted.is_empty() || p.is_nan() {
+        return None;
+    }
+
+    let p = p.clamp(0.0, 100.0) / 100.0;
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    let index = rank.clamp(1, sorted.len()) - 1;
+    Some(Duration::from_nanos(sorted[index]))
+}
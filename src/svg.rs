@@ -0,0 +1,262 @@
+//! Module to format captured lock events as a standalone svg image.
+//!
+//! Unlike [`html`][crate::html], the produced image doesn't need a browser or
+//! any scripts to be viewed, which makes it suitable for embedding in static
+//! documentation. Each section has a `<title>` element which most viewers show
+//! as a tooltip.
+
+use std::io;
+use std::time::Duration;
+
+use crate::html::{escape, GroupBy};
+use crate::layout::{position, Layout};
+use crate::{Error, Event, Events, Outcome};
+
+/// Margin around the image.
+const MARGIN: f32 = 10.0;
+/// Width of the column containing timeline headings.
+const HEADING_WIDTH: f32 = 100.0;
+/// Height of a lock title.
+const TITLE_HEIGHT: f32 = 24.0;
+/// Height of a single timeline.
+const ROW_HEIGHT: f32 = 20.0;
+/// Space between timelines.
+const ROW_GAP: f32 = 2.0;
+/// Space between locks.
+const LOCK_GAP: f32 = 10.0;
+
+/// Options used when writing svg.
+#[derive(Debug, Clone)]
+pub struct Options {
+    width: u32,
+    group_by: GroupBy,
+}
+
+impl Options {
+    /// Construct the default options.
+    pub fn new() -> Self {
+        Self {
+            width: 1200,
+            group_by: GroupBy::Thread,
+        }
+    }
+
+    /// Set the total width of the image in pixels.
+    ///
+    /// Defaults to `1200`.
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set how events are grouped into timelines.
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = group_by;
+        self
+    }
+}
+
+impl Default for Options {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Write events as an svg image to the given output.
+///
+/// Every lock is rendered as a group of timelines, one for each thread or task
+/// depending on [`Options::group_by`]. All timelines share the same time axis.
+///
+/// # Errors
+///
+/// If there are no events to render, such as when [`capture`] was never
+/// called, this returns [`Error::EmptyTrace`] without writing anything.
+///
+/// ```
+/// let events = unlock::drain();
+/// let mut out = Vec::new();
+/// let result = unlock::svg::write(&mut out, &events, &unlock::svg::Options::new());
+/// assert!(matches!(result, Err(unlock::Error::EmptyTrace)));
+/// assert!(out.is_empty());
+/// ```
+///
+/// [`capture`]: crate::capture
+pub fn write<W>(mut out: W, events: &Events, options: &Options) -> Result<(), Error>
+where
+    W: io::Write,
+{
+    let Some(layout) = Layout::new(events, options.group_by, None, &mut |_| true) else {
+        return Err(Error::EmptyTrace);
+    };
+
+    let rows = layout.locks.values().map(|rows| rows.len()).sum::<usize>() as f32;
+    let locks = layout.locks.len() as f32;
+
+    let width = (options.width as f32).max(HEADING_WIDTH + MARGIN * 3.0);
+    let height = MARGIN * 2.0
+        + TITLE_HEIGHT
+        + locks * (TITLE_HEIGHT + LOCK_GAP)
+        + rows * (ROW_HEIGHT + ROW_GAP);
+
+    let chart = Chart {
+        span: layout.span,
+        x: MARGIN * 2.0 + HEADING_WIDTH,
+        width: width - MARGIN * 3.0 - HEADING_WIDTH,
+    };
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="12">"#
+    )?;
+    writeln!(
+        out,
+        r##"<rect width="{width}" height="{height}" fill="#ffffff"/>"##
+    )?;
+
+    let (start, end) = layout.span;
+    let duration = Duration::from_nanos(end - start);
+    let count = events.len();
+
+    let mut y = MARGIN;
+
+    writeln!(
+        out,
+        r#"<text x="{MARGIN}" y="{}">{count} events over {duration:?}</text>"#,
+        y + TITLE_HEIGHT / 2.0
+    )?;
+
+    y += TITLE_HEIGHT;
+
+    for ((process, lock, type_name), rows) in &layout.locks {
+        let thread_names = events.thread_names.get(process);
+        let kind = lock.kind();
+        let index = lock.index();
+        let type_name = escape(type_name);
+
+        y += LOCK_GAP;
+
+        writeln!(
+            out,
+            r#"<text x="{MARGIN}" y="{}" font-weight="bold">{kind:?}&lt;{type_name}&gt; (lock index: {index})</text>"#,
+            y + TITLE_HEIGHT / 2.0
+        )?;
+
+        y += TITLE_HEIGHT;
+
+        for (row, events) in rows {
+            let heading = escape(&row.heading(thread_names));
+
+            writeln!(
+                out,
+                r##"<rect x="{MARGIN}" y="{y}" width="{}" height="{ROW_HEIGHT}" fill="#f0f0f0"/>"##,
+                width - MARGIN * 2.0
+            )?;
+            writeln!(
+                out,
+                r#"<text x="{}" y="{}">{heading}</text>"#,
+                MARGIN + 4.0,
+                y + ROW_HEIGHT * 0.7
+            )?;
+
+            for ev in events {
+                write_section(&mut out, &layout, &chart, ev, y, ROW_HEIGHT)?;
+            }
+
+            y += ROW_HEIGHT + ROW_GAP;
+        }
+    }
+
+    writeln!(out, "</svg>")?;
+    Ok(())
+}
+
+/// The area in which sections are drawn.
+struct Chart {
+    span: (u64, u64),
+    x: f32,
+    width: f32,
+}
+
+fn write_section(
+    out: &mut dyn io::Write,
+    layout: &Layout<'_>,
+    chart: &Chart,
+    ev: &Event,
+    y: f32,
+    height: f32,
+) -> io::Result<()> {
+    let Some(close) = layout.closes.get(&ev.id).copied() else {
+        return Ok(());
+    };
+
+    let open = ev.timestamp;
+
+    let Some((left, width)) = position(chart.span, open, close) else {
+        return Ok(());
+    };
+
+    let x = chart.x + left * chart.width;
+    let width = (width * chart.width).max(1.0);
+
+    let name = ev.name();
+
+    let fill = match ev.outcome {
+        Outcome::TimedOut => "#d00000",
+        Outcome::TryFailed => "#ff8000",
+        _ => fill(name),
+    };
+
+    let s = Duration::from_nanos(open);
+    let e = Duration::from_nanos(close);
+    let title = escape(&format!("{name} ({s:?}-{e:?})"));
+
+    writeln!(
+        out,
+        r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" fill="{fill}"><title>{title}</title></rect>"#
+    )?;
+
+    // Mark contended sections with a line along the top, like the html heat
+    // classes.
+    let heat = match ev.waiters {
+        0 => None,
+        1 => Some("#ffc040"),
+        2..=3 => Some("#ff8000"),
+        _ => Some("#d00000"),
+    };
+
+    if let Some(heat) = heat {
+        writeln!(
+            out,
+            r#"<rect x="{x}" y="{y}" width="{width}" height="3" fill="{heat}"/>"#
+        )?;
+    }
+
+    // Children are drawn in the lower half of their parent.
+    let y = y + height / 2.0;
+    let height = height / 2.0;
+
+    for child in layout.children.get(&ev.id).into_iter().flatten() {
+        write_section(out, layout, chart, child, y, height)?;
+    }
+
+    Ok(())
+}
+
+/// The fill color of a section, matching the html stylesheet.
+fn fill(name: &str) -> &'static str {
+    match name {
+        "critical" => "#e0e0e0",
+        "read" => "#367336",
+        "read_recursive" => "#5a9e5a",
+        "write" => "#ff8080",
+        "try_read" => "#a0e0a0",
+        "try_write" => "#ffb0b0",
+        "upgradable_read" => "#80b0ff",
+        "lock" => "#ff80ff",
+        "try_lock" => "#ffb0ff",
+        "try_lock_for" => "#c080ff",
+        "wait" => "#c0c080",
+        _ => "#a0a0a0",
+    }
+}
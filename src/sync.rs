@@ -1,13 +1,21 @@
 use std::any::type_name;
+use std::cell::Cell;
 use std::fmt;
+use std::mem::{self, ManuallyDrop};
 use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::AtomicU32;
+use std::time::Duration;
 
 use super::event::{EventId, LockId, LockKind};
+use super::intern::{name, Name};
 use super::tracing_context::get;
 
 /// Wrapper for [`parking_lot::RwLock<T>`].
 pub struct RwLock<T> {
     lock: LockId,
+    type_name: Name,
+    waiters: AtomicU32,
     inner: parking_lot::RwLock<T>,
 }
 
@@ -15,33 +23,169 @@ impl<T> RwLock<T> {
     /// Create a new `RwLock<T>`.
     #[inline]
     pub fn new(value: T) -> Self {
+        let lock = LockId::next(LockKind::RwLock);
+        get().created(lock);
+
         Self {
-            lock: LockId::next(LockKind::RwLock),
+            lock,
+            type_name: Name::new(type_name::<T>()),
+            waiters: AtomicU32::new(0),
             inner: parking_lot::RwLock::new(value),
         }
     }
 
+    /// Create a new `RwLock<T>` with a key which identifies it in captured
+    /// events.
+    ///
+    /// The index of a lock depends on the order in which locks are
+    /// constructed, which might vary between runs. The key on the other hand
+    /// is chosen by the user, so it can be used to identify the same lock
+    /// across separate runs and processes. See [`Events::lock_key`].
+    ///
+    /// This is only available if the `trace` feature is enabled, since the
+    /// lock types are otherwise re-exported as they are.
+    ///
+    /// [`Events::lock_key`]: crate::Events::lock_key
+    #[inline]
+    pub fn with_key(value: T, key: &'static str) -> Self {
+        let this = Self::new(value);
+        get().keyed(this.lock, key);
+        this
+    }
+
     /// Lock the `RwLock<T>` for reading.
     #[inline]
     pub fn read(&self) -> RwLockReadGuard<'_, T> {
-        let cx = get();
-        let event = cx.enter(self.lock, "critical", type_name::<T>(), None);
-        let inner = cx.with(self.lock, "read", type_name::<T>(), event, || {
-            self.inner.read()
-        });
+        let (inner, event) = get().acquire(
+            self.lock,
+            name!("read"),
+            self.type_name,
+            &self.waiters,
+            || self.inner.try_read(),
+            || self.inner.read(),
+        );
+        RwLockReadGuard { inner, event }
+    }
+
+    /// Lock the `RwLock<T>` for reading, without checking for pending
+    /// writers.
+    ///
+    /// See [`parking_lot::RwLock::read_recursive`]. This is recorded as a
+    /// distinct `read_recursive` event, since it can starve writers.
+    #[inline]
+    pub fn read_recursive(&self) -> RwLockReadGuard<'_, T> {
+        let (inner, event) = get().acquire(
+            self.lock,
+            name!("read_recursive"),
+            self.type_name,
+            &self.waiters,
+            || self.inner.try_read_recursive(),
+            || self.inner.read_recursive(),
+        );
         RwLockReadGuard { inner, event }
     }
 
     /// Lock the `RwLock<T>` for writing.
     #[inline]
     pub fn write(&self) -> RwLockWriteGuard<'_, T> {
-        let cx = get();
-        let event = cx.enter(self.lock, "critical", type_name::<T>(), None);
-        let inner = cx.with(self.lock, "write", type_name::<T>(), event, || {
-            self.inner.write()
-        });
+        let (inner, event) = get().acquire(
+            self.lock,
+            name!("write"),
+            self.type_name,
+            &self.waiters,
+            || self.inner.try_write(),
+            || self.inner.write(),
+        );
         RwLockWriteGuard { inner, event }
     }
+
+    /// Attempt to lock the `RwLock<T>` for reading without blocking.
+    ///
+    /// The attempt is recorded as a `try_read` event, see [`Mutex::try_lock`]
+    /// for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let lock = unlock::RwLock::new(0);
+    ///
+    /// let guard = lock.write();
+    /// assert!(lock.try_read().is_none());
+    /// drop(guard);
+    ///
+    /// assert_eq!(*lock.try_read().unwrap(), 0);
+    /// ```
+    #[inline]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let (inner, event) = get().try_acquire(
+            self.lock,
+            name!("try_read"),
+            self.type_name,
+            &self.waiters,
+            || self.inner.try_read(),
+        );
+
+        Some(RwLockReadGuard {
+            inner: inner?,
+            event,
+        })
+    }
+
+    /// Attempt to lock the `RwLock<T>` for writing without blocking.
+    ///
+    /// The attempt is recorded as a `try_write` event, see [`Mutex::try_lock`]
+    /// for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let lock = unlock::RwLock::new(0);
+    ///
+    /// let guard = lock.read();
+    /// assert!(lock.try_write().is_none());
+    /// drop(guard);
+    ///
+    /// *lock.try_write().unwrap() += 1;
+    /// assert_eq!(*lock.read(), 1);
+    /// ```
+    #[inline]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let (inner, event) = get().try_acquire(
+            self.lock,
+            name!("try_write"),
+            self.type_name,
+            &self.waiters,
+            || self.inner.try_write(),
+        );
+
+        Some(RwLockWriteGuard {
+            inner: inner?,
+            event,
+        })
+    }
+
+    /// Lock the `RwLock<T>` for upgradable reading.
+    ///
+    /// See [`parking_lot::RwLock::upgradable_read`].
+    #[inline]
+    pub fn upgradable_read(&self) -> RwLockUpgradableReadGuard<'_, T> {
+        let (inner, event) = get().acquire(
+            self.lock,
+            name!("upgradable_read"),
+            self.type_name,
+            &self.waiters,
+            || self.inner.try_upgradable_read(),
+            || self.inner.upgradable_read(),
+        );
+
+        RwLockUpgradableReadGuard {
+            inner,
+            event,
+            lock: self.lock,
+            type_name: self.type_name,
+            waiters: &self.waiters,
+        }
+    }
 }
 
 impl<T> fmt::Debug for RwLock<T>
@@ -105,31 +249,296 @@ impl<T> Drop for RwLockWriteGuard<'_, T> {
     }
 }
 
+/// Wrapper for [`parking_lot::RwLockUpgradableReadGuard<T>`].
+pub struct RwLockUpgradableReadGuard<'a, T> {
+    inner: parking_lot::RwLockUpgradableReadGuard<'a, T>,
+    event: Option<EventId>,
+    lock: LockId,
+    type_name: Name,
+    waiters: &'a AtomicU32,
+}
+
+impl<'a, T> RwLockUpgradableReadGuard<'a, T> {
+    /// Atomically upgrade to an exclusive write lock.
+    ///
+    /// See [`parking_lot::RwLockUpgradableReadGuard::upgrade`]. The critical
+    /// section of the upgradable read is recorded as released where the
+    /// upgrade starts, and a new critical section is recorded as a `write`
+    /// acquisition which waits for the upgrade to complete. Since the lock is
+    /// never released in between, the two sections are adjacent on the
+    /// timeline of the thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use unlock::RwLockUpgradableReadGuard;
+    ///
+    /// let lock = unlock::RwLock::new(1);
+    ///
+    /// let guard = lock.upgradable_read();
+    /// let mut guard = RwLockUpgradableReadGuard::upgrade(guard);
+    /// *guard += 1;
+    /// drop(guard);
+    ///
+    /// assert_eq!(*lock.read(), 2);
+    /// ```
+    #[inline]
+    pub fn upgrade(s: Self) -> RwLockWriteGuard<'a, T> {
+        let (lock, type_name, waiters) = (s.lock, s.type_name, s.waiters);
+        let (inner, event) = s.into_parts();
+
+        // The upgradable read is left before upgrading, so that it's closed
+        // even if the upgrade panics.
+        get().leave(event);
+
+        let slot = Cell::new(Some(inner));
+
+        let (inner, event) = get().acquire(
+            lock,
+            name!("write"),
+            type_name,
+            waiters,
+            || {
+                let upgradable = slot.take()?;
+
+                match parking_lot::RwLockUpgradableReadGuard::try_upgrade(upgradable) {
+                    Ok(write) => Some(write),
+                    Err(upgradable) => {
+                        slot.set(Some(upgradable));
+                        None
+                    }
+                }
+            },
+            || match slot.take() {
+                Some(upgradable) => parking_lot::RwLockUpgradableReadGuard::upgrade(upgradable),
+                None => unreachable!(),
+            },
+        );
+
+        RwLockWriteGuard { inner, event }
+    }
+
+    /// Deconstruct the guard without recording it as released.
+    fn into_parts(
+        self,
+    ) -> (
+        parking_lot::RwLockUpgradableReadGuard<'a, T>,
+        Option<EventId>,
+    ) {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: The guard is never dropped, so the inner guard is moved out
+        // of it exactly once.
+        let inner = unsafe { ptr::read(&this.inner) };
+        (inner, this.event)
+    }
+
+    /// Upgrade to an exclusive write lock, call `f` with exclusive access to
+    /// the data and downgrade back to an upgradable read lock.
+    ///
+    /// The time spent in `f` is recorded as a `write` event nested inside of
+    /// the upgradable read. The lock is downgraded even if `f` panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let lock = unlock::RwLock::new(1);
+    ///
+    /// let mut guard = lock.upgradable_read();
+    ///
+    /// if *guard == 1 {
+    ///     guard.with_upgraded(|value| *value += 1);
+    /// }
+    ///
+    /// assert_eq!(*guard, 2);
+    /// ```
+    pub fn with_upgraded<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        /// Downgrades the write guard back into the slot of the upgradable
+        /// guard when dropped.
+        struct Downgrade<'a, 'b, T> {
+            slot: &'b mut parking_lot::RwLockUpgradableReadGuard<'a, T>,
+            write: ManuallyDrop<parking_lot::RwLockWriteGuard<'a, T>>,
+            event: Option<EventId>,
+        }
+
+        impl<T> Drop for Downgrade<'_, '_, T> {
+            #[inline]
+            fn drop(&mut self) {
+                get().leave(self.event);
+
+                // SAFETY: The write guard is only taken here, and the slot
+                // was logically moved out of when the write guard was
+                // constructed.
+                unsafe {
+                    let write = ManuallyDrop::take(&mut self.write);
+                    let upgradable = parking_lot::RwLockWriteGuard::downgrade_to_upgradable(write);
+                    ptr::write(self.slot, upgradable);
+                }
+            }
+        }
+
+        // SAFETY: The guard in the slot is replaced by the downgraded guard
+        // before it's observed again, including during unwinding.
+        let upgradable = unsafe { ptr::read(&self.inner) };
+        let write = parking_lot::RwLockUpgradableReadGuard::upgrade(upgradable);
+
+        let mut downgrade = Downgrade {
+            slot: &mut self.inner,
+            write: ManuallyDrop::new(write),
+            event: None,
+        };
+
+        // Recording can panic through a custom thread index source, so this
+        // is only done once the guard is restored by `Downgrade` on unwind.
+        downgrade.event = get().nested(self.event, self.lock, name!("write"), self.type_name);
+
+        f(&mut downgrade.write)
+    }
+}
+
+impl<T> Deref for RwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> Drop for RwLockUpgradableReadGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        get().leave(self.event);
+    }
+}
+
 /// Wrapper for [`parking_lot::Mutex<T>`].
 pub struct Mutex<T> {
     inner: parking_lot::Mutex<T>,
     lock: LockId,
+    type_name: Name,
+    waiters: AtomicU32,
 }
 
 impl<T> Mutex<T> {
     /// Create a new `Mutex<T>`.
     #[inline]
     pub fn new(value: T) -> Self {
+        let lock = LockId::next(LockKind::Mutex);
+        get().created(lock);
+
         Self {
             inner: parking_lot::Mutex::new(value),
-            lock: LockId::next(LockKind::Mutex),
+            lock,
+            type_name: Name::new(type_name::<T>()),
+            waiters: AtomicU32::new(0),
         }
     }
 
+    /// Create a new `Mutex<T>` with a key which identifies it in captured
+    /// events.
+    ///
+    /// The index of a lock depends on the order in which locks are
+    /// constructed, which might vary between runs. The key on the other hand
+    /// is chosen by the user, so it can be used to identify the same lock
+    /// across separate runs and processes. See [`Events::lock_key`].
+    ///
+    /// This is only available if the `trace` feature is enabled, since the
+    /// lock types are otherwise re-exported as they are.
+    ///
+    /// [`Events::lock_key`]: crate::Events::lock_key
+    #[inline]
+    pub fn with_key(value: T, key: &'static str) -> Self {
+        let this = Self::new(value);
+        get().keyed(this.lock, key);
+        this
+    }
+
     /// Lock the `Mutex<T>` for writing.
     #[inline]
     pub fn lock(&self) -> MutexGuard<'_, T> {
-        let cx = get();
-        let event = cx.enter(self.lock, "critical", type_name::<T>(), None);
-        let inner = cx.with(self.lock, "lock", type_name::<T>(), event, || {
-            self.inner.lock()
-        });
-        MutexGuard { inner, event }
+        let (inner, event) = get().acquire(
+            self.lock,
+            name!("lock"),
+            self.type_name,
+            &self.waiters,
+            || self.inner.try_lock(),
+            || self.inner.lock(),
+        );
+        MutexGuard {
+            inner,
+            event,
+            mutex: self,
+        }
+    }
+
+    /// Attempt to lock the `Mutex<T>` for writing without blocking.
+    ///
+    /// The attempt is recorded as a `try_lock` event, which distinguishes it
+    /// from blocking acquisitions through [`lock`]. Since the attempt never
+    /// waits, a successful attempt is never considered contended. A failed
+    /// attempt is only recorded if enabled through
+    /// [`CaptureConfig::record_failed_tries`], in which case it has the
+    /// [`Outcome::TryFailed`] outcome.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let lock = unlock::Mutex::new(0);
+    ///
+    /// let guard = lock.lock();
+    /// assert!(lock.try_lock().is_none());
+    /// drop(guard);
+    ///
+    /// *lock.try_lock().unwrap() += 1;
+    /// assert_eq!(*lock.lock(), 1);
+    /// ```
+    ///
+    /// [`lock`]: Self::lock
+    /// [`CaptureConfig::record_failed_tries`]: crate::CaptureConfig::record_failed_tries
+    /// [`Outcome::TryFailed`]: crate::Outcome::TryFailed
+    #[inline]
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let (inner, event) = get().try_acquire(
+            self.lock,
+            name!("try_lock"),
+            self.type_name,
+            &self.waiters,
+            || self.inner.try_lock(),
+        );
+
+        Some(MutexGuard {
+            inner: inner?,
+            event,
+            mutex: self,
+        })
+    }
+
+    /// Attempt to lock the `Mutex<T>` for writing, giving up after `timeout`
+    /// has elapsed.
+    ///
+    /// The attempt is recorded as a `try_lock_for` event along with the
+    /// timeout and whether it was hit, see [`Event::outcome`].
+    ///
+    /// [`Event::outcome`]: crate::Event::outcome
+    #[inline]
+    pub fn try_lock_for(&self, timeout: Duration) -> Option<MutexGuard<'_, T>> {
+        let (inner, event) = get().acquire_timed(
+            self.lock,
+            name!("try_lock_for"),
+            self.type_name,
+            &self.waiters,
+            timeout,
+            |timeout| self.inner.try_lock_for(timeout),
+        );
+
+        Some(MutexGuard {
+            inner: inner?,
+            event,
+            mutex: self,
+        })
     }
 }
 
@@ -147,6 +556,100 @@ where
 pub struct MutexGuard<'a, T> {
     inner: parking_lot::MutexGuard<'a, T>,
     event: Option<EventId>,
+    mutex: &'a Mutex<T>,
+}
+
+impl<'a, T> MutexGuard<'a, T> {
+    /// Make a new [`MappedMutexGuard`] for a component of the locked data.
+    ///
+    /// See [`parking_lot::MutexGuard::map`]. The acquisition is recorded as
+    /// released once the mapped guard is dropped.
+    #[inline]
+    pub fn map<U, F>(s: Self, f: F) -> MappedMutexGuard<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+        U: ?Sized,
+    {
+        let (inner, event) = s.into_parts();
+        let event = LeaveOnUnwind(event);
+        let inner = parking_lot::MutexGuard::map(inner, f);
+
+        MappedMutexGuard {
+            inner,
+            event: event.defuse(),
+        }
+    }
+
+    /// Attempt to make a new [`MappedMutexGuard`] for a component of the
+    /// locked data, returning the original guard if `f` returns `None`.
+    ///
+    /// See [`parking_lot::MutexGuard::try_map`]. Either way the acquisition
+    /// is recorded as released once the returned guard is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use unlock::{Mutex, MutexGuard};
+    ///
+    /// let lock = Mutex::new(None::<u32>);
+    ///
+    /// let guard = MutexGuard::try_map(lock.lock(), |value| value.as_mut());
+    /// let mut guard = guard.err().unwrap();
+    /// *guard = Some(1);
+    ///
+    /// let mut value = MutexGuard::try_map(guard, |value| value.as_mut()).ok().unwrap();
+    /// *value += 1;
+    /// drop(value);
+    ///
+    /// assert_eq!(*lock.lock(), Some(2));
+    /// ```
+    #[inline]
+    pub fn try_map<U, F>(s: Self, f: F) -> Result<MappedMutexGuard<'a, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+        U: ?Sized,
+    {
+        let mutex = s.mutex;
+        let (inner, event) = s.into_parts();
+        let event = LeaveOnUnwind(event);
+
+        match parking_lot::MutexGuard::try_map(inner, f) {
+            Ok(inner) => Ok(MappedMutexGuard {
+                inner,
+                event: event.defuse(),
+            }),
+            Err(inner) => Err(MutexGuard {
+                inner,
+                event: event.defuse(),
+                mutex,
+            }),
+        }
+    }
+
+    /// Leak the guard, returning a reference to the locked data which lives as
+    /// long as the lock itself.
+    ///
+    /// See [`parking_lot::MutexGuard::leak`]. This leaves the lock locked
+    /// forever, but to avoid showing a critical section which never ends, the
+    /// acquisition is recorded as released at the point it was leaked. Leaked
+    /// critical sections can be found through [`Events::leaked`].
+    ///
+    /// [`Events::leaked`]: crate::Events::leaked
+    #[inline]
+    pub fn leak(s: Self) -> &'a mut T {
+        let (inner, event) = s.into_parts();
+        get().leak(event);
+        parking_lot::MutexGuard::leak(inner)
+    }
+
+    /// Deconstruct the guard without recording it as released.
+    fn into_parts(self) -> (parking_lot::MutexGuard<'a, T>, Option<EventId>) {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: The guard is never dropped, so the inner guard is moved out
+        // of it exactly once.
+        let inner = unsafe { ptr::read(&this.inner) };
+        (inner, this.event)
+    }
 }
 
 impl<T> Deref for MutexGuard<'_, T> {
@@ -171,3 +674,194 @@ impl<T> Drop for MutexGuard<'_, T> {
         get().leave(self.event);
     }
 }
+
+/// Wrapper for [`parking_lot::MappedMutexGuard<T>`].
+pub struct MappedMutexGuard<'a, T>
+where
+    T: ?Sized,
+{
+    inner: parking_lot::MappedMutexGuard<'a, T>,
+    event: Option<EventId>,
+}
+
+impl<T> Deref for MappedMutexGuard<'_, T>
+where
+    T: ?Sized,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for MappedMutexGuard<'_, T>
+where
+    T: ?Sized,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<T> Drop for MappedMutexGuard<'_, T>
+where
+    T: ?Sized,
+{
+    #[inline]
+    fn drop(&mut self) {
+        get().leave(self.event);
+    }
+}
+
+/// Wrapper for [`parking_lot::Condvar`].
+///
+/// Waits are recorded as a `wait` acquisition of the condition variable which
+/// lasts for as long as the thread was blocked, and notifications as
+/// zero-duration `notify_one` and `notify_all` sections. Since the mutex is
+/// released while waiting, its critical section is recorded as released when
+/// the wait starts, and a new one is started once it's re-acquired.
+pub struct Condvar {
+    inner: parking_lot::Condvar,
+    lock: LockId,
+    waiters: AtomicU32,
+}
+
+impl Condvar {
+    /// Create a new `Condvar`.
+    #[inline]
+    pub fn new() -> Self {
+        let lock = LockId::next(LockKind::Condvar);
+        get().created(lock);
+
+        Self {
+            inner: parking_lot::Condvar::new(),
+            lock,
+            waiters: AtomicU32::new(0),
+        }
+    }
+
+    /// Block the current thread until it's woken up by a notification.
+    ///
+    /// See [`parking_lot::Condvar::wait`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// use unlock::{Condvar, Mutex};
+    ///
+    /// let pair = Arc::new((Mutex::new(false), Condvar::new()));
+    /// let pair2 = pair.clone();
+    ///
+    /// let thread = thread::spawn(move || {
+    ///     let (lock, condvar) = &*pair2;
+    ///     *lock.lock() = true;
+    ///     condvar.notify_one();
+    /// });
+    ///
+    /// let (lock, condvar) = &*pair;
+    /// let mut started = lock.lock();
+    ///
+    /// while !*started {
+    ///     condvar.wait(&mut started);
+    /// }
+    ///
+    /// thread.join().unwrap();
+    /// ```
+    #[inline]
+    pub fn wait<T>(&self, guard: &mut MutexGuard<'_, T>) {
+        let cx = get();
+
+        // The mutex is released while waiting.
+        cx.leave(guard.event.take());
+
+        let ((), event) = cx.acquire(
+            self.lock,
+            name!("wait"),
+            name!("()"),
+            &self.waiters,
+            || None,
+            || self.inner.wait(&mut guard.inner),
+        );
+
+        // Nothing is held by waiting on a condition variable.
+        cx.leave(event);
+
+        let mutex = guard.mutex;
+
+        let (_, event) = cx.try_acquire(
+            mutex.lock,
+            name!("lock"),
+            mutex.type_name,
+            &mutex.waiters,
+            || Some(()),
+        );
+
+        guard.event = event;
+    }
+
+    /// Wake up one thread blocked on this condition variable, returning
+    /// whether a thread was woken up.
+    ///
+    /// See [`parking_lot::Condvar::notify_one`].
+    #[inline]
+    pub fn notify_one(&self) -> bool {
+        self.notify(name!("notify_one"));
+        self.inner.notify_one()
+    }
+
+    /// Wake up all threads blocked on this condition variable, returning the
+    /// number of threads woken up.
+    ///
+    /// See [`parking_lot::Condvar::notify_all`].
+    #[inline]
+    pub fn notify_all(&self) -> usize {
+        self.notify(name!("notify_all"));
+        self.inner.notify_all()
+    }
+
+    /// Record a notification as a zero-duration section.
+    fn notify(&self, name: Name) {
+        get().marker(self.lock, name, name!("()"), &self.waiters);
+    }
+}
+
+impl Default for Condvar {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Condvar {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// Records an event as left if dropped, which ensures that it's closed if a
+/// mapping function panics after the inner guard has been taken.
+struct LeaveOnUnwind(Option<EventId>);
+
+impl LeaveOnUnwind {
+    /// Take the event, without leaving it.
+    #[inline]
+    fn defuse(self) -> Option<EventId> {
+        let event = self.0;
+        mem::forget(self);
+        event
+    }
+}
+
+impl Drop for LeaveOnUnwind {
+    #[inline]
+    fn drop(&mut self) {
+        get().leave(self.0);
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::{Event, Events};
+
+impl Events {
+    /// Find lock acquisitions which were held across a point where their task
+    /// yielded.
+    ///
+    /// Holding a blocking lock across an `.await` is a classic async bug. This
+    /// relies on tasks being identified through
+    /// [`set_task_id`][crate::set_task_id], and detects acquisitions where
+    /// another task recorded events on the same thread while the lock was
+    /// held, or where the lock was released on a different thread than the
+    /// one it was acquired on.
+    pub fn guards_held_across_yield(&self) -> Vec<&Event> {
+        let leaves = self
+            .leaves
+            .iter()
+            .map(|leave| (leave.sibling, leave))
+            .collect::<HashMap<_, _>>();
+
+        // The timestamp and task of every event recorded on each thread.
+        let mut threads = HashMap::<_, Vec<_>>::new();
+
+        for enter in &self.enters {
+            threads
+                .entry(enter.thread_index)
+                .or_default()
+                .push((enter.timestamp, enter.task_id));
+        }
+
+        for timeline in threads.values_mut() {
+            timeline.sort_by_key(|&(timestamp, _)| timestamp);
+        }
+
+        let mut output = Vec::new();
+
+        for enter in &self.enters {
+            let (Some(task_id), None) = (enter.task_id, enter.parent) else {
+                continue;
+            };
+
+            let Some(leave) = leaves.get(&enter.id) else {
+                continue;
+            };
+
+            if leave.thread_index != enter.thread_index {
+                output.push(enter);
+                continue;
+            }
+
+            let Some(timeline) = threads.get(&enter.thread_index) else {
+                continue;
+            };
+
+            let from = timeline.partition_point(|&(timestamp, _)| timestamp <= enter.timestamp);
+
+            let yielded = timeline[from..]
+                .iter()
+                .take_while(|&&(timestamp, _)| timestamp < leave.timestamp)
+                .any(|&(_, other)| other.map_or(false, |other| other != task_id));
+
+            if yielded {
+                output.push(enter);
+            }
+        }
+
+        output
+    }
+}
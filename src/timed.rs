@@ -0,0 +1,46 @@
+use std::thread;
+use std::time::Instant;
+
+use crate::Events;
+
+/// A handle to a capture started with [`capture_for`][crate::capture_for].
+///
+/// Recording stops once the duration of the capture has elapsed, after which
+/// the events can be retrieved with [`join`][TimedCapture::join].
+#[must_use = "the captured events can only be retrieved through the handle"]
+pub struct TimedCapture {
+    deadline: Option<Instant>,
+}
+
+impl TimedCapture {
+    pub(crate) fn new(deadline: Option<Instant>) -> Self {
+        Self { deadline }
+    }
+
+    /// Test if the duration of the capture has elapsed, in which case
+    /// [`join`] will not block.
+    ///
+    /// [`join`]: TimedCapture::join
+    pub fn is_finished(&self) -> bool {
+        self.deadline
+            .map_or(true, |deadline| Instant::now() >= deadline)
+    }
+
+    /// Wait for the duration of the capture to elapse and drain its events.
+    ///
+    /// If the capture has already been drained through [`drain`], this
+    /// returns an empty collection.
+    ///
+    /// [`drain`]: crate::drain
+    pub fn join(self) -> Events {
+        if let Some(deadline) = self.deadline {
+            let now = Instant::now();
+
+            if deadline > now {
+                thread::sleep(deadline - now);
+            }
+        }
+
+        crate::drain()
+    }
+}
@@ -117,5 +117,68 @@
         });
     };
 
+    let formatDuration = (nanos) => {
+        if (nanos < 1000) {
+            return Math.round(nanos) + "ns";
+        }
+
+        if (nanos < 1000000) {
+            return (nanos / 1000).toFixed(1) + "µs";
+        }
+
+        if (nanos < 1000000000) {
+            return (nanos / 1000000).toFixed(1) + "ms";
+        }
+
+        return (nanos / 1000000000).toFixed(2) + "s";
+    };
+
+    let loadDurationFilter = () => {
+        let $filter = $w.document.getElementById("duration-filter");
+        let $value = $w.document.getElementById("duration-filter-value");
+
+        if (!$filter || !$value) {
+            return;
+        }
+
+        let max = parseInt($filter.getAttribute("data-max"));
+        let $sections = $w.document.querySelectorAll(".section[data-duration]");
+        let $entries = $w.document.querySelectorAll("[data-entry]");
+
+        let update = () => {
+            let position = parseInt($filter.value) / parseInt($filter.max);
+            // Use an exponential scale so that short durations are selectable.
+            let threshold = position === 0 ? 0 : Math.pow(max + 1, position) - 1;
+
+            $value.textContent = formatDuration(threshold);
+
+            $sections.forEach(el => {
+                let duration = parseInt(el.getAttribute("data-duration"));
+                el.classList.toggle("filtered", duration < threshold);
+            });
+
+            $entries.forEach(el => {
+                let entryStart = parseInt(el.getAttribute("data-entry-start"));
+                let entryClose = parseInt(el.getAttribute("data-entry-close"));
+                el.classList.toggle("filtered", entryClose - entryStart < threshold);
+            });
+        };
+
+        $filter.addEventListener("input", update);
+        update();
+    };
+
+    let loadLockToggles = () => {
+        $w.document.querySelectorAll(".lock-title").forEach(($title) => {
+            let $lock = $title.parentElement;
+
+            $title.addEventListener("click", () => {
+                $lock.classList.toggle("collapsed");
+            });
+        });
+    };
+
     $w.addEventListener("load", load);
+    $w.addEventListener("load", loadDurationFilter);
+    $w.addEventListener("load", loadLockToggles);
 })(window);
@@ -1,17 +1,33 @@
-use std::backtrace::Backtrace;
-use std::cell::Cell;
-use std::ptr::NonNull;
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+#[cfg(feature = "tokio")]
+use std::future::Future;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{
+    AtomicBool, AtomicPtr, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering,
+};
 use std::sync::Once;
-use std::time::Instant;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 
-use crate::event::{Event, EventBacktrace, EventId, Events, Leave, LockId};
+use crate::config::CaptureConfig;
+use crate::event::{Event, EventBacktrace, EventId, Events, Leave, LockId, LockKind, SortKey};
+use crate::intern::{name, Name};
+use crate::session::{SessionId, MAX_SESSIONS};
+use crate::{DrainError, LockState, Outcome, Process, TimedCapture};
 
 /// Initial event capacity for each thread.
 const CAPACITY: usize = 8192;
 
+/// Bits used to configure which kinds of acquisitions are recorded.
+const RECORD_READS: u8 = 0b001;
+const RECORD_WRITES: u8 = 0b010;
+const RECORD_MUTEXES: u8 = 0b100;
+
 /// Configure whether capturing is enabled or not.
 ///
 /// This can be used to enable capture in detail for particular sections of
@@ -19,40 +35,631 @@ const CAPACITY: usize = 8192;
 ///
 /// Once called capturing will be started and the timestamp for the capture
 /// system will be reset.
+///
+/// Captures can be nested, so that independent code paths can each capture
+/// around regions which overlap. Every call must be paired with a call to
+/// [`drain`] or [`try_drain`]:
+///
+/// * Only the outermost call starts the capture, resetting the timestamp
+///   baseline and applying its configuration. Nested calls leave the ongoing
+///   capture as it is.
+/// * Draining a nested capture returns a [`snapshot`] of the events captured
+///   so far, and leaves the capture running.
+/// * Draining the outermost capture stops it and returns all of its events.
+///
+/// # Examples
+///
+/// ```
+/// unlock::capture();
+///
+/// // Some other code path captures as well.
+/// unlock::capture();
+/// let inner = unlock::drain();
+///
+/// // Capture is still ongoing here.
+/// let outer = unlock::drain();
+/// ```
 pub fn capture() {
-    get().capture();
+    get().capture(CaptureConfig::new(), None);
+}
+
+/// Start capturing events with the given configuration.
+///
+/// This behaves like [`capture`], but allows for customizing how events are
+/// captured through [`CaptureConfig`].
+pub fn capture_with(config: CaptureConfig) {
+    get().capture(config, None);
+}
+
+/// Pause or resume recording of events without affecting the capture.
+///
+/// Unlike [`capture`], this doesn't reset the timestamp baseline of the
+/// capture, so it can cheaply be toggled many times, such as to only record
+/// events while handling particular kinds of requests. Acquisitions which
+/// were recorded while enabled are still recorded as released if recording
+/// is disabled in the meantime. Starting a capture enables recording.
+///
+/// This has no effect unless a capture has been started.
+pub fn set_enabled(enabled: bool) {
+    get().set_enabled(enabled);
+}
+
+/// Test if lock acquisitions are currently being recorded.
+///
+/// This is the case while a capture or a capture session is ongoing, unless
+/// recording has been paused with [`set_enabled`]. It can be used to only
+/// perform expensive instrumentation when it will be recorded, or to avoid
+/// starting a capture when one is already ongoing.
+///
+/// # Examples
+///
+/// ```
+/// unlock::capture();
+///
+/// if unlock::is_capturing() {
+///     unlock::with_context("request", "expensive to compute", || {
+///         /* do some work */
+///     });
+/// }
+///
+/// let events = unlock::drain();
+/// ```
+pub fn is_capturing() -> bool {
+    get().is_recording()
+}
+
+/// Start a capture session which is independent of the capture started
+/// through [`capture`].
+///
+/// Each session has its own timestamp baseline, and only contains events
+/// recorded while it was active. This allows a library to capture events for
+/// its own purposes without interfering with a capture started by the
+/// application, or other sessions. Events are recorded while any session or
+/// capture is active, and are drained with [`drain_session`].
+///
+/// Sessions share the configuration set through [`capture_with`], such as
+/// whether creation sites are recorded, and are paused by [`set_enabled`]
+/// like any other capture. Starting a session doesn't resume recording which
+/// was paused while another capture is active. The counts of dropped events
+/// and thread index conflicts only cover the time the session was active.
+///
+/// Events are not tagged with the session which recorded them. A session
+/// contains every event recorded while it was active, so sessions which are
+/// active at the same time can't tell apart the events caused by each other.
+///
+/// # Panics
+///
+/// Panics if too many sessions are active at the same time, which is
+/// currently 63.
+///
+/// # Examples
+///
+/// ```
+/// let lock = unlock::Mutex::new(0);
+///
+/// let session = unlock::capture_session();
+/// *lock.lock() += 1;
+/// let events = unlock::drain_session(session);
+/// ```
+pub fn capture_session() -> SessionId {
+    get().capture_session()
+}
+
+/// Stop the given capture session and drain its events.
+///
+/// Events which are still needed by other active sessions, or the capture
+/// started through [`capture`], are retained for them. If the session has
+/// already been drained, this returns an empty collection.
+pub fn drain_session(id: SessionId) -> Events {
+    get().drain_session(id).unwrap_or_else(|_| Events::new())
+}
+
+/// Start capturing events, and stop recording once the given duration has
+/// elapsed.
+///
+/// Acquisitions made after `duration` has elapsed are not recorded, which is
+/// as cheap as when no capture is ongoing. Critical sections which were
+/// entered in time are still recorded as released. The events are kept until
+/// the capture is drained, either by calling [`drain`] at any later point or
+/// through the returned [`TimedCapture`], which waits for the duration to
+/// elapse.
+///
+/// Like with [`capture`], a nested call leaves the ongoing capture as it is,
+/// so the duration only applies to the outermost capture.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let lock = unlock::Mutex::new(0);
+/// let capture = unlock::capture_for(Duration::from_millis(10));
+/// *lock.lock() += 1;
+/// let events = capture.join();
+/// ```
+///
+/// Draining the capture later:
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let _ = unlock::capture_for(Duration::from_secs(2));
+/// /* serve requests */
+/// let events = unlock::drain();
+/// ```
+pub fn capture_for(duration: Duration) -> TimedCapture {
+    get().capture(CaptureConfig::new(), Some(duration));
+    TimedCapture::new(Instant::now().checked_add(duration))
+}
+
+/// Start capturing events until the returned guard is dropped.
+///
+/// This calls [`capture`] and returns a [`CaptureGuard`] which calls [`drain`]
+/// once it is dropped, so that the capture is stopped even if the scope is
+/// left early through a `return`, `?` or a panic. The drained events are
+/// passed to the callback set through [`CaptureGuard::on_drain`], or can be
+/// retrieved directly with [`CaptureGuard::into_events`].
+///
+/// Since captures can be nested, guards can be used independently of other
+/// captures which are in progress.
+///
+/// # Examples
+///
+/// ```
+/// let lock = unlock::Mutex::new(0);
+///
+/// let guard = unlock::capture_scope();
+/// *lock.lock() += 1;
+/// let events = guard.into_events();
+/// ```
+///
+/// Handing the events over once the scope is left:
+///
+/// ```
+/// use std::sync::mpsc;
+///
+/// let lock = unlock::Mutex::new(0);
+/// let (tx, rx) = mpsc::channel();
+///
+/// {
+///     let _guard = unlock::capture_scope().on_drain(move |events| {
+///         let _ = tx.send(events);
+///     });
+///
+///     *lock.lock() += 1;
+/// }
+///
+/// let events = rx.recv().unwrap();
+/// ```
+pub fn capture_scope() -> CaptureGuard {
+    get().capture(CaptureConfig::new(), None);
+    CaptureGuard { on_drain: None }
+}
+
+/// A capture started with [`capture_scope`], which is drained when the guard
+/// is dropped.
+#[must_use = "the capture is drained as soon as the guard is dropped"]
+pub struct CaptureGuard {
+    on_drain: Option<Box<dyn FnOnce(Events) + Send>>,
+}
+
+impl CaptureGuard {
+    /// Set a callback which is called with the drained events once the guard
+    /// is dropped.
+    ///
+    /// This replaces any previously set callback.
+    pub fn on_drain<F>(mut self, f: F) -> Self
+    where
+        F: 'static + Send + FnOnce(Events),
+    {
+        self.on_drain = Some(Box::new(f));
+        self
+    }
+
+    /// Drain the capture now and return its events.
+    ///
+    /// The callback set through [`on_drain`] is not called.
+    ///
+    /// [`on_drain`]: CaptureGuard::on_drain
+    pub fn into_events(mut self) -> Events {
+        self.on_drain = None;
+        mem::forget(self);
+        drain()
+    }
+}
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        let events = drain();
+
+        if let Some(on_drain) = self.on_drain.take() {
+            on_drain(events);
+        }
+    }
+}
+
+/// Attach the given key-value attribute to every lock acquisition recorded on
+/// the current thread while `f` is running.
+///
+/// Calls can be nested, in which case all attributes of the enclosing calls
+/// are attached.
+///
+/// # Examples
+///
+/// ```
+/// let lock = unlock::Mutex::new(0);
+///
+/// unlock::with_context("request_id", "abc", || {
+///     *lock.lock() += 1;
+/// });
+/// ```
+pub fn with_context<K, V, F, R>(key: K, value: V, f: F) -> R
+where
+    K: Into<Cow<'static, str>>,
+    V: Into<Cow<'static, str>>,
+    F: FnOnce() -> R,
+{
+    struct Restore;
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ATTRIBUTES.with(|attributes| {
+                attributes.borrow_mut().pop();
+            });
+        }
+    }
+
+    ATTRIBUTES.with(|attributes| {
+        attributes.borrow_mut().push((key.into(), value.into()));
+    });
+
+    let _restore = Restore;
+    f()
+}
+
+/// Set the label of the current process.
+///
+/// The label is included in the description of the process in drained
+/// [`Events`], which helps telling processes apart when events from several
+/// processes are combined with [`Events::merge`].
+pub fn set_process_label<L>(label: L)
+where
+    L: Into<Cow<'static, str>>,
+{
+    *get().process_label.lock() = Some(label.into());
+}
+
+/// Set the identifier of the task currently running on this thread.
+///
+/// Every event recorded on this thread until the task identifier is cleared
+/// with [`clear_task_id`] will be tagged with it. This is intended to be
+/// called from the task-enter hook of an async runtime, so that lock events can
+/// be attributed to tasks rather than threads.
+pub fn set_task_id(task_id: u64) {
+    TASK_ID.with(|current| current.set(Some(task_id)));
+}
+
+/// Clear the identifier of the task running on this thread, as set by
+/// [`set_task_id`].
+pub fn clear_task_id() {
+    TASK_ID.with(|current| current.set(None));
+}
+
+/// Override how the index of the current thread is determined.
+///
+/// By default threads are assigned sequential indexes the first time they
+/// record an event. In runtimes which have their own scheme for identifying
+/// workers, `source` can be set to return the identifier of the current
+/// worker so that the thread axis of the trace lines up with other tooling.
+///
+/// The returned value can be arbitrary, but it is used as-is to identify the
+/// thread in the trace, so threads which return the same value will be merged
+/// into the same timeline. Values should therefore be unique among threads
+/// which are running at the same time. If a thread returns an index which is
+/// still in use by another thread, this is counted and reported through
+/// [`Events::thread_index_conflicts`]. An index stops being in use once its
+/// thread returns another index or exits.
+///
+/// Note that indexes handed out by the built-in source are unique among
+/// themselves, but might coincide with indexes returned by `source`, so the
+/// source should be set before any thread records an event.
+pub fn set_thread_index_source(source: fn() -> usize) {
+    THREAD_INDEX_SOURCE.store(source as *mut (), Ordering::Release);
+}
+
+/// Restore the built-in thread index source, undoing
+/// [`set_thread_index_source`].
+pub fn clear_thread_index_source() {
+    THREAD_INDEX_SOURCE.store(ptr::null_mut(), Ordering::Release);
+}
+
+/// Release the memory used to buffer events.
+///
+/// The buffers used to record events keep the capacity they've grown to when
+/// drained, so that later captures don't have to grow them again. Calling
+/// this after the last capture in a program, such as after one large capture,
+/// frees up that memory. Buffers which still hold events of an ongoing
+/// capture are shrunk to fit them.
+pub fn release_buffers() {
+    get().release_buffers();
+}
+
+/// Report the locks which are currently held or being waited for, based on
+/// the events captured so far.
+///
+/// Waiting threads report which thread currently holds the lock they are
+/// waiting for, which makes this useful for diagnosing hangs. Only
+/// acquisitions which have been recorded by the current capture are
+/// reported, so nothing is reported unless events are being captured.
+pub fn held_locks() -> Vec<LockState> {
+    get().held_locks()
+}
+
+/// Copy the events captured so far, without stopping the capture.
+///
+/// If events are not being captured, this returns an empty collection.
+///
+/// # Examples
+///
+/// ```
+/// unlock::capture();
+/// let lock = unlock::Mutex::new(0);
+/// *lock.lock() += 1;
+///
+/// let so_far = unlock::snapshot();
+/// let events = unlock::drain();
+/// ```
+pub fn snapshot() -> Events {
+    get()
+        .snapshot(SortKey::Id)
+        .unwrap_or_else(|_| Events::new())
 }
 
 /// Disable capture and drain the current collection of events.
+///
+/// If [`capture`] has been called more times than the capture has been
+/// drained, this only returns a [`snapshot`] and the capture is stopped once
+/// the outermost capture is drained.
+///
+/// If events are not being captured, this returns an empty collection. Use
+/// [`try_drain`] to distinguish this from a capture which didn't record
+/// anything.
 pub fn drain() -> Events {
-    get().drain()
+    get()
+        .drain(SortKey::Id, true)
+        .unwrap_or_else(|_| Events::new())
+}
+
+/// Disable capture and drain the current collection of events, failing if
+/// events are not being captured or if draining them would block.
+///
+/// Unlike [`drain`], this never waits for a thread which is currently
+/// recording an event, so it can be polled from a thread which must not
+/// stall, such as a timer thread exporting traces.
+///
+/// # Errors
+///
+/// Returns [`DrainError::NotCapturing`] if [`capture`] was never called or
+/// the capture has already been drained.
+///
+/// Returns [`DrainError::WouldBlock`] if another thread is currently holding
+/// any of the buffers events are stored in. The capture is then left as it
+/// was, so draining can be tried again later.
+///
+/// # Examples
+///
+/// ```
+/// unlock::capture();
+/// let events = unlock::try_drain();
+/// # #[cfg(feature = "trace")]
+/// assert!(events.is_ok());
+/// # #[cfg(feature = "trace")]
+/// assert!(unlock::try_drain().is_err());
+/// ```
+pub fn try_drain() -> Result<Events, DrainError> {
+    get().drain(SortKey::Id, false)
+}
+
+/// Disable capture and drain the current collection of events, sorted by the
+/// given key.
+///
+/// This is the same as [`drain`], except that it avoids having to sort the
+/// events again when another order is needed.
+pub fn drain_sorted(key: SortKey) -> Events {
+    get().drain(key, true).unwrap_or_else(|_| Events::new())
 }
 
-static mut TRACING_CONTEXT: NonNull<TracingContext> = NonNull::dangling();
+static TRACING_CONTEXT: AtomicPtr<TracingContext> = AtomicPtr::new(ptr::null_mut());
 static INIT_TRACING_CONTEXT: Once = Once::new();
 
-/// Rotating statically known index of the current thread.
+/// The index assigned to the next thread by the built-in source.
+///
+/// Indexes are never reused, so distinct threads always have distinct
+/// indexes. Threads whose indexes map to the same storage shard share its
+/// buffers, but every event still records the index of its own thread.
 static THREAD_INDEX: AtomicUsize = AtomicUsize::new(0);
 
+/// User-provided source of thread indexes, or null if the built-in one is
+/// used.
+static THREAD_INDEX_SOURCE: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Incremented whenever the state kept about threads is forgotten, so that
+/// threads know to provide it again.
+static THREAD_GENERATION: AtomicU64 = AtomicU64::new(1);
+
 thread_local! {
     static THREAD_INDEX_THREAD: Cell<Option<usize>> = const { Cell::new(None) };
+    /// The shallowest stack address at which this thread recorded an event.
+    static STACK_BASE: Cell<usize> = const { Cell::new(0) };
+    /// The thread generation in which the name of this thread was stored.
+    static THREAD_NAMED: Cell<u64> = const { Cell::new(0) };
+    /// The last index returned for this thread by a custom source.
+    static THREAD_INDEX_CLAIMED: RefCell<Claim> = const { RefCell::new(Claim { index: None, owner: 0, generation: 0 }) };
+    /// The state of the generator deciding which lock operations are sampled.
+    static SAMPLER: Cell<u32> = const { Cell::new(0) };
+    /// The task identifier set through `set_task_id`.
+    static TASK_ID: Cell<Option<u64>> = const { Cell::new(None) };
+    /// Attributes attached through `with_context`.
+    static ATTRIBUTES: RefCell<Vec<(Cow<'static, str>, Cow<'static, str>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An index returned for the current thread by a custom source, which is
+/// released when the thread exits.
+struct Claim {
+    index: Option<usize>,
+    // The built-in index of the thread.
+    owner: usize,
+    // The thread generation in which the index was claimed.
+    generation: u64,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if let Some(index) = self.index {
+            get().release_thread_index(index, self.owner);
+        }
+    }
 }
 
 /// Access the global tracing context.
-#[allow(static_mut_refs)]
 pub(super) fn get() -> &'static TracingContext {
-    unsafe {
-        INIT_TRACING_CONTEXT.call_once(|| {
-            TRACING_CONTEXT =
-                NonNull::from(Box::leak(Box::new(TracingContext::new(num_cpus::get()))));
-        });
-        TRACING_CONTEXT.as_ref()
-    }
+    INIT_TRACING_CONTEXT.call_once(|| {
+        TRACING_CONTEXT.store(new_context(), Ordering::Release);
+    });
+
+    // SAFETY: The context is initialized above, and contexts are leaked so
+    // they're never deallocated.
+    unsafe { &*TRACING_CONTEXT.load(Ordering::Acquire) }
+}
+
+/// Allocate a new context which lives for the rest of the program.
+fn new_context() -> *mut TracingContext {
+    Box::leak(Box::new(TracingContext::new(num_cpus::get())))
+}
+
+/// Replace the global tracing context with a fresh one.
+///
+/// This discards all buffered events and configuration, and stops any
+/// ongoing capture, so that tests which call [`capture`] and [`drain`] don't
+/// observe events recorded by earlier tests. Instrumented locks shouldn't be
+/// held while the context is reset.
+///
+/// This is only intended for tests and is only available with the
+/// `test-util` feature. Since the context is global, tests using it must
+/// still not run concurrently with each other. The old context is leaked, and
+/// thread indexes, task identifiers and lock indexes are not reset.
+///
+/// ```
+/// unlock::capture();
+/// unlock::reset_for_test();
+/// assert!(unlock::try_drain().is_err());
+/// ```
+#[cfg(feature = "test-util")]
+pub fn reset_for_test() {
+    get();
+    TRACING_CONTEXT.store(new_context(), Ordering::Release);
+    THREAD_GENERATION.fetch_add(1, Ordering::Relaxed);
 }
 
 struct ThreadStorage {
-    enters: Vec<Event>,
-    leaves: Vec<Leave>,
+    enters: VecDeque<Event>,
+    leaves: VecDeque<Leave>,
+    // The number of enter events which were dropped because the buffer was
+    // full, since the storage was created.
+    dropped: u64,
+    // Whether the oldest events are overwritten once the buffer is full,
+    // rather than dropping new ones.
+    overwrite: bool,
+}
+
+impl ThreadStorage {
+    /// Test if `count` enter events can be stored without exceeding `max`,
+    /// counting them as dropped if they can't.
+    ///
+    /// If the oldest events are overwritten, room is made for the new events
+    /// by dropping the oldest ones instead.
+    fn reserve(&mut self, count: usize, max: usize) -> bool {
+        let len = self.enters.len().saturating_add(count);
+
+        if len <= max {
+            return true;
+        }
+
+        if !self.overwrite || count > max {
+            self.dropped += count as u64;
+            return false;
+        }
+
+        let evicted = len - max;
+        self.enters.drain(..evicted);
+        self.dropped += evicted as u64;
+
+        // Since parents are recorded before their children, any children at
+        // the front have lost their parent.
+        while self
+            .enters
+            .front()
+            .map_or(false, |enter| enter.parent.is_some())
+        {
+            self.enters.pop_front();
+            self.dropped += 1;
+        }
+
+        // Leave events are mostly recorded in the same order as the enter
+        // events they belong to, so the ones of evicted sections are at the
+        // front. Any which are left behind are ignored when drained.
+        if let Some(oldest) = self.enters.front().map(|enter| enter.id) {
+            while self
+                .leaves
+                .front()
+                .map_or(false, |leave| leave.sibling < oldest)
+            {
+                self.leaves.pop_front();
+            }
+        }
+
+        true
+    }
+
+    /// Push an enter event recorded at the given timestamp.
+    fn push_enter(&mut self, mut event: Event, thread_index: usize, timestamp: u64) {
+        event.thread_index = thread_index;
+        event.timestamp = timestamp;
+        self.enters.push_back(event);
+    }
+}
+
+/// The state of a capture session.
+#[derive(Default, Clone, Copy)]
+struct Session {
+    // The timestamp baseline of the session.
+    adjust: u64,
+    // The wall-clock time at which the session was started, in nanoseconds
+    // since the unix epoch.
+    started_at: u64,
+    // The counters when the session was started.
+    counters: Counters,
+    // The number of sessions which have been started with this index.
+    generation: u64,
+}
+
+/// Counters which only ever grow, so that each capture reports how much they
+/// grew while it was active.
+#[derive(Default, Clone, Copy)]
+struct Counters {
+    dropped: u64,
+    thread_index_conflicts: u64,
+}
+
+/// An acquisition which is being recorded.
+struct Pending {
+    id: EventId,
+    acquiring_id: EventId,
+    // The critical and acquiring events, until they've been recorded.
+    events: Option<(Event, Event)>,
+    threshold: u64,
+    start: u64,
+    recorded: bool,
 }
 
 /// A context capturing tracing events.
@@ -64,6 +671,68 @@ pub(super) struct TracingContext {
     // Once capturing is started, this will be set to the instant it was started
     // so that timestamps can be adjusted relative to it.
     adjust: AtomicU64,
+    // Whether recording is enabled, which is toggled separately from when
+    // capturing was started.
+    enabled: AtomicBool,
+    // Bits indicating which captures are active, where the lowest bit is the
+    // capture started through `capture` and the others are sessions.
+    active: AtomicU64,
+    // The state of each capture session, indexed by session identifier,
+    // where the counters of the capture started through `capture` are stored
+    // at index zero. The lock is also held while starting and stopping
+    // captures.
+    sessions: Mutex<[Session; MAX_SESSIONS + 1]>,
+    // The number of times capture has been started without being drained.
+    depth: AtomicUsize,
+    // If set to something other than `u64::MAX`, the timestamp after which
+    // the capture started through `capture` no longer records events.
+    deadline: AtomicU64,
+    // Set once the deadline has passed, so that the clock doesn't need to be
+    // read to know that.
+    expired: AtomicBool,
+    // If set to something other than `u64::MAX`, only acquisitions which
+    // waited at least this many nanoseconds will be recorded.
+    contended_only: AtomicU64,
+    // The maximum number of enter events stored in each thread storage.
+    max_events: AtomicUsize,
+    // Only one in this many lock operations are recorded.
+    sample_one_in: AtomicU32,
+    // The resolution of recorded timestamps in nanoseconds.
+    resolution: AtomicU64,
+    // Bits indicating which kinds of acquisitions are recorded.
+    recorded_kinds: AtomicU8,
+    // Whether failed attempts to acquire a lock without blocking are recorded.
+    record_failed_tries: AtomicBool,
+    // Whether backtraces are captured for acquisitions.
+    record_backtraces: AtomicBool,
+    // Whether backtraces are only captured for contended acquisitions.
+    contended_backtraces: AtomicBool,
+    // The maximum length of captured backtraces.
+    max_backtrace_len: AtomicUsize,
+    // Whether the stack depth of acquisitions is recorded.
+    record_stack_depth: AtomicBool,
+    // Whether backtraces should be captured when locks are constructed.
+    capture_creation_sites: AtomicBool,
+    // Backtraces captured when locks were constructed.
+    creation_sites: Mutex<HashMap<LockId, EventBacktrace>>,
+    // Keys assigned to locks when they were constructed.
+    keys: Mutex<HashMap<LockId, Name>>,
+    // The identifier of the current process.
+    process: u32,
+    // The label of the current process.
+    process_label: Mutex<Option<Cow<'static, str>>>,
+    // The wall-clock time at which capturing was started, in nanoseconds
+    // since the unix epoch.
+    started_at: AtomicU64,
+    // Indexes currently claimed through a custom thread index source, mapped
+    // to the built-in index of the thread which claimed them.
+    thread_owners: Mutex<HashMap<usize, usize>>,
+    // Names of threads by thread index, captured the first time a thread
+    // records an event while capturing. Forgotten once no capture is active.
+    thread_names: Mutex<HashMap<usize, String>>,
+    // The number of times a thread returned an index already returned by
+    // another thread.
+    thread_index_conflicts: AtomicU64,
 }
 
 impl TracingContext {
@@ -73,8 +742,10 @@ impl TracingContext {
 
         for _ in 0..threads.max(1) {
             storage.push(Mutex::new(ThreadStorage {
-                enters: Vec::with_capacity(CAPACITY),
-                leaves: Vec::with_capacity(CAPACITY),
+                enters: VecDeque::with_capacity(CAPACITY),
+                leaves: VecDeque::with_capacity(CAPACITY),
+                dropped: 0,
+                overwrite: false,
             }));
         }
 
@@ -82,166 +753,1429 @@ impl TracingContext {
             storage,
             start: Instant::now(),
             adjust: AtomicU64::new(u64::MAX),
+            enabled: AtomicBool::new(true),
+            active: AtomicU64::new(0),
+            sessions: Mutex::new([Session::default(); MAX_SESSIONS + 1]),
+            depth: AtomicUsize::new(0),
+            deadline: AtomicU64::new(u64::MAX),
+            expired: AtomicBool::new(false),
+            contended_only: AtomicU64::new(u64::MAX),
+            max_events: AtomicUsize::new(usize::MAX),
+            sample_one_in: AtomicU32::new(1),
+            resolution: AtomicU64::new(1),
+            recorded_kinds: AtomicU8::new(RECORD_READS | RECORD_WRITES | RECORD_MUTEXES),
+            record_failed_tries: AtomicBool::new(false),
+            record_backtraces: AtomicBool::new(true),
+            contended_backtraces: AtomicBool::new(false),
+            max_backtrace_len: AtomicUsize::new(usize::MAX),
+            record_stack_depth: AtomicBool::new(false),
+            capture_creation_sites: AtomicBool::new(false),
+            creation_sites: Mutex::new(HashMap::new()),
+            keys: Mutex::new(HashMap::new()),
+            process: std::process::id(),
+            process_label: Mutex::new(None),
+            started_at: AtomicU64::new(0),
+            thread_owners: Mutex::new(HashMap::new()),
+            thread_names: Mutex::new(HashMap::new()),
+            thread_index_conflicts: AtomicU64::new(0),
         }
     }
 
-    /// Set whether capture is enabled.
-    pub(super) fn capture(&self) {
-        self.adjust.store(
-            Instant::now().duration_since(self.start).as_nanos() as u64,
-            Ordering::Release,
-        );
-    }
+    /// Set whether capture is enabled, optionally only recording for the
+    /// given `duration`.
+    pub(super) fn capture(&self, config: CaptureConfig, duration: Option<Duration>) {
+        let mut sessions = self.sessions.lock();
 
-    /// Enter the given span.
-    pub(super) fn enter(
-        &self,
-        lock: LockId,
-        name: &'static str,
-        type_name: &'static str,
-        parent: Option<EventId>,
-    ) -> Option<EventId> {
-        if self.adjust.load(Ordering::Acquire) == u64::MAX {
-            return None;
+        // Only the outermost capture is started.
+        if self.depth.fetch_add(1, Ordering::Relaxed) > 0 {
+            return;
         }
 
-        let id = EventId::next();
-        let name = name.into();
-        let type_name = type_name.into();
-        let backtrace = EventBacktrace::from_capture(Backtrace::capture());
-
-        self.record(|storage, thread_index, timestamp| {
-            storage.enters.push(Event {
-                id,
-                timestamp,
-                thread_index,
-                parent,
-                name,
-                type_name,
-                lock,
-                backtrace,
-            })
-        });
+        let contended_only = config
+            .contended_only
+            .map_or(u64::MAX, |threshold| threshold.as_nanos() as u64);
 
-        Some(id)
-    }
+        self.contended_only.store(contended_only, Ordering::Relaxed);
+        self.max_events
+            .store(config.max_events.unwrap_or(usize::MAX), Ordering::Relaxed);
 
-    /// Leave the given span.
-    pub(super) fn leave(&self, sibling: Option<EventId>) {
-        if let Some(sibling) = sibling {
-            self.record(|storage, thread_index, timestamp| {
-                storage.leaves.push(Leave {
-                    sibling,
-                    thread_index,
-                    timestamp,
-                })
-            });
+        for storage in &self.storage {
+            storage.lock().overwrite = config.ring_buffer;
         }
-    }
 
-    /// Record events around the given closure.
-    pub(super) fn with<F, T>(
-        &self,
-        lock: LockId,
-        name: &'static str,
-        type_name: &'static str,
-        parent: Option<EventId>,
-        f: F,
-    ) -> T
-    where
-        F: FnOnce() -> T,
-    {
-        if self.adjust.load(Ordering::Acquire) == u64::MAX {
-            return f();
+        sessions[0].counters = self.counters();
+
+        self.sample_one_in
+            .store(config.sample_one_in, Ordering::Relaxed);
+
+        self.capture_creation_sites
+            .store(config.creation_sites, Ordering::Relaxed);
+        let resolution = config.resolution.as_nanos().clamp(1, u64::MAX as u128) as u64;
+        self.resolution.store(resolution, Ordering::Relaxed);
+
+        let mut recorded_kinds = 0;
+
+        for (enabled, bit) in [
+            (config.record_reads, RECORD_READS),
+            (config.record_writes, RECORD_WRITES),
+            (config.record_mutexes, RECORD_MUTEXES),
+        ] {
+            if enabled {
+                recorded_kinds |= bit;
+            }
         }
 
-        let id = EventId::next();
-        let name = name.into();
-        let type_name = type_name.into();
-        let backtrace = EventBacktrace::from_capture(Backtrace::capture());
+        self.recorded_kinds.store(recorded_kinds, Ordering::Relaxed);
+        self.record_failed_tries
+            .store(config.record_failed_tries, Ordering::Relaxed);
+        self.record_backtraces
+            .store(config.backtraces, Ordering::Relaxed);
+        self.contended_backtraces
+            .store(config.contended_backtraces, Ordering::Relaxed);
+        self.max_backtrace_len.store(
+            config.max_backtrace_len.unwrap_or(usize::MAX),
+            Ordering::Relaxed,
+        );
+        self.record_stack_depth
+            .store(config.stack_depth, Ordering::Relaxed);
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
 
-        self.record(|storage, thread_index, timestamp| {
-            storage.enters.push(Event {
-                id,
-                timestamp,
-                thread_index,
-                parent,
-                name,
-                type_name,
-                lock,
-                backtrace,
-            })
-        });
+        self.started_at.store(started_at, Ordering::Relaxed);
+        self.enabled.store(true, Ordering::Relaxed);
 
-        let result = f();
+        let now = self.now();
 
-        self.record(|storage, thread_index, timestamp| {
-            storage.leaves.push(Leave {
-                thread_index,
-                sibling: id,
-                timestamp,
-            })
+        let deadline = duration.map_or(u64::MAX, |duration| {
+            now.saturating_add(u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX))
         });
 
-        result
+        self.deadline.store(deadline, Ordering::Relaxed);
+        self.expired.store(false, Ordering::Relaxed);
+        self.adjust
+            .store(self.to_resolution(now), Ordering::Release);
+        self.active.fetch_or(1, Ordering::Release);
     }
 
-    /// Record an event.
-    fn record<F>(&self, f: F)
-    where
-        F: FnOnce(&mut ThreadStorage, usize, u64),
-    {
-        let thread_index = thread_index();
-        // NB: This is at risk of being truncated, but that still gives us ~584
-        // years worth of tracing.
-        let duration = Instant::now().duration_since(self.start).as_nanos() as u64;
-        f(
-            &mut self.storage[thread_index % self.storage.len()].lock(),
-            thread_index,
-            duration,
-        );
+    /// Start a new capture session.
+    pub(super) fn capture_session(&self) -> SessionId {
+        let mut sessions = self.sessions.lock();
+
+        let free = !self.active.load(Ordering::Acquire) & !1;
+
+        if free == 0 {
+            panic!("unlock: Too many capture sessions");
+        }
+
+        let index = free.trailing_zeros() as usize;
+        let generation = sessions[index].generation.wrapping_add(1);
+
+        sessions[index] = Session {
+            adjust: self.to_resolution(self.now()),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64),
+            counters: self.counters(),
+            generation,
+        };
+
+        // Recording which was paused through `set_enabled` while another
+        // capture is active stays paused.
+        if self.active.load(Ordering::Acquire) == 0 {
+            self.enabled.store(true, Ordering::Relaxed);
+        }
+
+        self.active.fetch_or(1 << index, Ordering::Release);
+        SessionId::new(index, generation)
     }
 
-    /// Drain events.
+    /// Test if acquisitions of the given lock by the operation `name` should be
+    /// recorded.
+    #[inline]
+    fn records(&self, lock: LockId, name: Name) -> bool {
+        let bit = match lock.kind() {
+            LockKind::Mutex | LockKind::AsyncMutex | LockKind::Condvar => RECORD_MUTEXES,
+            LockKind::RwLock | LockKind::AsyncRwLock
+                if name == name!("write") || name == name!("try_write") =>
+            {
+                RECORD_WRITES
+            }
+            LockKind::RwLock | LockKind::AsyncRwLock => RECORD_READS,
+        };
+
+        self.recorded_kinds.load(Ordering::Relaxed) & bit != 0
+    }
+
+    /// Test if a lock operation of the given lock by the operation `name`
+    /// should be recorded, deciding whether it's part of the sample.
     ///
-    /// If capture is enabled while draining, the exact events recorded are
-    /// not specified.
-    pub(super) fn drain(&self) -> Events {
-        let adjust = self.adjust.swap(u64::MAX, Ordering::AcqRel);
+    /// This is decided once per operation, so that nothing is recorded for
+    /// operations which aren't sampled, including when they're released.
+    #[inline]
+    fn records_operation(&self, lock: LockId, name: Name) -> bool {
+        self.is_recording() && self.records(lock, name) && self.sampled()
+    }
 
-        if adjust == u64::MAX {
-            return Events::new();
+    /// Randomly decide whether the current lock operation is sampled.
+    #[inline]
+    fn sampled(&self) -> bool {
+        let one_in = self.sample_one_in.load(Ordering::Relaxed);
+
+        if one_in <= 1 {
+            return true;
         }
 
-        let mut events = Events::new();
+        SAMPLER.with(|state| {
+            // Seed the generator of each thread differently, and never with
+            // zero which it would get stuck at.
+            let mut x = match state.get() {
+                0 => (self.now() as u32) | 1,
+                x => x,
+            };
 
-        for storage in self.storage.iter() {
-            let mut storage = storage.lock();
+            // xorshift32
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            state.set(x);
+            x % one_in == 0
+        })
+    }
 
-            for mut enter in storage.enters.drain(..) {
-                enter.timestamp -= adjust;
-                events.enters.push(enter);
-            }
+    /// Set whether recording is enabled.
+    pub(super) fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
 
-            for mut leave in storage.leaves.drain(..) {
-                leave.timestamp -= adjust;
-                events.leaves.push(leave);
-            }
+    /// Test if new events should be recorded.
+    #[inline]
+    fn is_recording(&self) -> bool {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return false;
         }
 
-        events.enters.sort_by_key(|event| event.id);
-        events.leaves.sort_by_key(|event| event.sibling);
-        events
+        let active = self.active.load(Ordering::Acquire);
+
+        // Past its deadline, only sessions are recorded.
+        if active & 1 != 0 && self.is_expired() {
+            return active & !1 != 0;
+        }
+
+        active != 0
     }
-}
 
-fn thread_index() -> usize {
-    THREAD_INDEX_THREAD.with(|index| {
-        if let Some(index) = index.get() {
-            return index;
+    /// Test if the deadline of the capture started through `capture` has
+    /// passed.
+    #[inline]
+    fn is_expired(&self) -> bool {
+        if self.expired.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        let deadline = self.deadline.load(Ordering::Relaxed);
+
+        if deadline == u64::MAX || self.now() < deadline {
+            return false;
         }
 
-        let new_index = THREAD_INDEX.fetch_add(1, Ordering::Relaxed);
+        self.expired.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Capture a backtrace for the given event if enabled.
+    #[inline]
+    fn capture_backtrace(&self, event: &mut Event) {
+        if self.record_backtraces.load(Ordering::Relaxed) {
+            event.capture_backtrace(self.max_backtrace_len.load(Ordering::Relaxed));
+        }
+    }
+
+    /// Record the stack depth of the given event if enabled.
+    #[inline]
+    fn capture_stack_depth(&self, event: &mut Event) {
+        if self.record_stack_depth.load(Ordering::Relaxed) {
+            event.stack_depth = Some(stack_depth());
+        }
+    }
+
+    /// Register that the given lock has been constructed, capturing a
+    /// backtrace of where if enabled.
+    pub(super) fn created(&self, lock: LockId) {
+        if !self.capture_creation_sites.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let max_len = self.max_backtrace_len.load(Ordering::Relaxed);
+
+        if let Some(backtrace) = EventBacktrace::capture(max_len) {
+            self.creation_sites.lock().insert(lock, backtrace);
+        }
+    }
+
+    /// Register the key of the given lock.
+    pub(super) fn keyed(&self, lock: LockId, key: &'static str) {
+        self.keys.lock().insert(lock, Name::new(key));
+    }
+
+    /// Acquire a lock using the given closures, recording the acquisition.
+    ///
+    /// The `try_acquire` closure is first used to attempt to acquire the lock
+    /// without blocking, which tells whether the acquisition was contended.
+    /// The `waiters` counter keeps track of the number of threads currently
+    /// waiting for the lock.
+    ///
+    /// This returns the acquired value, and the event which must be left once
+    /// the lock is released.
+    pub(super) fn acquire<T>(
+        &self,
+        lock: LockId,
+        name: Name,
+        type_name: Name,
+        waiters: &AtomicU32,
+        try_acquire: impl FnOnce() -> Option<T>,
+        acquire: impl FnOnce() -> T,
+    ) -> (T, Option<EventId>) {
+        if !self.records_operation(lock, name) {
+            return (acquire(), None);
+        }
+
+        if let Some(acquired) = self.try_first(lock, name, type_name, waiters, try_acquire) {
+            return acquired;
+        }
+
+        let pending = self.begin(lock, name, type_name, waiters);
+        let value = acquire();
+        (value, self.finish(pending, waiters))
+    }
+
+    /// Acquire a lock asynchronously, recording the acquisition.
+    ///
+    /// This works like [`TracingContext::acquire`], except that the wait
+    /// spans the `.await` of the `acquire` future.
+    #[cfg(feature = "tokio")]
+    pub(super) async fn acquire_async<T, F>(
+        &self,
+        lock: LockId,
+        name: Name,
+        type_name: Name,
+        waiters: &AtomicU32,
+        try_acquire: impl FnOnce() -> Option<T>,
+        acquire: F,
+    ) -> (T, Option<EventId>)
+    where
+        F: Future<Output = T>,
+    {
+        if !self.records_operation(lock, name) {
+            return (acquire.await, None);
+        }
+
+        if let Some(acquired) = self.try_first(lock, name, type_name, waiters, try_acquire) {
+            return acquired;
+        }
+
+        // Stop waiting if the future is dropped before the lock is acquired.
+        struct Cancel<'a> {
+            cx: &'a TracingContext,
+            waiters: &'a AtomicU32,
+            pending: Option<Pending>,
+        }
+
+        impl Drop for Cancel<'_> {
+            fn drop(&mut self) {
+                if let Some(pending) = self.pending.take() {
+                    self.cx.cancel(pending, self.waiters);
+                }
+            }
+        }
+
+        let mut cancel = Cancel {
+            cx: self,
+            waiters,
+            pending: Some(self.begin(lock, name, type_name, waiters)),
+        };
+
+        let value = acquire.await;
+
+        let Some(pending) = cancel.pending.take() else {
+            unreachable!();
+        };
+
+        (value, self.finish(pending, waiters))
+    }
+
+    /// Attempt to acquire a lock without blocking before waiting for it.
+    ///
+    /// Returns the acquired value and the event which must be left once the
+    /// lock is released if the lock was acquired, in which case the
+    /// acquisition was uncontended.
+    fn try_first<T>(
+        &self,
+        lock: LockId,
+        name: Name,
+        type_name: Name,
+        waiters: &AtomicU32,
+        try_acquire: impl FnOnce() -> Option<T>,
+    ) -> Option<(T, Option<EventId>)> {
+        let start = self.now();
+        let value = try_acquire()?;
+
+        if self.contended_only.load(Ordering::Relaxed) != u64::MAX {
+            return Some((value, None));
+        }
+
+        let event = self.record_without_waiting(
+            start,
+            lock,
+            name,
+            type_name,
+            waiters,
+            Outcome::Acquired,
+            false,
+        );
+        Some((value, event))
+    }
+
+    /// Start waiting for a lock which couldn't be acquired without blocking.
+    ///
+    /// Unless only contended acquisitions are recorded, the acquisition is
+    /// recorded right away. Otherwise it's recorded once it's known whether
+    /// it waited for long enough in [`TracingContext::finish`].
+    fn begin(&self, lock: LockId, name: Name, type_name: Name, waiters: &AtomicU32) -> Pending {
+        let threshold = self.contended_only.load(Ordering::Relaxed);
+        let max_events = self.max_events.load(Ordering::Relaxed);
+
+        let id = EventId::next();
+        let acquiring_id = EventId::next();
+        let mut critical = Event::new(id, lock, name!("critical"), type_name, None);
+        let mut acquiring = Event::new(acquiring_id, lock, name, type_name, Some(id));
+
+        let waiting = waiters.fetch_add(1, Ordering::Relaxed);
+        let task_id = TASK_ID.with(Cell::get);
+
+        for event in [&mut critical, &mut acquiring] {
+            event.waiters = waiting;
+            event.contended = true;
+            event.task_id = task_id;
+            event.process = self.process;
+        }
+
+        critical.attributes = ATTRIBUTES.with(|attributes| attributes.borrow().clone());
+
+        let start = self.now();
+
+        let mut pending = Pending {
+            id,
+            acquiring_id,
+            events: None,
+            threshold,
+            start,
+            recorded: false,
+        };
+
+        if threshold != u64::MAX {
+            pending.events = Some((critical, acquiring));
+            return pending;
+        }
+
+        self.capture_backtrace(&mut critical);
+        self.capture_stack_depth(&mut critical);
+
+        pending.recorded = self.record_at(start, |storage, thread_index, timestamp| {
+            if !storage.reserve(2, max_events) {
+                return false;
+            }
+
+            storage.push_enter(critical, thread_index, timestamp);
+            storage.push_enter(acquiring, thread_index, timestamp);
+            true
+        });
+
+        pending
+    }
+
+    /// Stop waiting for a lock which will never be acquired, closing any
+    /// events which were recorded for it.
+    #[cfg(feature = "tokio")]
+    fn cancel(&self, pending: Pending, waiters: &AtomicU32) {
+        waiters.fetch_sub(1, Ordering::Relaxed);
+
+        if pending.events.is_none() && pending.recorded {
+            self.leave(Some(pending.acquiring_id));
+            self.leave(Some(pending.id));
+        }
+    }
+
+    /// Finish waiting for a lock once it has been acquired, returning the
+    /// event which must be left once the lock is released.
+    fn finish(&self, pending: Pending, waiters: &AtomicU32) -> Option<EventId> {
+        waiters.fetch_sub(1, Ordering::Relaxed);
+
+        let Pending {
+            id,
+            acquiring_id,
+            events,
+            threshold,
+            start,
+            recorded,
+        } = pending;
+
+        let Some((mut critical, acquiring)) = events else {
+            if !recorded {
+                return None;
+            }
+
+            self.leave(Some(acquiring_id));
+            return Some(id);
+        };
+
+        let end = self.now();
+
+        if end.saturating_sub(start) < threshold {
+            return None;
+        }
+
+        let max_events = self.max_events.load(Ordering::Relaxed);
+
+        // Record the acquisition after the fact since we only now know that
+        // it was contended.
+        self.capture_backtrace(&mut critical);
+        self.capture_stack_depth(&mut critical);
+
+        let recorded = self.record_at(start, |storage, thread_index, timestamp| {
+            if !storage.reserve(2, max_events) {
+                return false;
+            }
+
+            storage.push_enter(critical, thread_index, timestamp);
+            storage.push_enter(acquiring, thread_index, timestamp);
+            storage.leaves.push_back(Leave {
+                sibling: acquiring_id,
+                thread_index,
+                timestamp: self.to_resolution(end),
+                leaked: false,
+            });
+            true
+        });
+
+        recorded.then_some(id)
+    }
+
+    /// Attempt to acquire a lock with a timeout using the given closure,
+    /// recording the attempt and its outcome.
+    ///
+    /// The closure is first called with a zero timeout, which tells whether
+    /// the acquisition was contended. Since the outcome is only known once the
+    /// attempt has completed, the attempt is recorded after the fact.
+    ///
+    /// This returns the acquired value if successful, and the event which
+    /// must be left once the lock is released.
+    pub(super) fn acquire_timed<T>(
+        &self,
+        lock: LockId,
+        name: Name,
+        type_name: Name,
+        waiters: &AtomicU32,
+        timeout: Duration,
+        acquire: impl Fn(Duration) -> Option<T>,
+    ) -> (Option<T>, Option<EventId>) {
+        if !self.records_operation(lock, name) {
+            return (acquire(timeout), None);
+        }
+
+        let threshold = self.contended_only.load(Ordering::Relaxed);
+        let max_events = self.max_events.load(Ordering::Relaxed);
+
+        let id = EventId::next();
+        let mut critical = Event::new(id, lock, name!("critical"), type_name, None);
+        let mut acquiring = Event::new(EventId::next(), lock, name, type_name, Some(id));
+        let acquiring_id = acquiring.id;
+
+        let waiting = waiters.fetch_add(1, Ordering::Relaxed);
+        let start = self.now();
+
+        let (value, contended) = match acquire(Duration::ZERO) {
+            Some(value) => (Some(value), false),
+            None => (acquire(timeout), true),
+        };
+
+        waiters.fetch_sub(1, Ordering::Relaxed);
+        let end = self.now();
+
+        let outcome = match value {
+            Some(..) => Outcome::Acquired,
+            None => Outcome::TimedOut,
+        };
+
+        // Timeouts are always considered contended.
+        if outcome == Outcome::Acquired
+            && threshold != u64::MAX
+            && end.saturating_sub(start) < threshold
+        {
+            return (value, None);
+        }
+
+        let timeout = Some(timeout.as_nanos() as u64);
+
+        let task_id = TASK_ID.with(Cell::get);
+
+        for event in [&mut critical, &mut acquiring] {
+            event.waiters = waiting;
+            event.timeout = timeout;
+            event.outcome = outcome;
+            event.contended = contended;
+            event.task_id = task_id;
+            event.process = self.process;
+        }
+
+        critical.attributes = ATTRIBUTES.with(|attributes| attributes.borrow().clone());
+
+        if contended || !self.contended_backtraces.load(Ordering::Relaxed) {
+            self.capture_backtrace(&mut critical);
+        }
+
+        self.capture_stack_depth(&mut critical);
+
+        let recorded = self.record_at(start, |storage, thread_index, timestamp| {
+            if !storage.reserve(2, max_events) {
+                return false;
+            }
+
+            storage.push_enter(critical, thread_index, timestamp);
+            storage.push_enter(acquiring, thread_index, timestamp);
+
+            storage.leaves.push_back(Leave {
+                sibling: acquiring_id,
+                thread_index,
+                timestamp: self.to_resolution(end),
+                leaked: false,
+            });
+
+            // A failed attempt holds nothing, so it's immediately left.
+            if outcome != Outcome::Acquired {
+                storage.leaves.push_back(Leave {
+                    sibling: id,
+                    thread_index,
+                    timestamp: self.to_resolution(end),
+                    leaked: false,
+                });
+            }
+
+            true
+        });
+
+        let event = (recorded && outcome == Outcome::Acquired).then_some(id);
+        (value, event)
+    }
+
+    /// Attempt to acquire a lock without blocking using the given closure,
+    /// recording the attempt and its outcome.
+    ///
+    /// Since the attempt never waits, it's recorded with an acquiring event
+    /// which is left immediately. A successful attempt is never considered
+    /// contended. Failed attempts are only recorded if enabled through
+    /// [`CaptureConfig::record_failed_tries`], in which case they're recorded
+    /// with the [`Outcome::TryFailed`] outcome even if only contended
+    /// acquisitions are recorded. Otherwise no events are allocated for them.
+    ///
+    /// This returns the acquired value if successful, and the event which
+    /// must be left once the lock is released.
+    pub(super) fn try_acquire<T>(
+        &self,
+        lock: LockId,
+        name: Name,
+        type_name: Name,
+        waiters: &AtomicU32,
+        try_acquire: impl FnOnce() -> Option<T>,
+    ) -> (Option<T>, Option<EventId>) {
+        if !self.records_operation(lock, name) {
+            return (try_acquire(), None);
+        }
+
+        let start = self.now();
+
+        let Some(value) = try_acquire() else {
+            if self.record_failed_tries.load(Ordering::Relaxed) {
+                let outcome = Outcome::TryFailed;
+                self.record_without_waiting(start, lock, name, type_name, waiters, outcome, false);
+            }
+
+            return (None, None);
+        };
+
+        if self.contended_only.load(Ordering::Relaxed) != u64::MAX {
+            return (Some(value), None);
+        }
+
+        let event = self.record_without_waiting(
+            start,
+            lock,
+            name,
+            type_name,
+            waiters,
+            Outcome::Acquired,
+            false,
+        );
+        (Some(value), event)
+    }
+
+    /// Record a zero-duration section of the given lock, such as a
+    /// notification of a condition variable.
+    ///
+    /// Like other acquisitions which never wait, markers are not recorded if
+    /// only contended acquisitions are.
+    pub(super) fn marker(&self, lock: LockId, name: Name, type_name: Name, waiters: &AtomicU32) {
+        if !self.records_operation(lock, name) {
+            return;
+        }
+
+        if self.contended_only.load(Ordering::Relaxed) != u64::MAX {
+            return;
+        }
+
+        self.record_without_waiting(
+            self.now(),
+            lock,
+            name,
+            type_name,
+            waiters,
+            Outcome::Acquired,
+            true,
+        );
+    }
+
+    /// Record an attempt at `start` which didn't wait, returning the event
+    /// which must be left once the lock is released unless `close` is set or
+    /// the attempt failed, in which case it's left immediately.
+    ///
+    /// A failed attempt found the lock held, so it's considered contended.
+    /// Otherwise no backtrace is captured if only backtraces of contended
+    /// acquisitions are recorded.
+    #[allow(clippy::too_many_arguments)]
+    fn record_without_waiting(
+        &self,
+        start: u64,
+        lock: LockId,
+        name: Name,
+        type_name: Name,
+        waiters: &AtomicU32,
+        outcome: Outcome,
+        close: bool,
+    ) -> Option<EventId> {
+        let max_events = self.max_events.load(Ordering::Relaxed);
+        let contended = outcome != Outcome::Acquired;
+        let close = close || contended;
+
+        let id = EventId::next();
+        let mut critical = Event::new(id, lock, name!("critical"), type_name, None);
+        let mut acquiring = Event::new(EventId::next(), lock, name, type_name, Some(id));
+        let acquiring_id = acquiring.id;
+
+        let waiting = waiters.load(Ordering::Relaxed);
+        let task_id = TASK_ID.with(Cell::get);
+
+        for event in [&mut critical, &mut acquiring] {
+            event.waiters = waiting;
+            event.outcome = outcome;
+            event.contended = contended;
+            event.task_id = task_id;
+            event.process = self.process;
+        }
+
+        critical.attributes = ATTRIBUTES.with(|attributes| attributes.borrow().clone());
+
+        if contended || !self.contended_backtraces.load(Ordering::Relaxed) {
+            self.capture_backtrace(&mut critical);
+        }
+
+        self.capture_stack_depth(&mut critical);
+
+        let recorded = self.record_at(start, |storage, thread_index, timestamp| {
+            if !storage.reserve(2, max_events) {
+                return false;
+            }
+
+            storage.push_enter(critical, thread_index, timestamp);
+            storage.push_enter(acquiring, thread_index, timestamp);
+
+            storage.leaves.push_back(Leave {
+                sibling: acquiring_id,
+                thread_index,
+                timestamp,
+                leaked: false,
+            });
+
+            if close {
+                storage.leaves.push_back(Leave {
+                    sibling: id,
+                    thread_index,
+                    timestamp,
+                    leaked: false,
+                });
+            }
+
+            true
+        });
+
+        (recorded && !close).then_some(id)
+    }
+
+    /// Enter an event nested inside of the given parent event, which must be
+    /// left once it's completed.
+    ///
+    /// Nothing is recorded if the parent wasn't recorded.
+    pub(super) fn nested(
+        &self,
+        parent: Option<EventId>,
+        lock: LockId,
+        name: Name,
+        type_name: Name,
+    ) -> Option<EventId> {
+        let parent = parent?;
+
+        if !self.is_recording() || !self.records(lock, name) {
+            return None;
+        }
+
+        let max_events = self.max_events.load(Ordering::Relaxed);
+
+        let mut event = Event::new(EventId::next(), lock, name, type_name, Some(parent));
+        event.task_id = TASK_ID.with(Cell::get);
+        event.process = self.process;
+        let id = event.id;
+
+        let recorded = self.record(|storage, thread_index, timestamp| {
+            if !storage.reserve(1, max_events) {
+                return false;
+            }
+
+            storage.push_enter(event, thread_index, timestamp);
+            true
+        });
+
+        recorded.then_some(id)
+    }
+
+    /// Leave the given span.
+    pub(super) fn leave(&self, sibling: Option<EventId>) {
+        self.record_leave(sibling, false);
+    }
+
+    /// Leave the given span of a lock which is leaked, and which will never be
+    /// released.
+    pub(super) fn leak(&self, sibling: Option<EventId>) {
+        self.record_leave(sibling, true);
+    }
+
+    fn record_leave(&self, sibling: Option<EventId>, leaked: bool) {
+        if let Some(sibling) = sibling {
+            self.record(|storage, thread_index, timestamp| {
+                storage.leaves.push_back(Leave {
+                    sibling,
+                    thread_index,
+                    timestamp,
+                    leaked,
+                })
+            });
+        }
+    }
+
+    /// Get the current timestamp in nanoseconds since the context was
+    /// created.
+    fn now(&self) -> u64 {
+        // NB: This is at risk of being truncated, but that still gives us ~584
+        // years worth of tracing.
+        Instant::now().duration_since(self.start).as_nanos() as u64
+    }
+
+    /// Round a timestamp down to the configured resolution.
+    ///
+    /// Since the baseline that timestamps are adjusted against is rounded the
+    /// same way, drained timestamps are exact multiples of the resolution.
+    #[inline]
+    fn to_resolution(&self, timestamp: u64) -> u64 {
+        let resolution = self.resolution.load(Ordering::Relaxed);
+        timestamp - timestamp % resolution
+    }
+
+    /// Record an event.
+    fn record<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut ThreadStorage, usize, u64) -> R,
+    {
+        self.record_at(self.now(), f)
+    }
+
+    /// Record an event at the given timestamp.
+    fn record_at<F, R>(&self, timestamp: u64, f: F) -> R
+    where
+        F: FnOnce(&mut ThreadStorage, usize, u64) -> R,
+    {
+        let thread_index = self.thread_index();
+
+        f(
+            &mut self.storage[thread_index % self.storage.len()].lock(),
+            thread_index,
+            self.to_resolution(timestamp),
+        )
+    }
+
+    /// Get the index of the current thread.
+    fn thread_index(&self) -> usize {
+        let source = THREAD_INDEX_SOURCE.load(Ordering::Acquire);
+
+        if source.is_null() {
+            let index = builtin_thread_index();
+            let generation = THREAD_GENERATION.load(Ordering::Relaxed);
+
+            if THREAD_NAMED.with(|named| named.replace(generation)) != generation {
+                self.name_thread(index);
+            }
+
+            return index;
+        }
+
+        // SAFETY: Only function pointers of this type are stored in the
+        // source.
+        let source = unsafe { mem::transmute::<*mut (), fn() -> usize>(source) };
+        let index = source();
+
+        let generation = THREAD_GENERATION.load(Ordering::Relaxed);
+
+        // Only check for conflicts when the index of this thread changes,
+        // which keeps the common path to a thread-local lookup.
+        let claimed = THREAD_INDEX_CLAIMED.with(|claim| {
+            let mut claim = claim.borrow_mut();
+
+            if claim.index == Some(index) && claim.generation == generation {
+                return None;
+            }
+
+            claim.owner = builtin_thread_index();
+            claim.generation = generation;
+            Some((claim.index.replace(index), claim.owner))
+        });
+
+        if let Some((previous, owner)) = claimed {
+            self.claim_thread_index(index, previous, owner);
+            self.name_thread(index);
+        }
+
+        index
+    }
+
+    /// Claim `index` for the thread with the built-in index `owner`, releasing
+    /// the `previous` index it claimed.
+    ///
+    /// Indexes are only considered to conflict if they're claimed by two
+    /// threads at the same time, so that indexes can be handed from threads
+    /// which are done with them to new ones.
+    #[cold]
+    fn claim_thread_index(&self, index: usize, previous: Option<usize>, owner: usize) {
+        let mut owners = self.thread_owners.lock();
+
+        if let Some(previous) = previous {
+            if owners.get(&previous) == Some(&owner) {
+                owners.remove(&previous);
+            }
+        }
+
+        if *owners.entry(index).or_insert(owner) != owner {
+            self.thread_index_conflicts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Release `index` if it's claimed by the thread with the built-in index
+    /// `owner`.
+    fn release_thread_index(&self, index: usize, owner: usize) {
+        let mut owners = self.thread_owners.lock();
+
+        if owners.get(&index) == Some(&owner) {
+            owners.remove(&index);
+        }
+    }
+
+    /// Forget the state kept about threads once no capture is active, so that
+    /// it doesn't accumulate across captures. Threads provide it again the
+    /// next time they record an event.
+    fn forget_threads(&self, blocking: bool) {
+        let sessions = if blocking {
+            self.sessions.lock()
+        } else {
+            match self.sessions.try_lock() {
+                Some(sessions) => sessions,
+                None => return,
+            }
+        };
+
+        if self.active.load(Ordering::Acquire) != 0 {
+            return;
+        }
+
+        let mut owners = self.thread_owners.lock();
+        let mut names = self.thread_names.lock();
+        THREAD_GENERATION.fetch_add(1, Ordering::Relaxed);
+        owners.clear();
+        names.clear();
+        drop((owners, names));
+        drop(sessions);
+    }
+
+    /// Store the name of the current thread, if it has one, as the name of
+    /// the given thread index.
+    #[cold]
+    fn name_thread(&self, index: usize) {
+        let thread = thread::current();
+
+        if let Some(name) = thread.name() {
+            self.thread_names
+                .lock()
+                .entry(index)
+                .or_insert_with(|| name.to_owned());
+        }
+    }
+
+    /// Shrink the buffers of every thread storage to fit their events.
+    pub(super) fn release_buffers(&self) {
+        for storage in self.storage.iter() {
+            let mut storage = storage.lock();
+            storage.enters.shrink_to_fit();
+            storage.leaves.shrink_to_fit();
+        }
+    }
+
+    /// Report the state of acquisitions which haven't been released.
+    pub(super) fn held_locks(&self) -> Vec<LockState> {
+        let adjust = self.adjust.load(Ordering::Acquire);
+
+        if adjust == u64::MAX {
+            return Vec::new();
+        }
+
+        // Open acquisitions and the nested events spent waiting for them.
+        let mut open = Vec::new();
+        let mut waits = HashMap::new();
+        let mut closes = HashMap::new();
+
+        for storage in self.storage.iter() {
+            let storage = storage.lock();
+
+            for enter in &storage.enters {
+                match enter.parent {
+                    Some(parent) => {
+                        waits.insert(parent, enter.id);
+                    }
+                    None => {
+                        open.push((enter.id, enter.lock, enter.thread_index, enter.timestamp));
+                    }
+                }
+            }
+
+            for leave in &storage.leaves {
+                closes.insert(leave.sibling, leave.timestamp);
+            }
+        }
+
+        open.retain(|(id, ..)| !closes.contains_key(id));
+        open.sort_by_key(|&(id, ..)| id);
+
+        let since = |timestamp: u64| Duration::from_nanos(timestamp.saturating_sub(adjust));
+
+        let mut states = Vec::with_capacity(open.len());
+        let mut holders = HashMap::new();
+
+        for &(id, lock, thread, timestamp) in &open {
+            let acquired = waits.get(&id).and_then(|wait| closes.get(wait));
+
+            match acquired {
+                Some(&acquired) => {
+                    holders.entry(lock).or_insert(thread);
+
+                    states.push(LockState::Held {
+                        lock,
+                        thread,
+                        since: since(acquired),
+                    });
+                }
+                None => {
+                    states.push(LockState::Waiting {
+                        lock,
+                        thread,
+                        since: since(timestamp),
+                        blocked_by: None,
+                    });
+                }
+            }
+        }
+
+        for state in &mut states {
+            if let LockState::Waiting {
+                lock, blocked_by, ..
+            } = state
+            {
+                *blocked_by = holders.get(lock).copied();
+            }
+        }
+
+        states
+    }
+
+    /// Drain events.
+    ///
+    /// If capture is enabled while draining, the exact events recorded are
+    /// not specified.
+    pub(super) fn drain(&self, key: SortKey, blocking: bool) -> Result<Events, DrainError> {
+        let sessions = if blocking {
+            self.sessions.lock()
+        } else {
+            self.sessions.try_lock().ok_or(DrainError::WouldBlock)?
+        };
+
+        let depth = self.depth.load(Ordering::Relaxed);
+
+        if depth == 0 {
+            return Err(DrainError::NotCapturing);
+        }
+
+        // Every storage is locked before anything is changed, so that a drain
+        // which would block leaves the capture as it was.
+        let storage = if blocking {
+            self.lock_storage()
+        } else {
+            self.try_lock_storage().ok_or(DrainError::WouldBlock)?
+        };
+
+        self.depth.store(depth - 1, Ordering::Relaxed);
+
+        let since = sessions[0].counters;
+
+        // A nested capture only gets a snapshot, since the capture is still
+        // ongoing.
+        if depth > 1 {
+            drop(sessions);
+            return self.snapshot_from(storage, since, key);
+        }
+
+        let adjust = self.adjust.swap(u64::MAX, Ordering::AcqRel);
+        self.active.fetch_and(!1, Ordering::AcqRel);
+        let started_at = self.started_at.load(Ordering::Relaxed);
+        let deadline = self.deadline.swap(u64::MAX, Ordering::Relaxed);
+        let retained_from = self.retained_from(&sessions);
+        drop(sessions);
+
+        let mut events = self.collect(storage, retained_from, adjust, deadline, started_at, since);
+        self.forget_threads(blocking);
+        events.sort(key);
+        Ok(events)
+    }
+
+    /// Copy the events captured so far.
+    pub(super) fn snapshot(&self, key: SortKey) -> Result<Events, DrainError> {
+        let since = self.sessions.lock()[0].counters;
+        self.snapshot_from(self.lock_storage(), since, key)
+    }
+
+    /// Copy the events captured so far out of the given locked storage.
+    fn snapshot_from(
+        &self,
+        storage: Vec<MutexGuard<'_, ThreadStorage>>,
+        since: Counters,
+        key: SortKey,
+    ) -> Result<Events, DrainError> {
+        let adjust = self.adjust.load(Ordering::Acquire);
+
+        if adjust == u64::MAX {
+            return Err(DrainError::NotCapturing);
+        }
+
+        let started_at = self.started_at.load(Ordering::Relaxed);
+        let deadline = self.deadline.load(Ordering::Relaxed);
+        let mut events = self.collect(storage, 0, adjust, deadline, started_at, since);
+        events.sort(key);
+        Ok(events)
+    }
+
+    /// Drain the events of the given capture session.
+    pub(super) fn drain_session(&self, id: SessionId) -> Result<Events, DrainError> {
+        let bit = 1 << id.index();
+
+        let (session, retained_from) = {
+            let sessions = self.sessions.lock();
+
+            // An identifier of a drained session must not drain a later
+            // session which reuses its index.
+            if sessions[id.index()].generation != id.generation() {
+                return Err(DrainError::NotCapturing);
+            }
+
+            if self.active.fetch_and(!bit, Ordering::AcqRel) & bit == 0 {
+                return Err(DrainError::NotCapturing);
+            }
+
+            (sessions[id.index()], self.retained_from(&sessions))
+        };
+
+        let mut events = self.collect(
+            self.lock_storage(),
+            retained_from,
+            session.adjust,
+            u64::MAX,
+            session.started_at,
+            session.counters,
+        );
+
+        self.forget_threads(true);
+        events.sort(SortKey::Id);
+        Ok(events)
+    }
+
+    /// The current values of the counters reported by captures.
+    fn counters(&self) -> Counters {
+        Counters {
+            dropped: self
+                .storage
+                .iter()
+                .map(|storage| storage.lock().dropped)
+                .sum(),
+            thread_index_conflicts: self.thread_index_conflicts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Lock every thread storage.
+    fn lock_storage(&self) -> Vec<MutexGuard<'_, ThreadStorage>> {
+        self.storage.iter().map(|storage| storage.lock()).collect()
+    }
+
+    /// Try to lock every thread storage, returning `None` if any of them is
+    /// currently locked.
+    fn try_lock_storage(&self) -> Option<Vec<MutexGuard<'_, ThreadStorage>>> {
+        self.storage
+            .iter()
+            .map(|storage| storage.try_lock())
+            .collect()
+    }
+
+    /// The earliest timestamp of events which are still needed by an active
+    /// capture.
+    fn retained_from(&self, sessions: &[Session; MAX_SESSIONS + 1]) -> u64 {
+        let mut active = self.active.load(Ordering::Acquire);
+        let mut from = u64::MAX;
+
+        if active & 1 != 0 {
+            from = self.adjust.load(Ordering::Acquire);
+        }
+
+        active &= !1;
+
+        while active != 0 {
+            let index = active.trailing_zeros() as usize;
+            from = from.min(sessions[index].adjust);
+            active &= !(1 << index);
+        }
+
+        from
+    }
+
+    /// Collect the events entered between `adjust` and `deadline` out of the
+    /// locked `storage`, removing the ones recorded before `retained_from`
+    /// since no active capture needs them.
+    fn collect(
+        &self,
+        storage: Vec<MutexGuard<'_, ThreadStorage>>,
+        retained_from: u64,
+        adjust: u64,
+        deadline: u64,
+        started_at: u64,
+        since: Counters,
+    ) -> Events {
+        let mut events = Events::new();
+        let mut enters = VecDeque::new();
+        let mut leaves = VecDeque::new();
+
+        // Each storage is unlocked as soon as its events have been collected.
+        for mut storage in storage {
+            for enter in storage.enters.drain(..) {
+                let retained = enter.timestamp >= retained_from;
+
+                // Sessions which are still active can record past the
+                // deadline.
+                if enter.timestamp < adjust || enter.timestamp >= deadline {
+                    if retained {
+                        enters.push_back(enter);
+                    }
+
+                    continue;
+                }
+
+                if retained {
+                    enters.push_back(enter.clone());
+                }
+
+                events.enters.push(enter);
+            }
+
+            storage.enters.append(&mut enters);
+
+            for leave in storage.leaves.drain(..) {
+                let retained = leave.timestamp >= retained_from;
+
+                if leave.timestamp < adjust {
+                    if retained {
+                        leaves.push_back(leave);
+                    }
+
+                    continue;
+                }
+
+                if retained {
+                    leaves.push_back(leave.clone());
+                }
+
+                events.leaves.push(leave);
+            }
+
+            storage.leaves.append(&mut leaves);
+
+            events.dropped += storage.dropped;
+        }
+
+        events.dropped = events.dropped.saturating_sub(since.dropped);
+
+        // Sections which were entered before the capture started or which
+        // were evicted from a ring buffer are left out, so the events nested
+        // in them and their leave events are as well. Nested events can be
+        // recorded long after their parent, so they're not necessarily
+        // evicted along with it.
+        let ids = loop {
+            let ids = events
+                .enters
+                .iter()
+                .map(|enter| enter.id)
+                .collect::<HashSet<_>>();
+
+            let len = events.enters.len();
+
+            events
+                .enters
+                .retain(|enter| enter.parent.map_or(true, |parent| ids.contains(&parent)));
+
+            if events.enters.len() == len {
+                break ids;
+            }
+        };
+
+        if !events.leaves.is_empty() {
+            events.leaves.retain(|leave| ids.contains(&leave.sibling));
+        }
+
+        // Events recorded before the baseline, such as by a thread which read
+        // the clock right before the capture was started, were left out
+        // above. Adjusting still saturates in case that ever changes.
+        for enter in &mut events.enters {
+            enter.timestamp = enter.timestamp.saturating_sub(adjust);
+        }
+
+        for leave in &mut events.leaves {
+            leave.timestamp = leave.timestamp.saturating_sub(adjust);
+        }
+
+        events.count_held();
+
+        events.thread_index_conflicts = self
+            .thread_index_conflicts
+            .load(Ordering::Relaxed)
+            .saturating_sub(since.thread_index_conflicts);
+
+        let creation_sites = self.creation_sites.lock();
+
+        if !creation_sites.is_empty() {
+            for enter in &events.enters {
+                if let Some(backtrace) = creation_sites.get(&enter.lock) {
+                    events
+                        .creation_sites
+                        .entry(enter.lock)
+                        .or_insert_with(|| backtrace.clone());
+                }
+            }
+        }
+
+        drop(creation_sites);
+
+        let keys = self.keys.lock();
+
+        if !keys.is_empty() {
+            for enter in &events.enters {
+                if let Some(key) = keys.get(&enter.lock) {
+                    events.keys.insert(enter.lock, *key);
+                }
+            }
+        }
+
+        drop(keys);
+
+        let thread_names = self.thread_names.lock();
+
+        if !thread_names.is_empty() {
+            for enter in &events.enters {
+                if let Some(name) = thread_names.get(&enter.thread_index) {
+                    events
+                        .thread_names
+                        .entry(self.process)
+                        .or_default()
+                        .entry(enter.thread_index)
+                        .or_insert_with(|| name.clone());
+                }
+            }
+        }
+
+        drop(thread_names);
+
+        events.processes.push(Process::current(
+            self.process_label.lock().clone(),
+            (started_at != 0).then_some(started_at),
+        ));
+
+        // Symbolizing is done here on the draining thread, since it is too
+        // expensive to do while acquiring locks.
+        #[cfg(feature = "backtrace")]
+        events.resolve_backtraces();
+        events
+    }
+}
+
+/// Get the approximate depth of the stack of the current thread in bytes.
+///
+/// This is the distance from the shallowest point at which this has been
+/// called on the current thread, assuming that the stack grows downwards.
+#[inline(never)]
+fn stack_depth() -> u32 {
+    let marker = 0u8;
+    let address = ptr::addr_of!(marker) as usize;
+
+    STACK_BASE.with(|base| {
+        let top = base.get().max(address);
+        base.set(top);
+        u32::try_from(top - address).unwrap_or(u32::MAX)
+    })
+}
+
+/// Get the index of the current thread from the built-in source.
+///
+/// Every thread is assigned a unique index the first time this is called.
+fn builtin_thread_index() -> usize {
+    THREAD_INDEX_THREAD.with(|index| {
+        if let Some(index) = index.get() {
+            return index;
+        }
+
+        let result = THREAD_INDEX.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |index| {
+            index.checked_add(1)
+        });
+
+        let Ok(new_index) = result else {
+            panic!("unlock: Too many threads");
+        };
+
         index.set(Some(new_index));
         new_index
     })
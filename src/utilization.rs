@@ -0,0 +1,59 @@
+use crate::{Events, LockId};
+
+impl Events {
+    /// Calculate the fraction of the trace during which the given lock was
+    /// held by anyone.
+    ///
+    /// This is computed by merging all the intervals during which the lock was
+    /// held, from the point it was acquired until it was released, and
+    /// dividing it by the total span of the trace. A lock which is close to
+    /// `1.0` is a bottleneck.
+    ///
+    /// Returns `0.0` if the trace is empty.
+    pub fn utilization(&self, lock: LockId) -> f32 {
+        let Some((start, end)) = self.span() else {
+            return 0.0;
+        };
+
+        if start >= end {
+            return 0.0;
+        }
+
+        let times = self.holds();
+        let mut holds = Vec::new();
+
+        for enter in &self.enters {
+            if enter.lock != lock || enter.parent.is_some() {
+                continue;
+            }
+
+            if let Some((open, Some(close))) = times.hold(enter) {
+                holds.push((open, close));
+            }
+        }
+
+        holds.sort();
+
+        let mut held = 0;
+        let mut current: Option<(u64, u64)> = None;
+
+        for (open, close) in holds {
+            match &mut current {
+                Some((_, current_close)) if open <= *current_close => {
+                    *current_close = (*current_close).max(close);
+                }
+                _ => {
+                    if let Some((open, close)) = current.replace((open, close)) {
+                        held += close.saturating_sub(open);
+                    }
+                }
+            }
+        }
+
+        if let Some((open, close)) = current {
+            held += close.saturating_sub(open);
+        }
+
+        held as f32 / (end - start) as f32
+    }
+}
@@ -0,0 +1,80 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+use unlock::LockKind;
+
+#[test]
+fn wait_splits_the_mutex_section() {
+    let pair = Arc::new((unlock::Mutex::new(false), unlock::Condvar::new()));
+    let pair2 = pair.clone();
+
+    unlock::capture();
+
+    let (lock, condvar) = &*pair;
+    let mut started = lock.lock();
+
+    // The mutex is held until we wait, so we wait at least once.
+    let thread = thread::spawn(move || {
+        let (lock, condvar) = &*pair2;
+        *lock.lock() = true;
+        condvar.notify_one();
+    });
+
+    while !*started {
+        condvar.wait(&mut started);
+    }
+
+    drop(started);
+    thread.join().unwrap();
+
+    let events = unlock::drain();
+
+    let first = &events.enters()[0];
+    assert_eq!(first.lock().kind(), LockKind::Mutex);
+
+    let roots = events
+        .enters()
+        .iter()
+        .filter(|event| event.parent().is_none())
+        .filter(|event| event.thread_index() == first.thread_index())
+        .collect::<Vec<_>>();
+
+    let section = |root: &unlock::Event| {
+        let child = events
+            .enters()
+            .iter()
+            .find(|event| event.parent() == Some(root.id()))
+            .unwrap();
+        (root.lock().kind(), child.name())
+    };
+
+    assert!(roots.len() >= 3);
+    assert_eq!(roots.len() % 2, 1);
+
+    // Sections alternate between holding the mutex and waiting on the
+    // condition variable, starting and ending with the mutex.
+    for (n, root) in roots.iter().enumerate() {
+        if n % 2 == 0 {
+            assert_eq!(section(root), (LockKind::Mutex, "lock"));
+        } else {
+            assert_eq!(section(root), (LockKind::Condvar, "wait"));
+        }
+    }
+
+    let closes = events
+        .leaves()
+        .iter()
+        .map(|leave| (leave.sibling(), leave.timestamp()))
+        .collect::<HashMap<_, _>>();
+
+    // Each section is closed before the next one is opened.
+    for pair in roots.windows(2) {
+        assert!(closes[&pair[0].id()] <= pair[1].timestamp());
+    }
+
+    assert!(closes.contains_key(&roots[roots.len() - 1].id()));
+    assert_eq!(events.leaves().len(), events.enters().len());
+}
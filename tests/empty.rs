@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use unlock::{Error, Events};
+
+/// Tests modify the global tracing context, so they can't run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// A collection from a capture which didn't record anything.
+fn empty() -> Events {
+    unlock::capture();
+    unlock::drain()
+}
+
+/// A collection from a capture which was never started.
+fn never_captured() -> Events {
+    if cfg!(all(feature = "trace", feature = "parking_lot")) {
+        assert!(unlock::try_drain().is_err());
+    }
+
+    unlock::drain()
+}
+
+/// A directory of its own to write files to.
+fn directory(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn assert_renders_nothing(events: &Events, name: &str) {
+    assert!(events.is_empty());
+
+    let dir = directory(name);
+    let result = unlock::html::write(dir.join("trace.html"), events);
+    assert!(matches!(result, Err(Error::EmptyTrace)));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    let result = unlock::chrome::write(dir.join("trace.json"), events);
+    assert!(matches!(result, Err(Error::EmptyTrace)));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    let mut out = Vec::new();
+    let result = unlock::html::write_to(&mut out, events, &unlock::html::Options::new());
+    assert!(matches!(result, Err(Error::EmptyTrace)));
+    assert!(out.is_empty());
+
+    let result = unlock::svg::write(&mut out, events, &unlock::svg::Options::new());
+    assert!(matches!(result, Err(Error::EmptyTrace)));
+    assert!(out.is_empty());
+
+    let result = unlock::gantt::write(&mut out, events, 80);
+    assert!(matches!(result, Err(Error::EmptyTrace)));
+    assert!(out.is_empty());
+
+    unlock::folded::write_to(&mut out, events).unwrap();
+    unlock::folded::write_wait_to(&mut out, events).unwrap();
+    assert!(out.is_empty());
+
+    unlock::json::write(&mut out, events).unwrap();
+    let json = serde_json::from_slice::<serde_json::Value>(&out).unwrap();
+    assert_eq!(json["enters"], serde_json::json!([]));
+    assert_eq!(json["leaves"], serde_json::json!([]));
+}
+
+fn assert_summarizes_nothing(events: &Events) {
+    let stats = events.stats();
+    assert!(stats.locks.is_empty());
+    assert!(stats.types.is_empty());
+
+    let summary = events.summary();
+    assert_eq!(summary.locks().count(), 0);
+
+    assert!(events.hold_times().is_empty());
+    assert!(events.wait_times().is_empty());
+    assert_eq!(events.leaked().count(), 0);
+}
+
+#[test]
+fn empty_capture() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let events = empty();
+    assert_renders_nothing(&events, "empty-capture");
+    assert_summarizes_nothing(&events);
+}
+
+#[test]
+fn never_captured_events() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let events = never_captured();
+    assert_renders_nothing(&events, "never-captured");
+    assert_summarizes_nothing(&events);
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "serde")]
+
+use std::fs;
+use std::path::PathBuf;
+
+use unlock::html::{self, Options};
+use unlock::Events;
+
+/// A capture of a mutex spanning three seconds, where event `2` only lasts for
+/// half a microsecond.
+fn events() -> Events {
+    let json = r#"{
+        "enters": [
+            {"id":1,"timestamp":0,"thread_index":0,"parent":null,"name":"lock","type_name":"u32","lock":2147483649},
+            {"id":2,"timestamp":2000000000,"thread_index":0,"parent":null,"name":"lock","type_name":"u32","lock":2147483649},
+            {"id":3,"timestamp":2500000000,"thread_index":0,"parent":null,"name":"lock","type_name":"u32","lock":2147483649}
+        ],
+        "leaves": [
+            {"sibling":1,"thread_index":0,"timestamp":1000000000},
+            {"sibling":2,"thread_index":0,"timestamp":2000000500},
+            {"sibling":3,"thread_index":0,"timestamp":3000000000}
+        ]
+    }"#;
+
+    serde_json::from_str(json).unwrap()
+}
+
+/// Render events to a string with the given options.
+fn render(options: &Options) -> String {
+    let mut out = Vec::new();
+    html::write_to(&mut out, &events(), options).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// Find the element rendering the event with the given identifier.
+fn element(html: &str, id: u64) -> &str {
+    html.lines()
+        .find(|line| line.contains(&format!("<div id=\"event-{id}\"")))
+        .expect("the event is rendered")
+}
+
+/// A directory of its own to write files to.
+fn directory(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn short_sections_are_visible() {
+    let html = render(&Options::new());
+    let short = element(&html, 2);
+    assert!(short.contains("width: 0.0000%;"), "{short}");
+    assert!(short.contains("min-width: 1px;"), "{short}");
+
+    let html = render(&Options::new().min_section_width(4));
+    let short = element(&html, 2);
+    assert!(short.contains("min-width: 4px;"), "{short}");
+
+    let html = render(&Options::new().min_section_width(0));
+    let short = element(&html, 2);
+    assert!(short.contains("min-width: 0px;"), "{short}");
+}
+
+#[test]
+fn assets_are_linked_when_not_written() {
+    let dir = directory("html-assets");
+    let path = dir.join("trace.html");
+
+    html::write_with(&path, &events(), &Options::new().write_assets(false)).unwrap();
+
+    let document = fs::read_to_string(&path).unwrap();
+    assert!(document.contains(r#"<link href="trace.css" rel="stylesheet">"#));
+    assert!(document.contains(r#"src="trace.js""#));
+    assert!(!dir.join("trace.css").exists());
+    assert!(!dir.join("trace.js").exists());
+
+    // The assets written by default are the ones the document links to.
+    html::write(&path, &events()).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), document);
+    assert!(!fs::read_to_string(dir.join("trace.css"))
+        .unwrap()
+        .is_empty());
+    assert!(!fs::read_to_string(dir.join("trace.js")).unwrap().is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "serde")]
+
+use unlock::Events;
+
+/// Build a capture where every event has a distinct type name.
+fn events(count: usize) -> String {
+    let enters = (0..count)
+        .map(|n| {
+            format!(
+                r#"{{"id":{},"timestamp":0,"thread_index":0,"parent":null,"name":"lock","type_name":"type{n}","lock":2147483649}}"#,
+                n + 1
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!(r#"{{"enters":[{}],"leaves":[]}}"#, enters.join(","))
+}
+
+#[test]
+fn too_many_names_is_an_error() {
+    let Err(error) = serde_json::from_str::<Events>(&events(70_000)) else {
+        panic!("expected an error");
+    };
+
+    assert!(
+        error.to_string().contains("too many distinct names"),
+        "{error}"
+    );
+
+    // Names which are already interned can still be used.
+    let parsed = serde_json::from_str::<Events>(&events(10)).unwrap();
+    assert_eq!(parsed.enters()[9].type_name(), "type9");
+}
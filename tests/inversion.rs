@@ -0,0 +1,45 @@
+#![cfg(feature = "serde")]
+
+use std::time::Duration;
+
+use unlock::Events;
+
+/// Critical sections of an `RwLock` where thread `1` waits for a write held by
+/// thread `0`, and later for a read held by thread `2`.
+fn events() -> Events {
+    let json = r#"{
+        "enters": [
+            {"id":1,"timestamp":0,"thread_index":0,"parent":null,"name":"critical","type_name":"u32","lock":1073741825},
+            {"id":2,"timestamp":0,"thread_index":0,"parent":1,"name":"write","type_name":"u32","lock":1073741825},
+            {"id":3,"timestamp":10,"thread_index":1,"parent":null,"name":"critical","type_name":"u32","lock":1073741825},
+            {"id":4,"timestamp":10,"thread_index":1,"parent":3,"name":"read","type_name":"u32","lock":1073741825},
+            {"id":5,"timestamp":200,"thread_index":2,"parent":null,"name":"critical","type_name":"u32","lock":1073741825},
+            {"id":6,"timestamp":200,"thread_index":2,"parent":5,"name":"read","type_name":"u32","lock":1073741825},
+            {"id":7,"timestamp":210,"thread_index":1,"parent":null,"name":"critical","type_name":"u32","lock":1073741825},
+            {"id":8,"timestamp":210,"thread_index":1,"parent":7,"name":"read","type_name":"u32","lock":1073741825}
+        ],
+        "leaves": [
+            {"sibling":2,"thread_index":0,"timestamp":0},
+            {"sibling":1,"thread_index":0,"timestamp":100},
+            {"sibling":4,"thread_index":1,"timestamp":100},
+            {"sibling":3,"thread_index":1,"timestamp":150},
+            {"sibling":6,"thread_index":2,"timestamp":200},
+            {"sibling":5,"thread_index":2,"timestamp":300},
+            {"sibling":8,"thread_index":1,"timestamp":250},
+            {"sibling":7,"thread_index":1,"timestamp":260}
+        ]
+    }"#;
+
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn readers_do_not_block_readers() {
+    let events = events();
+    let inversions = events.priority_inversions(|thread| if thread == 1 { 10 } else { 0 });
+
+    assert_eq!(inversions.len(), 1);
+    assert_eq!(inversions[0].waiter().id(), events.enters()[2].id());
+    assert_eq!(inversions[0].holder().id(), events.enters()[0].id());
+    assert_eq!(inversions[0].overlap(), Duration::from_nanos(90));
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "serde")]
+
+use std::collections::HashSet;
+
+use unlock::{Events, LockId};
+
+/// The first mutex.
+const LOCK: u32 = (2 << 30) + 1;
+
+/// Build a capture of one critical section on thread 0 of the given
+/// process, using the same event, lock and thread identifiers as any other
+/// capture built through this.
+fn events(process: u32, thread_name: &str, extra: &str) -> Events {
+    let json = format!(
+        r#"{{
+            "enters":[
+                {{"id":1,"timestamp":0,"thread_index":0,"parent":null,"name":"critical","type_name":"u32","lock":{LOCK},"process":{process}}},
+                {{"id":2,"timestamp":0,"thread_index":0,"parent":1,"name":"lock","type_name":"u32","lock":{LOCK},"process":{process}}}
+            ],
+            "leaves":[
+                {{"sibling":2,"thread_index":0,"timestamp":10}},
+                {{"sibling":1,"thread_index":0,"timestamp":20}}
+            ],
+            "processes":[{{"id":{process},"start":1000}}],
+            "thread_names":{{"{process}":{{"0":"{thread_name}"}}}}
+            {extra}
+        }}"#
+    );
+
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn merge_renumbers_overlapping_identifiers() {
+    // A lock which is never entered, but which has a key.
+    let keyed: LockId = serde_json::from_str(&(LOCK + 1).to_string()).unwrap();
+
+    let mut events = events(
+        1,
+        "first",
+        &format!(r#","keys":{{"{}":"keyed"}}"#, LOCK + 1),
+    );
+    events.merge(self::events(2, "second", ""));
+
+    assert_eq!(events.enters().len(), 4);
+    assert_eq!(events.leaves().len(), 4);
+
+    let ids = events
+        .enters()
+        .iter()
+        .map(|event| event.id())
+        .collect::<HashSet<_>>();
+
+    assert_eq!(ids.len(), 4);
+
+    // Every leave and parent still refers to an event of its own process.
+    for leave in events.leaves() {
+        assert!(ids.contains(&leave.sibling()));
+    }
+
+    let (first, second) = events.enters().split_at(2);
+    assert_eq!(first[1].parent(), Some(first[0].id()));
+    assert_eq!(second[1].parent(), Some(second[0].id()));
+
+    // The lock of the merged process doesn't collide with the keyed lock.
+    assert_ne!(second[0].lock(), first[0].lock());
+    assert_ne!(second[0].lock(), keyed);
+    assert_eq!(events.lock_key(keyed), Some("keyed"));
+    assert_eq!(events.lock_key(second[0].lock()), None);
+
+    // Threads with the same index keep the names of their own process.
+    assert_eq!(events.thread_name(&first[0]), Some("first"));
+    assert_eq!(events.thread_name(&second[0]), Some("second"));
+}
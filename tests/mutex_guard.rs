@@ -0,0 +1,82 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+
+use unlock::MutexGuard;
+
+/// Tests modify the global tracing context, so they can't run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[test]
+fn try_map_leaves_when_the_closure_panics() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::Mutex::new(Some(0));
+
+    unlock::capture();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let _ = MutexGuard::try_map(lock.lock(), |_| -> Option<&mut i32> { panic!("boom") });
+    }));
+
+    assert!(result.is_err());
+
+    // The lock was released during unwinding.
+    let mut value = MutexGuard::try_map(lock.lock(), |value| value.as_mut())
+        .ok()
+        .unwrap();
+    *value += 1;
+    drop(value);
+
+    let events = unlock::drain();
+
+    let roots = events
+        .enters()
+        .iter()
+        .filter(|event| event.parent().is_none())
+        .collect::<Vec<_>>();
+
+    assert_eq!(roots.len(), 2);
+    assert_eq!(events.leaves().len(), events.enters().len());
+
+    for root in roots {
+        let leaves = events
+            .leaves()
+            .iter()
+            .filter(|leave| leave.sibling() == root.id());
+        assert_eq!(leaves.count(), 1);
+    }
+}
+
+#[test]
+fn leak_records_a_leaked_leave() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let leaked = unlock::Mutex::new(0);
+    let released = unlock::Mutex::new(0);
+
+    unlock::capture();
+
+    *MutexGuard::leak(leaked.lock()) += 1;
+    *released.lock() += 1;
+
+    let events = unlock::drain();
+
+    assert!(leaked.try_lock().is_none());
+
+    let sections = events.leaked().collect::<Vec<_>>();
+    assert_eq!(sections.len(), 1);
+    assert!(sections[0].parent().is_none());
+    assert_ne!(sections[0].lock(), events.enters().last().unwrap().lock());
+
+    let leaves = events
+        .leaves()
+        .iter()
+        .filter(|leave| leave.leaked())
+        .collect::<Vec<_>>();
+
+    assert_eq!(leaves.len(), 1);
+    assert_eq!(leaves[0].sibling(), sections[0].id());
+    assert_eq!(events.leaves().len(), events.enters().len());
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "serde")]
+
+use unlock::Events;
+
+/// Build a capture of `count` events which share their names.
+fn shared_names(count: usize) -> Events {
+    let enters = (0..count)
+        .map(|n| {
+            format!(
+                r#"{{"id":{},"timestamp":0,"thread_index":0,"parent":null,"name":"lock","type_name":"shared_type","lock":2147483649}}"#,
+                n + 1
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let json = format!(r#"{{"enters":[{}],"leaves":[]}}"#, enters.join(","));
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn names_are_serialized_once() {
+    let events = shared_names(3);
+
+    let mut written = Vec::new();
+    unlock::json::write(&mut written, &events).unwrap();
+    let written = String::from_utf8(written).unwrap();
+
+    for json in [serde_json::to_string(&events).unwrap(), written] {
+        assert!(
+            json.starts_with(r#"{"names":["lock","shared_type"],"#),
+            "{json}"
+        );
+        assert_eq!(json.matches("shared_type").count(), 1, "{json}");
+
+        let parsed = serde_json::from_str::<Events>(&json).unwrap();
+        assert_eq!(parsed.enters().len(), 3);
+
+        for event in parsed.enters() {
+            assert_eq!(event.name(), "lock");
+            assert_eq!(event.type_name(), "shared_type");
+        }
+    }
+}
+
+#[test]
+fn name_indexes_need_a_table() {
+    let json = r#"{"enters":[{"id":1,"timestamp":0,"thread_index":0,"parent":null,"name":0,"type_name":1,"lock":2147483649}],"leaves":[]}"#;
+    assert!(serde_json::from_str::<Events>(json).is_err());
+}
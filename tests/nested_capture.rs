@@ -0,0 +1,64 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::sync::Mutex;
+
+/// Tests modify the global tracing context, so they can't run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn roots(events: &unlock::Events) -> usize {
+    events
+        .enters()
+        .iter()
+        .filter(|event| event.parent().is_none())
+        .count()
+}
+
+#[test]
+fn inner_drain_snapshots_and_outer_drain_stops() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::Mutex::new(0);
+
+    unlock::capture();
+    *lock.lock() += 1;
+
+    unlock::capture();
+    *lock.lock() += 1;
+
+    let inner = unlock::drain();
+    assert_eq!(roots(&inner), 2);
+    assert!(unlock::is_capturing());
+
+    *lock.lock() += 1;
+
+    let outer = unlock::drain();
+    assert_eq!(roots(&outer), 3);
+    assert_eq!(outer.enters().len(), outer.leaves().len());
+    assert!(!unlock::is_capturing());
+
+    *lock.lock() += 1;
+    assert!(unlock::drain().is_empty());
+}
+
+#[test]
+fn every_capture_must_be_drained() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::Mutex::new(0);
+
+    unlock::capture();
+    unlock::capture();
+    unlock::capture();
+
+    *lock.lock() += 1;
+
+    assert_eq!(roots(&unlock::drain()), 1);
+    assert_eq!(roots(&unlock::drain()), 1);
+    assert!(unlock::is_capturing());
+
+    *lock.lock() += 1;
+
+    assert_eq!(roots(&unlock::drain()), 2);
+    assert!(!unlock::is_capturing());
+    assert!(unlock::try_drain().is_err());
+}
@@ -0,0 +1,79 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::sync::Mutex;
+
+use unlock::{CaptureConfig, EventId, Outcome};
+
+/// Tests modify the global tracing context, so they can't run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn id(id: EventId) -> usize {
+    id.to_string().parse().unwrap()
+}
+
+#[test]
+fn failed_try_write_records_nothing_by_default() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::RwLock::new(0);
+
+    unlock::capture();
+
+    let guard = lock.write();
+    assert!(lock.try_write().is_none());
+    drop(guard);
+    *lock.write() += 1;
+
+    let events = unlock::drain();
+
+    let roots = events
+        .enters()
+        .iter()
+        .filter(|event| event.parent().is_none())
+        .collect::<Vec<_>>();
+
+    assert_eq!(roots.len(), 2);
+    assert!(roots
+        .iter()
+        .all(|event| event.outcome() == Outcome::Acquired));
+
+    // No identifiers were allocated for the failed attempt.
+    assert_eq!(id(roots[1].id()), id(roots[0].id()) + 2);
+
+    // Every leave belongs to one of the two acquisitions.
+    assert_eq!(events.enters().len(), 4);
+    assert_eq!(events.leaves().len(), 4);
+    assert!(events.leaves().iter().all(|leave| events
+        .enters()
+        .iter()
+        .any(|event| event.id() == leave.sibling())));
+}
+
+#[test]
+fn failed_try_lock_is_recorded_when_enabled() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::Mutex::new(0);
+
+    unlock::capture_with(CaptureConfig::new().record_failed_tries(true));
+
+    let guard = lock.lock();
+    assert!(lock.try_lock().is_none());
+    drop(guard);
+
+    let events = unlock::drain();
+
+    let outcomes = events
+        .enters()
+        .iter()
+        .filter(|event| event.parent().is_none())
+        .map(|event| event.outcome())
+        .collect::<Vec<_>>();
+
+    assert_eq!(outcomes, [Outcome::Acquired, Outcome::TryFailed]);
+
+    let id = events.enters()[0].lock();
+    assert_eq!(events.stats().locks[&id].acquisitions, 1);
+    assert_eq!(events.hold_times()[&id].len(), 1);
+    assert_eq!(events.wait_times()[&id].len(), 1);
+}
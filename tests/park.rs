@@ -0,0 +1,40 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::sync::Barrier;
+use std::thread;
+use std::time::Duration;
+
+use unlock::CaptureConfig;
+
+#[test]
+fn upgrades_and_failed_tries_are_not_waits() {
+    let lock = unlock::RwLock::new(0);
+    let barrier = Barrier::new(2);
+
+    unlock::capture_with(CaptureConfig::new().record_failed_tries(true));
+
+    // The write nested in the upgradable read is a hold, not a wait.
+    let mut guard = lock.upgradable_read();
+    guard.with_upgraded(|_| thread::sleep(Duration::from_millis(5)));
+    drop(guard);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            let _guard = lock.write();
+            barrier.wait();
+            thread::sleep(Duration::from_millis(5));
+        });
+
+        barrier.wait();
+        assert!(lock.try_write().is_none());
+        *lock.write() += 1;
+    });
+
+    let events = unlock::drain();
+    let id = events.enters()[0].lock();
+
+    // Only the upgradable read and the two writes waited, of which the
+    // write which found the lock held parked.
+    let rate = events.park_rate(id, Duration::from_millis(1));
+    assert!((rate - 1.0 / 3.0).abs() < f32::EPSILON, "{rate}");
+}
@@ -0,0 +1,60 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use unlock::CaptureConfig;
+
+/// Tests modify the global tracing context, so they can't run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn record(resolution: Duration, hold: Duration) -> unlock::Events {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    unlock::capture_with(CaptureConfig::new().resolution(resolution));
+
+    let lock = unlock::Mutex::new(0);
+
+    for _ in 0..3 {
+        let mut guard = lock.lock();
+        *guard += 1;
+        thread::sleep(hold);
+    }
+
+    unlock::drain()
+}
+
+#[test]
+fn timestamps_are_multiples_of_the_resolution() {
+    let resolution = Duration::from_micros(100);
+    let events = record(resolution, Duration::from_micros(250));
+
+    assert_eq!(events.enters().len(), 6);
+    assert_eq!(events.leaves().len(), 6);
+
+    let timestamps = events
+        .enters()
+        .iter()
+        .map(|event| event.timestamp())
+        .chain(events.leaves().iter().map(|leave| leave.timestamp()));
+
+    for timestamp in timestamps {
+        assert_eq!(timestamp.as_nanos() % resolution.as_nanos(), 0);
+    }
+}
+
+#[test]
+fn durations_are_kept_within_the_resolution() {
+    let resolution = Duration::from_millis(1);
+    let hold = Duration::from_millis(5);
+    let events = record(resolution, hold);
+
+    let holds = &events.hold_times()[&events.enters()[0].lock()];
+    assert_eq!(holds.len(), 3);
+
+    // Rounding both ends down loses less than one unit of resolution.
+    for &duration in holds {
+        assert!(duration + resolution > hold, "{duration:?}");
+    }
+}
@@ -0,0 +1,56 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::collections::HashSet;
+
+use unlock::{CaptureConfig, LockKind};
+
+#[test]
+fn evicted_sections_leave_no_orphans() {
+    let rwlock = unlock::RwLock::new(0);
+    let mutex = unlock::Mutex::new(0);
+
+    unlock::capture_with(CaptureConfig::new().max_events(8).ring_buffer(true));
+
+    let mut guard = rwlock.upgradable_read();
+
+    // Evict the upgradable read while it's still held.
+    for _ in 0..8 {
+        *mutex.lock() += 1;
+    }
+
+    // Nested in the evicted upgradable read.
+    guard.with_upgraded(|value| *value += 1);
+    drop(guard);
+
+    for _ in 0..2 {
+        *mutex.lock() += 1;
+    }
+
+    let events = unlock::drain();
+
+    assert!(events.dropped() > 0);
+    assert!(!events.is_empty());
+
+    let ids = events
+        .enters()
+        .iter()
+        .map(|event| event.id())
+        .collect::<HashSet<_>>();
+
+    for event in events.enters() {
+        if let Some(parent) = event.parent() {
+            assert!(ids.contains(&parent), "orphaned {event:?}");
+        }
+    }
+
+    for leave in events.leaves() {
+        assert!(ids.contains(&leave.sibling()), "orphaned {leave:?}");
+    }
+
+    // Every remaining section is a complete mutex section.
+    assert!(events
+        .enters()
+        .iter()
+        .all(|event| event.lock().kind() == LockKind::Mutex));
+    assert_eq!(events.leaves().len(), events.enters().len());
+}
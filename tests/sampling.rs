@@ -0,0 +1,46 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::collections::HashMap;
+
+use unlock::CaptureConfig;
+
+#[test]
+fn about_one_in_n_sections_are_sampled() {
+    const OPERATIONS: usize = 4000;
+
+    let lock = unlock::Mutex::new(0);
+
+    unlock::capture_with(CaptureConfig::new().sample_one_in(4));
+
+    for _ in 0..OPERATIONS {
+        *lock.lock() += 1;
+    }
+
+    let events = unlock::drain();
+
+    let mut children = HashMap::<_, Vec<_>>::new();
+    let mut roots = 0;
+
+    for event in events.enters() {
+        match event.parent() {
+            Some(parent) => children.entry(parent).or_default().push(event),
+            None => roots += 1,
+        }
+    }
+
+    // The expected number of samples is 1000, with a standard deviation of
+    // about 27.
+    assert!((800..1200).contains(&roots), "{roots}");
+
+    // Every sampled section keeps its acquiring event, and is released.
+    for event in events.enters() {
+        if event.parent().is_none() {
+            let children = &children[&event.id()];
+            assert_eq!(children.len(), 1);
+            assert_eq!(children[0].name(), "lock");
+        }
+    }
+
+    assert_eq!(children.len(), roots);
+    assert_eq!(events.leaves().len(), events.enters().len());
+}
@@ -0,0 +1,63 @@
+#![cfg(all(feature = "trace", feature = "parking_lot", feature = "serde"))]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use unlock::{Events, Mutex, RwLock};
+
+/// Render events with `html::write` into a directory of its own, returning
+/// the written document.
+fn render(events: &Events, dir: &str) -> Vec<u8> {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("trace.html");
+    unlock::html::write(&path, events).unwrap();
+    let html = fs::read(&path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    html
+}
+
+#[test]
+fn saved_trace_renders_like_the_original() {
+    let mutex = Arc::new(Mutex::with_key(Vec::new(), "values"));
+    let rwlock = Arc::new(RwLock::new(0));
+
+    unlock::capture();
+
+    let threads = (0..4)
+        .map(|n| {
+            let mutex = mutex.clone();
+            let rwlock = rwlock.clone();
+
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    unlock::with_context("worker", n.to_string(), || {
+                        mutex.lock().push(n);
+                    });
+
+                    *rwlock.write() += 1;
+                    let _ = *rwlock.read();
+                    thread::sleep(Duration::from_micros(100));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let original = unlock::drain();
+    assert!(!original.is_empty());
+
+    let json = serde_json::to_string(&original).unwrap();
+    let deserialized: Events = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(
+        render(&original, "serialize-original"),
+        render(&deserialized, "serialize-deserialized")
+    );
+}
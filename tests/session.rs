@@ -0,0 +1,61 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Tests modify the global tracing context, so they can't run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[test]
+fn drained_session_does_not_drain_reused_index() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::Mutex::new(0);
+
+    let stale = unlock::capture_session();
+    assert!(unlock::drain_session(stale).enters().is_empty());
+
+    let session = unlock::capture_session();
+    assert_ne!(session, stale);
+
+    *lock.lock() += 1;
+
+    assert!(unlock::drain_session(stale).enters().is_empty());
+    assert!(!unlock::drain_session(session).enters().is_empty());
+}
+
+#[test]
+fn events_buffered_before_the_session_baseline_are_left_out() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::Mutex::new(0);
+
+    unlock::capture();
+
+    // Recorded on another thread below the baseline of the session, and
+    // still buffered for the capture when the session is drained.
+    thread::scope(|s| {
+        s.spawn(|| *lock.lock() += 1);
+    });
+
+    thread::sleep(Duration::from_millis(1));
+    let session = unlock::capture_session();
+    *lock.lock() += 1;
+
+    let events = unlock::drain_session(session);
+    assert_eq!(events.enters().len(), 2);
+    assert_eq!(events.leaves().len(), 2);
+
+    let timestamps = events
+        .enters()
+        .iter()
+        .map(|event| event.timestamp())
+        .chain(events.leaves().iter().map(|leave| leave.timestamp()));
+
+    for timestamp in timestamps {
+        assert!(timestamp < Duration::from_secs(60), "{timestamp:?}");
+    }
+
+    assert_eq!(unlock::drain().enters().len(), 4);
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "serde")]
+
+use std::time::Duration;
+
+use unlock::{Events, LockId};
+
+/// Build a capture of a single lock, where acquisition `k` out of `1..=10`
+/// waits for `k` nanoseconds and holds the lock for `10 * k` nanoseconds,
+/// followed by an attempt which times out after waiting for 50 nanoseconds.
+fn events() -> (Events, LockId) {
+    let mut enters = Vec::new();
+    let mut leaves = Vec::new();
+
+    let mut section = |id: u64, start: u64, wait: u64, hold: u64, outcome: &str| {
+        let contended = wait > 0;
+
+        enters.push(format!(
+            r#"{{"id":{id},"timestamp":{start},"thread_index":0,"parent":null,"name":"lock","type_name":"u32","lock":1,"outcome":"{outcome}","contended":{contended}}}"#
+        ));
+        enters.push(format!(
+            r#"{{"id":{},"timestamp":{start},"thread_index":0,"parent":{id},"name":"acquire","type_name":"u32","lock":1}}"#,
+            id + 1
+        ));
+        leaves.push(format!(
+            r#"{{"sibling":{},"thread_index":0,"timestamp":{}}}"#,
+            id + 1,
+            start + wait
+        ));
+        leaves.push(format!(
+            r#"{{"sibling":{id},"thread_index":0,"timestamp":{}}}"#,
+            start + wait + hold
+        ));
+    };
+
+    for k in 1..=10 {
+        section(k * 2, k * 1000, k, 10 * k, "Acquired");
+    }
+
+    section(100, 20000, 50, 0, "TimedOut");
+
+    let json = format!(
+        r#"{{"enters":[{}],"leaves":[{}]}}"#,
+        enters.join(","),
+        leaves.join(",")
+    );
+
+    let events: Events = serde_json::from_str(&json).unwrap();
+    let lock = events.enters()[0].lock();
+    (events, lock)
+}
+
+#[test]
+fn summary_of_known_durations() {
+    let (events, lock) = events();
+    let summary = events.summary();
+
+    assert_eq!(summary.locks().collect::<Vec<_>>(), [lock]);
+
+    assert_eq!(summary.hold_count(lock), 10);
+    assert_eq!(summary.hold_total(lock), Duration::from_nanos(550));
+    assert_eq!(summary.hold_mean(lock), Some(Duration::from_nanos(55)));
+    assert_eq!(summary.hold_max(lock), Some(Duration::from_nanos(100)));
+    assert_eq!(
+        summary.hold_percentile(lock, 0.0),
+        Some(Duration::from_nanos(10))
+    );
+    assert_eq!(
+        summary.hold_percentile(lock, 50.0),
+        Some(Duration::from_nanos(50))
+    );
+    assert_eq!(
+        summary.hold_percentile(lock, 90.0),
+        Some(Duration::from_nanos(90))
+    );
+    assert_eq!(
+        summary.hold_percentile(lock, 99.0),
+        Some(Duration::from_nanos(100))
+    );
+
+    // The attempt which timed out waited, but held nothing.
+    assert_eq!(summary.wait_count(lock), 11);
+    assert_eq!(summary.wait_total(lock), Duration::from_nanos(105));
+    assert_eq!(summary.wait_mean(lock), Some(Duration::from_nanos(9)));
+    assert_eq!(summary.wait_max(lock), Some(Duration::from_nanos(50)));
+    assert_eq!(
+        summary.wait_percentile(lock, 50.0),
+        Some(Duration::from_nanos(6))
+    );
+    assert_eq!(
+        summary.wait_percentile(lock, 100.0),
+        Some(Duration::from_nanos(50))
+    );
+}
+
+#[test]
+fn stats_of_known_durations() {
+    let (events, lock) = events();
+    let stats = events.stats();
+
+    let expected = unlock::LockStats {
+        acquisitions: 10,
+        contended: 10,
+        hold_total: Duration::from_nanos(550),
+        hold_mean: Duration::from_nanos(55),
+        hold_max: Duration::from_nanos(100),
+        wait_total: Duration::from_nanos(105),
+        wait_mean: Duration::from_nanos(9),
+        wait_max: Duration::from_nanos(50),
+    };
+
+    assert_eq!(stats.locks[&lock], expected);
+    assert_eq!(stats.types["u32"], expected);
+}
@@ -0,0 +1,76 @@
+#![cfg(all(feature = "trace", feature = "parking_lot", feature = "test-util"))]
+
+use std::sync::{Barrier, Mutex};
+use std::thread;
+
+/// Tests modify the global tracing context, so they can't run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn constant_index() -> usize {
+    7
+}
+
+/// Run `threads` threads which each record an acquisition, where `overlap`
+/// decides whether they're all alive at the same time.
+fn record(threads: usize, overlap: bool) -> unlock::Events {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    unlock::reset_for_test();
+    unlock::set_thread_index_source(constant_index);
+    unlock::capture();
+    spawn(threads, overlap);
+    unlock::clear_thread_index_source();
+    unlock::drain()
+}
+
+fn spawn(threads: usize, overlap: bool) {
+    let lock = unlock::Mutex::new(0);
+    let barrier = Barrier::new(if overlap { threads } else { 1 });
+
+    thread::scope(|s| {
+        for _ in 0..threads {
+            let handle = s.spawn(|| {
+                *lock.lock() += 1;
+                barrier.wait();
+            });
+
+            if !overlap {
+                handle.join().unwrap();
+            }
+        }
+    });
+}
+
+#[test]
+fn concurrent_threads_with_the_same_index_conflict() {
+    let events = record(2, true);
+    assert_eq!(events.thread_index_conflicts(), 1);
+    assert!(events
+        .enters()
+        .iter()
+        .all(|event| event.thread_index() == 7));
+}
+
+#[test]
+fn index_handed_over_between_threads_does_not_conflict() {
+    let events = record(3, false);
+    assert_eq!(events.thread_index_conflicts(), 0);
+}
+
+#[test]
+fn sessions_only_count_their_own_conflicts() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    unlock::reset_for_test();
+    unlock::set_thread_index_source(constant_index);
+    unlock::capture();
+    spawn(2, true);
+
+    let session = unlock::capture_session();
+    let events = unlock::drain_session(session);
+    assert_eq!(events.thread_index_conflicts(), 0);
+
+    unlock::clear_thread_index_source();
+    let events = unlock::drain();
+    assert_eq!(events.thread_index_conflicts(), 1);
+}
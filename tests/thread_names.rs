@@ -0,0 +1,23 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::thread;
+
+#[test]
+fn names_are_captured_again_by_later_captures() {
+    let lock = unlock::Mutex::new(0);
+    let lock = &lock;
+
+    thread::scope(|s| {
+        thread::Builder::new()
+            .name(String::from("worker"))
+            .spawn_scoped(s, || {
+                for _ in 0..2 {
+                    unlock::capture();
+                    *lock.lock() += 1;
+                    let events = unlock::drain();
+                    assert_eq!(events.thread_name(&events.enters()[0]), Some("worker"));
+                }
+            })
+            .unwrap();
+    });
+}
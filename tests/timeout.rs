@@ -0,0 +1,48 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::thread;
+use std::time::Duration;
+
+use unlock::Outcome;
+
+#[test]
+fn try_lock_for_times_out() {
+    const TIMEOUT: Duration = Duration::from_millis(10);
+
+    let lock = unlock::Mutex::new(0);
+    let other = unlock::Mutex::new(0);
+
+    unlock::capture();
+
+    let guard = lock.lock();
+
+    thread::scope(|s| {
+        s.spawn(|| assert!(lock.try_lock_for(TIMEOUT).is_none()));
+    });
+
+    drop(guard);
+
+    *lock.try_lock_for(TIMEOUT).unwrap() += 1;
+    *other.lock() += 1;
+
+    let events = unlock::drain();
+
+    let timed = events
+        .enters()
+        .iter()
+        .filter(|event| event.parent().is_none() && event.timeout().is_some())
+        .collect::<Vec<_>>();
+
+    assert_eq!(timed.len(), 2);
+    assert_eq!(timed[0].outcome(), Outcome::TimedOut);
+    assert_eq!(timed[1].outcome(), Outcome::Acquired);
+    assert!(timed.iter().all(|event| event.timeout() == Some(TIMEOUT)));
+
+    let id = timed[0].lock();
+    assert_eq!(events.deadline_miss_rate(id), Some(0.5));
+    assert_eq!(
+        events.deadline_miss_rate(events.enters().last().unwrap().lock()),
+        None
+    );
+    assert_eq!(events.leaves().len(), events.enters().len());
+}
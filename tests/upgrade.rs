@@ -0,0 +1,127 @@
+#![cfg(all(feature = "trace", feature = "parking_lot"))]
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use unlock::{EventId, Events, RwLockUpgradableReadGuard};
+
+/// Tests modify the global tracing context, so they can't run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// The names of the children of every root event, in order.
+fn sections(events: &Events) -> Vec<Vec<&'static str>> {
+    let mut children = HashMap::<_, Vec<_>>::new();
+
+    for event in events.enters() {
+        if let Some(parent) = event.parent() {
+            children.entry(parent).or_default().push(event.name());
+        }
+    }
+
+    events
+        .enters()
+        .iter()
+        .filter(|event| event.parent().is_none())
+        .map(|event| children.remove(&event.id()).unwrap_or_default())
+        .collect()
+}
+
+fn closed_at(events: &Events, id: EventId) -> Duration {
+    let leave = events.leaves().iter().find(|leave| leave.sibling() == id);
+    leave.expect("event was never left").timestamp()
+}
+
+#[test]
+fn upgrade_leaves_and_enters_a_write_section() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::RwLock::new(0);
+
+    unlock::capture();
+
+    let upgradable = lock.upgradable_read();
+    let mut write = RwLockUpgradableReadGuard::upgrade(upgradable);
+    *write += 1;
+    drop(write);
+
+    let events = unlock::drain();
+
+    assert_eq!(sections(&events), [["upgradable_read"], ["write"]]);
+
+    let roots = events
+        .enters()
+        .iter()
+        .filter(|event| event.parent().is_none())
+        .collect::<Vec<_>>();
+
+    // The upgradable section is closed before the write section is opened.
+    assert!(closed_at(&events, roots[0].id()) <= roots[1].timestamp());
+    assert!(closed_at(&events, roots[1].id()) >= roots[1].timestamp());
+    assert_eq!(events.leaves().len(), events.enters().len());
+}
+
+#[test]
+fn with_upgraded_records_a_nested_write() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::RwLock::new(0);
+
+    unlock::capture();
+
+    let mut upgradable = lock.upgradable_read();
+    upgradable.with_upgraded(|value| *value += 1);
+    assert_eq!(*upgradable, 1);
+    drop(upgradable);
+
+    let events = unlock::drain();
+
+    assert_eq!(sections(&events), [["upgradable_read", "write"]]);
+
+    let write = events
+        .enters()
+        .iter()
+        .find(|event| event.parent().is_some() && event.name() == "write")
+        .unwrap();
+
+    let root = write.parent().unwrap();
+    assert!(closed_at(&events, write.id()) <= closed_at(&events, root));
+    assert_eq!(events.leaves().len(), events.enters().len());
+}
+
+#[test]
+fn with_upgraded_downgrades_on_panic() {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+
+    let lock = unlock::RwLock::new(0);
+
+    unlock::capture();
+
+    let mut upgradable = lock.upgradable_read();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        upgradable.with_upgraded(|value| {
+            *value += 1;
+            panic!("boom");
+        })
+    }));
+
+    assert!(result.is_err());
+
+    // The guard is downgraded back and can still be used.
+    assert_eq!(*upgradable, 1);
+    upgradable.with_upgraded(|value| *value += 1);
+    drop(upgradable);
+
+    *lock.write() += 1;
+
+    let events = unlock::drain();
+
+    assert_eq!(
+        sections(&events),
+        [vec!["upgradable_read", "write", "write"], vec!["write"]]
+    );
+    assert_eq!(events.leaves().len(), events.enters().len());
+    assert_eq!(*lock.read(), 3);
+}